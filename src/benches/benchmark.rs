@@ -3,7 +3,9 @@ extern crate criterion;
 extern crate evtx;
 
 use criterion::Criterion;
-use evtx::EvtxParser;
+use evtx::{EvtxParser, NullOutput, ParserSettings};
+use std::io;
+use std::sync::Arc;
 
 // first chunk has 90 records
 fn process_90_records(buffer: &'static [u8]) {
@@ -32,6 +34,20 @@ fn process_90_records_json(buffer: &'static [u8]) {
     }
 }
 
+// Parses the records without formatting them, to measure binxml decoding alone.
+fn process_90_records_null_output(buffer: &'static [u8]) {
+    let mut parser = EvtxParser::from_buffer(buffer.to_vec()).unwrap();
+    let settings = Arc::new(ParserSettings::default());
+    let mut chunk = parser.chunks().next().unwrap().unwrap();
+    let mut chunk = chunk.parse(settings.clone()).unwrap();
+
+    for record in chunk.iter().take(90) {
+        let mut output = NullOutput::with_writer(io::sink(), &settings);
+        record.unwrap().into_output(&mut output).unwrap();
+        output.into_writer().unwrap();
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let evtx_file = include_bytes!("../../samples/security.evtx");
     // ~11ms before strings cache
@@ -44,6 +60,10 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("read 90 records json", move |b| {
         b.iter(|| process_90_records_json(evtx_file))
     });
+
+    c.bench_function("read 90 records null output", move |b| {
+        b.iter(|| process_90_records_null_output(evtx_file))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,19 +1,20 @@
-use crate::err::{EvtxError, Result};
+use crate::err::{EvtxError, Result, SerializationError};
 
-use crate::binxml::value_variant::BinXmlValue;
+use crate::binxml::value_variant::{BinXmlValue, ValueFormatter};
 use crate::model::deserialized::{
     BinXMLDeserializedTokens, BinXmlTemplateRef, TemplateSubstitutionDescriptor,
 };
-use crate::model::xml::{XmlElementBuilder, XmlModel, XmlPIBuilder};
+use crate::model::xml::{XmlElement, XmlElementBuilder, XmlModel, XmlPIBuilder};
 use crate::xml_output::BinXmlOutput;
 use log::{debug, trace, warn};
+use serde_json::json;
 use std::borrow::{BorrowMut, Cow};
 
 use std::mem;
 
 use crate::binxml::name::{BinXmlName, BinXmlNameRef};
 use crate::binxml::tokens::read_template_definition;
-use crate::EvtxChunk;
+use crate::{EvtxChunk, MessageResolver, ValueTransform};
 use std::io::{Cursor, Seek, SeekFrom};
 
 pub fn parse_tokens<'a, T: BinXmlOutput>(
@@ -23,15 +24,29 @@ pub fn parse_tokens<'a, T: BinXmlOutput>(
 ) -> Result<()> {
     let expanded_tokens = expand_templates(tokens, chunk)?;
     let record_model = create_record_model(expanded_tokens, chunk)?;
+    let value_transform = chunk.settings.get_value_transform();
+    let value_formatter = chunk.settings.get_value_formatter();
+    let message_resolver = chunk.settings.get_message_resolver();
+    let mut message_values = MessageValues::default();
 
     visitor.visit_start_of_stream()?;
 
     let mut stack = vec![];
+    let mut closed_elements = 0;
 
     for owned_token in record_model {
         match owned_token {
-            XmlModel::OpenElement(open_element) => {
+            XmlModel::OpenElement(mut open_element) => {
+                if let Some(formatter) = value_formatter {
+                    format_attributes(formatter, &mut open_element);
+                }
+                if let Some(transform) = value_transform {
+                    transform_attributes(transform, &stack, &mut open_element);
+                }
                 stack.push(open_element);
+                if message_resolver.is_some() {
+                    message_values.open_element(&stack);
+                }
                 visitor.visit_open_start_element(stack.last().ok_or({
                     EvtxError::FailedToCreateRecordModel(
                         "Invalid parser state - expected stack to be non-empty",
@@ -39,14 +54,35 @@ pub fn parse_tokens<'a, T: BinXmlOutput>(
                 })?)?;
             }
             XmlModel::CloseElement => {
-                let close_element = stack.pop().ok_or({
-                    EvtxError::FailedToCreateRecordModel(
-                        "Invalid parser state - expected stack to be non-empty",
-                    )
-                })?;
+                if let Some(resolver) = message_resolver {
+                    if stack.len() == 1 {
+                        message_values.visit_message(resolver, visitor)?;
+                    }
+                }
+                let close_element = stack
+                    .pop()
+                    .ok_or(SerializationError::UnbalancedStack { closed_elements })?;
+                closed_elements += 1;
                 visitor.visit_close_element(&close_element)?
             }
-            XmlModel::Value(s) => visitor.visit_characters(s)?,
+            XmlModel::Value(s) => {
+                let s = match value_formatter {
+                    Some(formatter) => BinXmlValue::format_with(s, formatter),
+                    None => s,
+                };
+                let s = match value_transform {
+                    Some(transform) => transform_value(transform, &value_path(&stack), s),
+                    None => s,
+                };
+                if message_resolver.is_some() {
+                    message_values.value(&stack, &s);
+                }
+                visitor.visit_characters(s)?
+            }
+            XmlModel::CData(data) => visitor.visit_cdata_section(data)?,
+            XmlModel::CharRef(value) => {
+                visitor.visit_character_reference(Cow::Owned(value.to_string()))?
+            }
             XmlModel::EndOfStream => {}
             XmlModel::StartOfStream => {}
             XmlModel::PI(pi) => visitor.visit_processing_instruction(&pi)?,
@@ -54,11 +90,174 @@ pub fn parse_tokens<'a, T: BinXmlOutput>(
         };
     }
 
+    if !stack.is_empty() {
+        return Err(SerializationError::UnexpectedEof {
+            open_elements: stack.len(),
+        }
+        .into());
+    }
+
     visitor.visit_end_of_stream()?;
 
     Ok(())
 }
 
+/// The parts of a record which are passed to a `MessageResolver`, collected while rendering it.
+#[derive(Default)]
+struct MessageValues {
+    provider: Option<String>,
+    event_id: Option<u32>,
+    values: Vec<String>,
+    /// The depth of the `EventData` (or `UserData`) field being rendered, if any.
+    field_depth: Option<usize>,
+    has_rendering_info: bool,
+}
+
+impl MessageValues {
+    /// Called with the stack of open elements, once an element was pushed to it.
+    fn open_element(&mut self, stack: &[XmlElement]) {
+        let names: Vec<&str> = stack.iter().map(|e| e.name.as_str()).collect();
+
+        match names.as_slice() {
+            ["Event", "System", "Provider"] => {
+                self.provider = stack[2].attribute_str("Name").map(Cow::into_owned);
+            }
+            ["Event", "RenderingInfo"] => self.has_rendering_info = true,
+            // `UserData` has a single element named after the event, which holds the fields.
+            ["Event", "EventData", _] | ["Event", "UserData", _, _] => {
+                self.values.push(String::new());
+                self.field_depth = Some(names.len());
+            }
+            _ => {}
+        }
+
+        if matches!(self.field_depth, Some(depth) if names.len() < depth) {
+            self.field_depth = None;
+        }
+    }
+
+    fn value(&mut self, stack: &[XmlElement], value: &BinXmlValue) {
+        let in_field = match self.field_depth {
+            Some(depth) => stack.len() >= depth,
+            None => false,
+        };
+
+        if in_field {
+            if let Some(field) = self.values.last_mut() {
+                field.push_str(&value.as_cow_str());
+            }
+        } else if stack.len() == 3
+            && stack[0].name.as_str() == "Event"
+            && stack[1].name.as_str() == "System"
+            && stack[2].name.as_str() == "EventID"
+        {
+            self.event_id = value.as_cow_str().trim().parse().ok();
+        }
+    }
+
+    /// Writes `RenderingInfo/Message`, just before the root element is closed.
+    fn visit_message<T: BinXmlOutput>(
+        &self,
+        resolver: &MessageResolver,
+        visitor: &mut T,
+    ) -> Result<()> {
+        if self.has_rendering_info {
+            return Ok(());
+        }
+
+        let (provider, event_id) = match (&self.provider, self.event_id) {
+            (Some(provider), Some(event_id)) => (provider, event_id),
+            _ => return Ok(()),
+        };
+
+        let message = match resolver(provider, event_id, &self.values) {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+
+        let rendering_info = XmlElement {
+            name: Cow::Owned(BinXmlName::from_str("RenderingInfo")),
+            attributes: vec![],
+        };
+        let message_element = XmlElement {
+            name: Cow::Owned(BinXmlName::from_str("Message")),
+            attributes: vec![],
+        };
+
+        visitor.visit_open_start_element(&rendering_info)?;
+        visitor.visit_open_start_element(&message_element)?;
+        visitor.visit_characters(Cow::Owned(BinXmlValue::StringType(message)))?;
+        visitor.visit_close_element(&message_element)?;
+        visitor.visit_close_element(&rendering_info)?;
+
+        Ok(())
+    }
+}
+
+/// The path of the innermost element of `stack`, as passed to a `ValueTransform`.
+fn value_path(stack: &[XmlElement]) -> String {
+    let names: Vec<Cow<str>> = stack
+        .iter()
+        .map(|element| {
+            let name = element.name.as_str();
+
+            match element.attribute_str("Name") {
+                Some(data_name) if name == "Data" => data_name,
+                _ => Cow::Borrowed(name),
+            }
+        })
+        .collect();
+
+    names.join("/")
+}
+
+/// Replaces `value` with the result of `transform`, if any.
+fn transform_value<'v, 'a>(
+    transform: &ValueTransform,
+    path: &str,
+    value: Cow<'v, BinXmlValue<'a>>,
+) -> Cow<'v, BinXmlValue<'a>> {
+    let replacement = transform(path, &value.as_cow_str()).map(Cow::into_owned);
+
+    match replacement {
+        Some(replacement) => Cow::Owned(BinXmlValue::StringType(replacement)),
+        None => value,
+    }
+}
+
+/// Writes the attribute values of `element` using `formatter`.
+fn format_attributes(formatter: &dyn ValueFormatter, element: &mut XmlElement) {
+    for attribute in element.attributes.iter_mut() {
+        let value = mem::replace(&mut attribute.value, Cow::Owned(BinXmlValue::NullType));
+        attribute.value = BinXmlValue::format_with(value, formatter);
+    }
+}
+
+/// Applies `transform` to the attributes of `element`, which is about to be pushed on `stack`.
+fn transform_attributes(
+    transform: &ValueTransform,
+    stack: &[XmlElement],
+    element: &mut XmlElement,
+) {
+    let element_path = value_path(std::slice::from_ref(element));
+    let parent_path = value_path(stack);
+
+    for attribute in element.attributes.iter_mut() {
+        let path = if parent_path.is_empty() {
+            format!("{}/@{}", element_path, attribute.name.as_str())
+        } else {
+            format!(
+                "{}/{}/@{}",
+                parent_path,
+                element_path,
+                attribute.name.as_str()
+            )
+        };
+        let value = mem::replace(&mut attribute.value, Cow::Owned(BinXmlValue::NullType));
+        attribute.value = transform_value(transform, &path, value);
+    }
+}
+
 pub fn create_record_model<'a>(
     tokens: Vec<Cow<'a, BinXMLDeserializedTokens<'a>>>,
     chunk: &'a EvtxChunk<'a>,
@@ -98,17 +297,15 @@ pub fn create_record_model<'a>(
                     Some(builder) => model.push(XmlModel::OpenElement(builder.finish()?)),
                 };
             }
-            Cow::Owned(BinXMLDeserializedTokens::CDATASection)
-            | Cow::Borrowed(BinXMLDeserializedTokens::CDATASection) => {
-                return Err(EvtxError::FailedToCreateRecordModel(
-                    "Unimplemented - CDATA",
-                ));
+            Cow::Owned(BinXMLDeserializedTokens::CDATASection(data)) => {
+                model.push(XmlModel::CData(Cow::Owned(data)))
             }
-            Cow::Owned(BinXMLDeserializedTokens::CharRef)
-            | Cow::Borrowed(BinXMLDeserializedTokens::CharRef) => {
-                return Err(EvtxError::FailedToCreateRecordModel(
-                    "Unimplemented - CharacterReference",
-                ));
+            Cow::Borrowed(BinXMLDeserializedTokens::CDATASection(data)) => {
+                model.push(XmlModel::CData(Cow::Borrowed(data)))
+            }
+            Cow::Owned(BinXMLDeserializedTokens::CharRef(value))
+            | Cow::Borrowed(&BinXMLDeserializedTokens::CharRef(value)) => {
+                model.push(XmlModel::CharRef(value))
             }
             Cow::Owned(BinXMLDeserializedTokens::EntityRef(ref entity))
             | Cow::Borrowed(BinXMLDeserializedTokens::EntityRef(ref entity)) => {
@@ -240,7 +437,7 @@ pub fn create_record_model<'a>(
     Ok(model)
 }
 
-fn expand_string_ref<'a>(
+pub(crate) fn expand_string_ref<'a>(
     string_ref: &BinXmlNameRef,
     chunk: &'a EvtxChunk<'a>,
 ) -> Result<Cow<'a, BinXmlName>> {
@@ -251,7 +448,8 @@ fn expand_string_ref<'a>(
             let cursor_ref = cursor.borrow_mut();
             try_seek!(cursor_ref, string_ref.offset, "Cache missed string")?;
 
-            let string = BinXmlName::from_stream(cursor_ref)?;
+            let string =
+                BinXmlName::from_stream(cursor_ref, chunk.settings.get_on_invalid_utf16())?;
             Ok(Cow::Owned(string))
         }
     }
@@ -316,8 +514,12 @@ fn expand_template<'a>(
         let mut cursor = Cursor::new(chunk.data);
 
         let _ = cursor.seek(SeekFrom::Start(u64::from(template.template_def_offset)));
-        let template_def =
-            read_template_definition(&mut cursor, Some(chunk), chunk.settings.get_ansi_codec())?;
+        let template_def = read_template_definition(
+            &mut cursor,
+            Some(chunk),
+            chunk.settings.get_ansi_codec(),
+            chunk.settings.get_on_invalid_utf16(),
+        )?;
 
         for token in template_def.tokens {
             if let BinXMLDeserializedTokens::Substitution(ref substitution_descriptor) = token {
@@ -380,3 +582,265 @@ pub fn expand_templates<'a>(
 
     Ok(stack)
 }
+
+/// The outcome of looking for a record's `System/EventID` without rendering it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EventIdScan {
+    Found(u32),
+    /// The record has no `System/EventID` element.
+    Missing,
+    /// The record could not be scanned (ex. a template missing from the cache, or a malformed id).
+    Unknown,
+}
+
+/// Walks a token in document order, expanding templates and nested BinXml in place,
+/// until `visit` returns `Some`.
+///
+/// Returns `on_error()` if a template can't be read.
+fn walk_token<R>(
+    token: &BinXMLDeserializedTokens,
+    chunk: &EvtxChunk,
+    on_error: &impl Fn() -> R,
+    visit: &mut impl FnMut(&BinXMLDeserializedTokens) -> Option<R>,
+) -> Option<R> {
+    match token {
+        BinXMLDeserializedTokens::Value(BinXmlValue::BinXmlType(tokens)) => {
+            for token in tokens {
+                if let Some(result) = walk_token(token, chunk, on_error, visit) {
+                    return Some(result);
+                }
+            }
+        }
+        BinXMLDeserializedTokens::TemplateInstance(template) => {
+            let template_def = match chunk
+                .template_table
+                .get_template(template.template_def_offset)
+            {
+                Some(template_def) => Cow::Borrowed(template_def),
+                None => {
+                    // Like `expand_template`, read templates which are missing from the cache.
+                    let mut cursor = Cursor::new(chunk.data);
+                    let _ = cursor.seek(SeekFrom::Start(u64::from(template.template_def_offset)));
+                    match read_template_definition(
+                        &mut cursor,
+                        None,
+                        chunk.settings.get_ansi_codec(),
+                        chunk.settings.get_on_invalid_utf16(),
+                    ) {
+                        Ok(template_def) => Cow::Owned(template_def),
+                        Err(_) => return Some(on_error()),
+                    }
+                }
+            };
+
+            for token in template_def.tokens.iter() {
+                let token = match token {
+                    BinXMLDeserializedTokens::Substitution(descriptor) if descriptor.ignore => {
+                        continue
+                    }
+                    BinXMLDeserializedTokens::Substitution(descriptor) => {
+                        match template
+                            .substitution_array
+                            .get(descriptor.substitution_index as usize)
+                        {
+                            Some(value) => value,
+                            None => continue,
+                        }
+                    }
+                    token => token,
+                };
+
+                if let Some(result) = walk_token(token, chunk, on_error, visit) {
+                    return Some(result);
+                }
+            }
+        }
+        token => return visit(token),
+    }
+
+    None
+}
+
+/// Finds the `Event/System/EventID` of a record, by walking its tokens and templates in place.
+///
+/// This avoids expanding the templates and building the record model, and stops as soon as
+/// the id is found, which is early in the `System` block.
+pub(crate) fn scan_event_id(tokens: &[BinXMLDeserializedTokens], chunk: &EvtxChunk) -> EventIdScan {
+    let mut scanner = EventIdScanner {
+        chunk,
+        path: vec![],
+        in_start_tag: false,
+    };
+
+    for token in tokens {
+        let scan = walk_token(token, chunk, &|| EventIdScan::Unknown, &mut |token| {
+            scanner.visit(token)
+        });
+        if let Some(scan) = scan {
+            return scan;
+        }
+    }
+
+    EventIdScan::Missing
+}
+
+struct EventIdScanner<'c> {
+    chunk: &'c EvtxChunk<'c>,
+    path: Vec<String>,
+    in_start_tag: bool,
+}
+
+impl<'c> EventIdScanner<'c> {
+    fn is_in_event_id(&self) -> bool {
+        self.path.len() == 3
+            && self.path[0] == "Event"
+            && self.path[1] == "System"
+            && self.path[2] == "EventID"
+    }
+
+    /// Returns `Some` once the scan is over.
+    fn visit(&mut self, token: &BinXMLDeserializedTokens) -> Option<EventIdScan> {
+        match token {
+            BinXMLDeserializedTokens::OpenStartElement(element) => {
+                match expand_string_ref(&element.name, self.chunk) {
+                    Ok(name) => self.path.push(name.as_str().to_owned()),
+                    Err(_) => return Some(EventIdScan::Unknown),
+                }
+                self.in_start_tag = true;
+            }
+            BinXMLDeserializedTokens::CloseStartElement => self.in_start_tag = false,
+            BinXMLDeserializedTokens::CloseEmptyElement
+            | BinXMLDeserializedTokens::CloseElement => {
+                self.in_start_tag = false;
+                self.path.pop();
+                // Once `System` is closed, the id will not appear anymore.
+                if self.path.len() == 1 && self.path[0] == "Event" {
+                    return Some(EventIdScan::Missing);
+                }
+            }
+            BinXMLDeserializedTokens::Value(value)
+                if !self.in_start_tag && self.is_in_event_id() =>
+            {
+                return Some(match value.as_cow_str().trim().parse::<u32>() {
+                    Ok(event_id) => EventIdScan::Found(event_id),
+                    Err(_) => EventIdScan::Unknown,
+                });
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+/// Builds the `Event/System` element of a record, by walking its tokens and templates in place.
+///
+/// The result has the layout of `JsonOutput` with the default settings, but only contains
+/// `System`, and is built without expanding the templates or rendering the rest of the record.
+/// Returns `None` if the record could not be scanned.
+pub(crate) fn scan_system(
+    tokens: &[BinXMLDeserializedTokens],
+    chunk: &EvtxChunk,
+) -> Option<serde_json::Value> {
+    let mut scanner = SystemScanner {
+        chunk,
+        path: vec![],
+        in_start_tag: false,
+        attribute: None,
+        system: serde_json::Map::new(),
+    };
+
+    for token in tokens {
+        match walk_token(token, chunk, &|| false, &mut |token| scanner.visit(token)) {
+            Some(true) => break,
+            Some(false) => return None,
+            None => {}
+        }
+    }
+
+    Some(json!({"Event": {"System": scanner.system}}))
+}
+
+struct SystemScanner<'c> {
+    chunk: &'c EvtxChunk<'c>,
+    path: Vec<String>,
+    in_start_tag: bool,
+    /// The name of the attribute whose value comes next.
+    attribute: Option<String>,
+    system: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<'c> SystemScanner<'c> {
+    /// Returns `Some(true)` once `System` was closed, and `Some(false)` if the scan failed.
+    fn visit(&mut self, token: &BinXMLDeserializedTokens) -> Option<bool> {
+        match token {
+            BinXMLDeserializedTokens::OpenStartElement(element) => {
+                match expand_string_ref(&element.name, self.chunk) {
+                    Ok(name) => self.path.push(name.as_str().to_owned()),
+                    Err(_) => return Some(false),
+                }
+                self.in_start_tag = true;
+                self.attribute = None;
+
+                if let Some(name) = self.system_child() {
+                    let name = name.to_owned();
+                    self.system.entry(name).or_insert(serde_json::Value::Null);
+                }
+            }
+            BinXMLDeserializedTokens::Attribute(attribute) => {
+                match expand_string_ref(&attribute.name, self.chunk) {
+                    Ok(name) => self.attribute = Some(name.as_str().to_owned()),
+                    Err(_) => return Some(false),
+                }
+            }
+            BinXMLDeserializedTokens::CloseStartElement => {
+                self.in_start_tag = false;
+                self.attribute = None;
+            }
+            BinXMLDeserializedTokens::CloseEmptyElement
+            | BinXMLDeserializedTokens::CloseElement => {
+                self.in_start_tag = false;
+                self.attribute = None;
+                self.path.pop();
+                if self.path.len() == 1 && self.path[0] == "Event" {
+                    return Some(true);
+                }
+            }
+            BinXMLDeserializedTokens::Value(value) => {
+                let name = match self.system_child() {
+                    Some(name) => name.to_owned(),
+                    None => return None,
+                };
+                let value = serde_json::Value::from(value);
+                let element = self.system.entry(name).or_insert(serde_json::Value::Null);
+
+                match (self.in_start_tag, &self.attribute) {
+                    (true, Some(attribute)) if !value.is_null() => {
+                        if !element.is_object() {
+                            *element = json!({});
+                        }
+                        element["#attributes"][attribute] = value;
+                    }
+                    (true, _) => {}
+                    (false, _) => match element {
+                        serde_json::Value::Object(object) => {
+                            object.insert("#text".to_owned(), value);
+                        }
+                        element => *element = value,
+                    },
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    /// The name of the child of `Event/System` we are in, if any.
+    fn system_child(&self) -> Option<&str> {
+        match self.path.as_slice() {
+            [event, system, child] if event == "Event" && system == "System" => Some(child),
+            _ => None,
+        }
+    }
+}
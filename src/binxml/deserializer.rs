@@ -6,7 +6,8 @@ use log::trace;
 use std::io::{Seek, SeekFrom};
 
 use crate::binxml::tokens::{
-    read_open_start_element, read_processing_instruction_data, read_processing_instruction_target,
+    read_cdata_section, read_char_ref, read_open_start_element, read_processing_instruction_data,
+    read_processing_instruction_target,
 };
 use crate::binxml::value_variant::BinXmlValue;
 
@@ -19,6 +20,7 @@ use crate::{
 };
 
 use crate::evtx_chunk::EvtxChunk;
+use crate::utils::OnInvalidUtf16;
 use encoding::EncodingRef;
 
 use std::io::Cursor;
@@ -32,6 +34,7 @@ pub struct IterTokens<'a> {
     eof: bool,
     is_inside_substitution: bool,
     ansi_codec: EncodingRef,
+    on_invalid_utf16: OnInvalidUtf16,
 }
 
 pub struct BinXmlDeserializer<'a> {
@@ -41,6 +44,7 @@ pub struct BinXmlDeserializer<'a> {
     // if called from substitution token with value type: Binary XML (0x21)
     is_inside_substitution: bool,
     ansi_codec: EncodingRef,
+    on_invalid_utf16: OnInvalidUtf16,
 }
 
 impl<'a> BinXmlDeserializer<'a> {
@@ -50,6 +54,7 @@ impl<'a> BinXmlDeserializer<'a> {
         chunk: Option<&'a EvtxChunk<'a>>,
         is_inside_substitution: bool,
         ansi_codec: EncodingRef,
+        on_invalid_utf16: OnInvalidUtf16,
     ) -> Self {
         BinXmlDeserializer {
             data,
@@ -57,6 +62,7 @@ impl<'a> BinXmlDeserializer<'a> {
             chunk,
             is_inside_substitution,
             ansi_codec,
+            on_invalid_utf16,
         }
     }
 
@@ -67,6 +73,7 @@ impl<'a> BinXmlDeserializer<'a> {
         data_size: Option<u32>,
         is_inside_substitution: bool,
         ansi_codec: EncodingRef,
+        on_invalid_utf16: OnInvalidUtf16,
     ) -> Result<Vec<BinXMLDeserializedTokens<'a>>> {
         let offset = cursor.position();
 
@@ -76,6 +83,7 @@ impl<'a> BinXmlDeserializer<'a> {
             chunk,
             is_inside_substitution,
             ansi_codec,
+            on_invalid_utf16,
         );
 
         let mut tokens = vec![];
@@ -109,6 +117,7 @@ impl<'a> BinXmlDeserializer<'a> {
             eof: false,
             is_inside_substitution: self.is_inside_substitution,
             ansi_codec: self.ansi_codec,
+            on_invalid_utf16: self.on_invalid_utf16,
         })
     }
 }
@@ -147,19 +156,23 @@ impl<'a> IterTokens<'a> {
             BinXMLRawToken::CloseEmptyElement => Ok(BinXMLDeserializedTokens::CloseEmptyElement),
             BinXMLRawToken::CloseElement => Ok(BinXMLDeserializedTokens::CloseElement),
             BinXMLRawToken::Value => Ok(BinXMLDeserializedTokens::Value(
-                BinXmlValue::from_binxml_stream(cursor, self.chunk, None, self.ansi_codec)?,
+                BinXmlValue::from_binxml_stream(
+                    cursor,
+                    self.chunk,
+                    None,
+                    self.ansi_codec,
+                    self.on_invalid_utf16,
+                )?,
             )),
             BinXMLRawToken::Attribute(_token_information) => {
                 Ok(BinXMLDeserializedTokens::Attribute(read_attribute(cursor)?))
             }
-            BinXMLRawToken::CDataSection => Err(DeserializationError::UnimplementedToken {
-                name: "CDataSection",
-                offset: cursor.position(),
-            }),
-            BinXMLRawToken::CharReference => Err(DeserializationError::UnimplementedToken {
-                name: "CharReference",
-                offset: cursor.position(),
-            }),
+            BinXMLRawToken::CDataSection => Ok(BinXMLDeserializedTokens::CDATASection(
+                read_cdata_section(cursor, self.on_invalid_utf16)?,
+            )),
+            BinXMLRawToken::CharReference => {
+                Ok(BinXMLDeserializedTokens::CharRef(read_char_ref(cursor)?))
+            }
             BinXMLRawToken::EntityReference => Ok(BinXMLDeserializedTokens::EntityRef(
                 read_entity_ref(cursor)?,
             )),
@@ -167,10 +180,10 @@ impl<'a> IterTokens<'a> {
                 read_processing_instruction_target(cursor)?,
             )),
             BinXMLRawToken::ProcessingInstructionData => Ok(BinXMLDeserializedTokens::PIData(
-                read_processing_instruction_data(cursor)?,
+                read_processing_instruction_data(cursor, self.on_invalid_utf16)?,
             )),
             BinXMLRawToken::TemplateInstance => Ok(BinXMLDeserializedTokens::TemplateInstance(
-                read_template(cursor, self.chunk, self.ansi_codec)?,
+                read_template(cursor, self.chunk, self.ansi_codec, self.on_invalid_utf16)?,
             )),
             BinXMLRawToken::NormalSubstitution => Ok(BinXMLDeserializedTokens::Substitution(
                 read_substitution_descriptor(cursor, false)?,
@@ -249,10 +262,66 @@ impl<'a> Iterator for IterTokens<'a> {
 
 #[cfg(test)]
 mod tests {
+    use super::BinXmlDeserializer;
     use crate::evtx_chunk::EvtxChunkData;
+    use crate::model::deserialized::BinXMLDeserializedTokens;
+    use crate::utils::OnInvalidUtf16;
     use crate::{ensure_env_logger_initialized, ParserSettings};
+    use encoding::all::WINDOWS_1252;
+    use std::io::Cursor;
     use std::sync::Arc;
 
+    #[test]
+    fn test_reads_cdata_section() {
+        let mut data = vec![0x07, 0x05, 0x00];
+        data.extend("<a/>!".encode_utf16().flat_map(u16::to_le_bytes));
+        data.push(0x00);
+
+        let mut cursor = Cursor::new(data.as_slice());
+        let tokens = BinXmlDeserializer::read_binxml_fragment(
+            &mut cursor,
+            None,
+            None,
+            false,
+            WINDOWS_1252,
+            OnInvalidUtf16::Error,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                BinXMLDeserializedTokens::CDATASection("<a/>!".to_string()),
+                BinXMLDeserializedTokens::EndOfStream
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reads_char_ref() {
+        let data = [0x08, 0x41, 0x00, 0x48, 0x3c, 0x00, 0x00];
+
+        let mut cursor = Cursor::new(&data[..]);
+        let tokens = BinXmlDeserializer::read_binxml_fragment(
+            &mut cursor,
+            None,
+            None,
+            false,
+            WINDOWS_1252,
+            OnInvalidUtf16::Error,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                BinXMLDeserializedTokens::CharRef(0x41),
+                BinXMLDeserializedTokens::CharRef(0x3c),
+                BinXMLDeserializedTokens::EndOfStream
+            ]
+        );
+    }
+
     #[test]
     fn test_reads_a_single_record() {
         ensure_env_logger_initialized();
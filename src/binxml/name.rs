@@ -3,7 +3,7 @@ use crate::err::DeserializationResult as Result;
 use crate::ChunkOffset;
 pub use byteorder::{LittleEndian, ReadBytesExt};
 
-use crate::utils::read_len_prefixed_utf16_string;
+use crate::utils::{read_len_prefixed_utf16_string, OnInvalidUtf16};
 
 use std::{
     fmt::Formatter,
@@ -83,20 +83,26 @@ impl BinXmlNameRef {
 }
 
 impl BinXmlName {
-    #[cfg(test)]
     pub(crate) fn from_str(s: &str) -> Self {
         BinXmlName { str: s.to_string() }
     }
 
-    #[cfg(test)]
     pub(crate) fn from_string(s: String) -> Self {
         BinXmlName { str: s }
     }
 
     /// Reads a tuple of (String, Hash, Offset) from a stream.
-    pub fn from_stream(cursor: &mut Cursor<&[u8]>) -> Result<Self> {
-        let name =
-            try_read!(cursor, len_prefixed_utf_16_str_nul_terminated, "name")?.unwrap_or_default();
+    pub fn from_stream(
+        cursor: &mut Cursor<&[u8]>,
+        on_invalid_utf16: OnInvalidUtf16,
+    ) -> Result<Self> {
+        let name = try_read!(
+            cursor,
+            len_prefixed_utf_16_str_nul_terminated,
+            "name",
+            on_invalid_utf16
+        )?
+        .unwrap_or_default();
 
         Ok(BinXmlName { str: name })
     }
@@ -9,7 +9,7 @@ use std::io::Cursor;
 use crate::binxml::deserializer::BinXmlDeserializer;
 use crate::binxml::name::BinXmlNameRef;
 use crate::binxml::value_variant::{BinXmlValue, BinXmlValueType};
-use crate::utils::read_len_prefixed_utf16_string;
+use crate::utils::{read_len_prefixed_utf16_string, OnInvalidUtf16};
 
 use log::{error, trace, warn};
 
@@ -23,6 +23,7 @@ pub fn read_template<'a>(
     cursor: &mut Cursor<&'a [u8]>,
     chunk: Option<&'a EvtxChunk<'a>>,
     ansi_codec: EncodingRef,
+    on_invalid_utf16: OnInvalidUtf16,
 ) -> Result<BinXmlTemplateRef<'a>> {
     trace!("TemplateInstance at {}", cursor.position());
 
@@ -78,6 +79,7 @@ pub fn read_template<'a>(
             chunk,
             Some(descriptor.size),
             ansi_codec,
+            on_invalid_utf16,
         )?;
 
         trace!("\t {:?}", value);
@@ -140,6 +142,7 @@ pub fn read_template_definition<'a>(
     cursor: &mut Cursor<&'a [u8]>,
     chunk: Option<&'a EvtxChunk<'a>>,
     ansi_codec: EncodingRef,
+    on_invalid_utf16: OnInvalidUtf16,
 ) -> Result<BinXMLTemplateDefinition<'a>> {
     let header = read_template_definition_header(cursor)?;
 
@@ -155,6 +158,7 @@ pub fn read_template_definition<'a>(
         Some(header.data_size),
         false,
         ansi_codec,
+        on_invalid_utf16,
     ) {
         Ok(tokens) => BinXMLTemplateDefinition { header, tokens },
         Err(e) => {
@@ -208,17 +212,41 @@ pub fn read_processing_instruction_target(
     Ok(BinXMLProcessingInstructionTarget { name })
 }
 
-pub fn read_processing_instruction_data(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+pub fn read_processing_instruction_data(
+    cursor: &mut Cursor<&[u8]>,
+    on_invalid_utf16: OnInvalidUtf16,
+) -> Result<String> {
     trace!(
         "Offset `0x{:08x}` - ProcessingInstructionTarget",
         cursor.position(),
     );
 
-    let data = try_read!(cursor, len_prefixed_utf_16_str, "pi_data")?.unwrap_or_default();
+    let data = try_read!(cursor, len_prefixed_utf_16_str, "pi_data", on_invalid_utf16)?
+        .unwrap_or_default();
     trace!("PIData - {}", data,);
     Ok(data)
 }
 
+pub fn read_cdata_section(
+    cursor: &mut Cursor<&[u8]>,
+    on_invalid_utf16: OnInvalidUtf16,
+) -> Result<String> {
+    trace!("Offset `0x{:08x}` - CDataSection", cursor.position());
+
+    let data =
+        try_read!(cursor, len_prefixed_utf_16_str, "cdata", on_invalid_utf16)?.unwrap_or_default();
+    trace!("CDATA - {}", data);
+    Ok(data)
+}
+
+pub fn read_char_ref(cursor: &mut Cursor<&[u8]>) -> Result<u16> {
+    trace!("Offset `0x{:08x}` - CharReference", cursor.position());
+    let value = try_read!(cursor, u16)?;
+    trace!("\t value: {}", value);
+
+    Ok(value)
+}
+
 pub fn read_substitution_descriptor(
     cursor: &mut Cursor<&[u8]>,
     optional: bool,
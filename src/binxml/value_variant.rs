@@ -10,7 +10,7 @@ use winstructs::guid::Guid;
 use crate::model::deserialized::BinXMLDeserializedTokens;
 use crate::utils::{
     read_ansi_encoded_string, read_len_prefixed_utf16_string, read_null_terminated_utf16_string,
-    read_systemtime, read_utf16_by_size,
+    read_systemtime, read_utf16_by_size, OnInvalidUtf16,
 };
 use chrono::{DateTime, Utc};
 use log::trace;
@@ -193,6 +193,7 @@ impl<'a> BinXmlValue<'a> {
         chunk: Option<&'a EvtxChunk<'a>>,
         size: Option<u16>,
         ansi_codec: EncodingRef,
+        on_invalid_utf16: OnInvalidUtf16,
     ) -> Result<BinXmlValue<'a>> {
         let value_type_token = try_read!(cursor, u8)?;
 
@@ -203,7 +204,14 @@ impl<'a> BinXmlValue<'a> {
             },
         )?;
 
-        let data = Self::deserialize_value_type(&value_type, cursor, chunk, size, ansi_codec)?;
+        let data = Self::deserialize_value_type(
+            &value_type,
+            cursor,
+            chunk,
+            size,
+            ansi_codec,
+            on_invalid_utf16,
+        )?;
 
         Ok(data)
     }
@@ -214,6 +222,7 @@ impl<'a> BinXmlValue<'a> {
         chunk: Option<&'a EvtxChunk<'a>>,
         size: Option<u16>,
         ansi_codec: EncodingRef,
+        on_invalid_utf16: OnInvalidUtf16,
     ) -> Result<BinXmlValue<'a>> {
         trace!(
             "Offset `0x{offset:08x} ({offset}): {value_type:?}, {size:?}",
@@ -225,7 +234,7 @@ impl<'a> BinXmlValue<'a> {
         let value = match (value_type, size) {
             (BinXmlValueType::NullType, _) => BinXmlValue::NullType,
             (BinXmlValueType::StringType, Some(sz)) => BinXmlValue::StringType(
-                read_utf16_by_size(cursor, u64::from(sz))
+                read_utf16_by_size(cursor, u64::from(sz), on_invalid_utf16)
                     .map_err(|e| {
                         WrappedIoError::io_error_with_message(
                             e,
@@ -236,7 +245,13 @@ impl<'a> BinXmlValue<'a> {
                     .unwrap_or_else(|| "".to_owned()),
             ),
             (BinXmlValueType::StringType, None) => BinXmlValue::StringType(
-                try_read!(cursor, len_prefixed_utf_16_str, "<string_value>")?.unwrap_or_default(),
+                try_read!(
+                    cursor,
+                    len_prefixed_utf_16_str,
+                    "<string_value>",
+                    on_invalid_utf16
+                )?
+                .unwrap_or_default(),
             ),
             (BinXmlValueType::AnsiStringType, Some(sz)) => BinXmlValue::AnsiStringType(Cow::Owned(
                 read_ansi_encoded_string(cursor, u64::from(sz), ansi_codec)?
@@ -290,7 +305,12 @@ impl<'a> BinXmlValue<'a> {
             }
             (BinXmlValueType::BinXmlType, None) => {
                 let tokens = BinXmlDeserializer::read_binxml_fragment(
-                    cursor, chunk, None, true, ansi_codec,
+                    cursor,
+                    chunk,
+                    None,
+                    true,
+                    ansi_codec,
+                    on_invalid_utf16,
                 )?;
 
                 BinXmlValue::BinXmlType(tokens)
@@ -302,6 +322,7 @@ impl<'a> BinXmlValue<'a> {
                     Some(u32::from(sz)),
                     true,
                     ansi_codec,
+                    on_invalid_utf16,
                 )?;
 
                 BinXmlValue::BinXmlType(tokens)
@@ -324,7 +345,7 @@ impl<'a> BinXmlValue<'a> {
             }
             // The array types are always sized.
             (BinXmlValueType::StringArrayType, Some(sz)) => BinXmlValue::StringArrayType(
-                try_read_sized_array!(cursor, null_terminated_utf_16_str, sz),
+                try_read_sized_array!(cursor, null_terminated_utf_16_str, sz, on_invalid_utf16),
             ),
             (BinXmlValueType::Int8ArrayType, Some(sz)) => {
                 BinXmlValue::Int8ArrayType(try_read_sized_array!(cursor, i8, sz))
@@ -408,6 +429,55 @@ fn to_delimited_list<N: ToString>(ns: impl AsRef<Vec<N>>) -> String {
         .join(",")
 }
 
+/// Bytes will be formatted as const length of 2 with '0' padding, like `wevtutil` does.
+fn to_hex_string(bytes: &[u8]) -> String {
+    let mut repr = String::with_capacity(bytes.len() * 2);
+
+    for b in bytes.iter() {
+        write!(repr, "{:02X}", b).expect("Writing to a String cannot fail");
+    }
+
+    repr
+}
+
+/// Standard (RFC 4648) base64, with padding.
+fn to_base64_string(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut repr = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                repr.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize]));
+            } else {
+                repr.push('=');
+            }
+        }
+    }
+
+    repr
+}
+
+/// Formats a GUID the way Windows renders it, `{54849625-5478-4994-A5BA-3E3B0328C30D}`.
+/// The first three groups are stored little-endian, which `Guid` already accounts for.
+fn to_guid_string(guid: &Guid) -> String {
+    format!("{{{}}}", guid)
+}
+
+fn to_timestamp_strings(timestamps: &[DateTime<Utc>]) -> Vec<String> {
+    timestamps
+        .iter()
+        .map(|tm| tm.format(DATETIME_FORMAT).to_string())
+        .collect()
+}
+
 impl<'c> From<BinXmlValue<'c>> for serde_json::Value {
     fn from(value: BinXmlValue<'c>) -> Self {
         match value {
@@ -425,12 +495,8 @@ impl<'c> From<BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::Real32Type(num) => json!(num),
             BinXmlValue::Real64Type(num) => json!(num),
             BinXmlValue::BoolType(num) => json!(num),
-            BinXmlValue::BinaryType(bytes) => {
-                // Bytes will be formatted as const length of 2 with '0' padding.
-                let repr: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-                json!(repr)
-            }
-            BinXmlValue::GuidType(guid) => json!(guid.to_string()),
+            BinXmlValue::BinaryType(bytes) => json!(to_hex_string(bytes)),
+            BinXmlValue::GuidType(guid) => json!(to_guid_string(&guid)),
             //            BinXmlValue::SizeTType(sz) => json!(sz.to_string()),
             BinXmlValue::FileTimeType(tm) => json!(tm.format(DATETIME_FORMAT).to_string()),
             BinXmlValue::SysTimeType(tm) => json!(tm.format(DATETIME_FORMAT).to_string()),
@@ -450,10 +516,10 @@ impl<'c> From<BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::Real64ArrayType(numbers) => json!(numbers),
             BinXmlValue::BoolArrayType(bools) => json!(bools),
             BinXmlValue::GuidArrayType(guids) => {
-                json!(guids.iter().map(Guid::to_string).collect::<Vec<String>>())
+                json!(guids.iter().map(to_guid_string).collect::<Vec<String>>())
             }
-            BinXmlValue::FileTimeArrayType(filetimes) => json!(filetimes),
-            BinXmlValue::SysTimeArrayType(systimes) => json!(systimes),
+            BinXmlValue::FileTimeArrayType(filetimes) => json!(to_timestamp_strings(&filetimes)),
+            BinXmlValue::SysTimeArrayType(systimes) => json!(to_timestamp_strings(&systimes)),
             BinXmlValue::SidArrayType(sids) => {
                 json!(sids.iter().map(Sid::to_string).collect::<Vec<String>>())
             }
@@ -488,12 +554,8 @@ impl<'c> From<&'c BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::Real32Type(num) => json!(num),
             BinXmlValue::Real64Type(num) => json!(num),
             BinXmlValue::BoolType(num) => json!(num),
-            BinXmlValue::BinaryType(bytes) => {
-                // Bytes will be formatted as const length of 2 with '0' padding.
-                let repr: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-                json!(repr)
-            }
-            BinXmlValue::GuidType(guid) => json!(guid.to_string()),
+            BinXmlValue::BinaryType(bytes) => json!(to_hex_string(bytes)),
+            BinXmlValue::GuidType(guid) => json!(to_guid_string(guid)),
             //            BinXmlValue::SizeTType(sz) => json!(sz.to_string()),
             BinXmlValue::FileTimeType(tm) => json!(tm.format(DATETIME_FORMAT).to_string()),
             BinXmlValue::SysTimeType(tm) => json!(tm.format(DATETIME_FORMAT).to_string()),
@@ -513,10 +575,10 @@ impl<'c> From<&'c BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::Real64ArrayType(numbers) => json!(numbers),
             BinXmlValue::BoolArrayType(bools) => json!(bools),
             BinXmlValue::GuidArrayType(guids) => {
-                json!(guids.iter().map(Guid::to_string).collect::<Vec<String>>())
+                json!(guids.iter().map(to_guid_string).collect::<Vec<String>>())
             }
-            BinXmlValue::FileTimeArrayType(filetimes) => json!(filetimes),
-            BinXmlValue::SysTimeArrayType(systimes) => json!(systimes),
+            BinXmlValue::FileTimeArrayType(filetimes) => json!(to_timestamp_strings(filetimes)),
+            BinXmlValue::SysTimeArrayType(systimes) => json!(to_timestamp_strings(systimes)),
             BinXmlValue::SidArrayType(sids) => {
                 json!(sids.iter().map(Sid::to_string).collect::<Vec<String>>())
             }
@@ -535,7 +597,62 @@ impl<'c> From<&'c BinXmlValue<'c>> for serde_json::Value {
 }
 
 impl<'a> BinXmlValue<'a> {
-    pub fn as_cow_str(&self) -> Cow<str> {
+    /// The type of the value, as it is declared in BinXml.
+    pub fn value_type(&self) -> BinXmlValueType {
+        match self {
+            BinXmlValue::NullType => BinXmlValueType::NullType,
+            BinXmlValue::StringType(_) => BinXmlValueType::StringType,
+            BinXmlValue::AnsiStringType(_) => BinXmlValueType::AnsiStringType,
+            BinXmlValue::Int8Type(_) => BinXmlValueType::Int8Type,
+            BinXmlValue::UInt8Type(_) => BinXmlValueType::UInt8Type,
+            BinXmlValue::Int16Type(_) => BinXmlValueType::Int16Type,
+            BinXmlValue::UInt16Type(_) => BinXmlValueType::UInt16Type,
+            BinXmlValue::Int32Type(_) => BinXmlValueType::Int32Type,
+            BinXmlValue::UInt32Type(_) => BinXmlValueType::UInt32Type,
+            BinXmlValue::Int64Type(_) => BinXmlValueType::Int64Type,
+            BinXmlValue::UInt64Type(_) => BinXmlValueType::UInt64Type,
+            BinXmlValue::Real32Type(_) => BinXmlValueType::Real32Type,
+            BinXmlValue::Real64Type(_) => BinXmlValueType::Real64Type,
+            BinXmlValue::BoolType(_) => BinXmlValueType::BoolType,
+            BinXmlValue::BinaryType(_) => BinXmlValueType::BinaryType,
+            BinXmlValue::GuidType(_) => BinXmlValueType::GuidType,
+            BinXmlValue::SizeTType(_) => BinXmlValueType::SizeTType,
+            BinXmlValue::FileTimeType(_) => BinXmlValueType::FileTimeType,
+            BinXmlValue::SysTimeType(_) => BinXmlValueType::SysTimeType,
+            BinXmlValue::SidType(_) => BinXmlValueType::SidType,
+            BinXmlValue::HexInt32Type(_) => BinXmlValueType::HexInt32Type,
+            BinXmlValue::HexInt64Type(_) => BinXmlValueType::HexInt64Type,
+            BinXmlValue::EvtHandle => BinXmlValueType::EvtHandle,
+            BinXmlValue::BinXmlType(_) => BinXmlValueType::BinXmlType,
+            BinXmlValue::EvtXml => BinXmlValueType::EvtXmlType,
+            BinXmlValue::StringArrayType(_) => BinXmlValueType::StringArrayType,
+            BinXmlValue::AnsiStringArrayType => BinXmlValueType::AnsiStringArrayType,
+            BinXmlValue::Int8ArrayType(_) => BinXmlValueType::Int8ArrayType,
+            BinXmlValue::UInt8ArrayType(_) => BinXmlValueType::UInt8ArrayType,
+            BinXmlValue::Int16ArrayType(_) => BinXmlValueType::Int16ArrayType,
+            BinXmlValue::UInt16ArrayType(_) => BinXmlValueType::UInt16ArrayType,
+            BinXmlValue::Int32ArrayType(_) => BinXmlValueType::Int32ArrayType,
+            BinXmlValue::UInt32ArrayType(_) => BinXmlValueType::UInt32ArrayType,
+            BinXmlValue::Int64ArrayType(_) => BinXmlValueType::Int64ArrayType,
+            BinXmlValue::UInt64ArrayType(_) => BinXmlValueType::UInt64ArrayType,
+            BinXmlValue::Real32ArrayType(_) => BinXmlValueType::Real32ArrayType,
+            BinXmlValue::Real64ArrayType(_) => BinXmlValueType::Real64ArrayType,
+            BinXmlValue::BoolArrayType(_) => BinXmlValueType::BoolArrayType,
+            BinXmlValue::BinaryArrayType => BinXmlValueType::BinaryArrayType,
+            BinXmlValue::GuidArrayType(_) => BinXmlValueType::GuidArrayType,
+            BinXmlValue::SizeTArrayType => BinXmlValueType::SizeTArrayType,
+            BinXmlValue::FileTimeArrayType(_) => BinXmlValueType::FileTimeArrayType,
+            BinXmlValue::SysTimeArrayType(_) => BinXmlValueType::SysTimeArrayType,
+            BinXmlValue::SidArrayType(_) => BinXmlValueType::SidArrayType,
+            BinXmlValue::HexInt32ArrayType(_) => BinXmlValueType::HexInt32ArrayType,
+            BinXmlValue::HexInt64ArrayType(_) => BinXmlValueType::HexInt64ArrayType,
+            BinXmlValue::EvtArrayHandle => BinXmlValueType::EvtHandleArray,
+            BinXmlValue::BinXmlArrayType => BinXmlValueType::BinXmlArrayType,
+            BinXmlValue::EvtXmlArrayType => BinXmlValueType::EvtXmlArrayType,
+        }
+    }
+
+    pub fn as_cow_str(&self) -> Cow<'_, str> {
         match self {
             BinXmlValue::NullType => Cow::Borrowed(""),
             BinXmlValue::StringType(s) => Cow::Borrowed(s.as_ref()),
@@ -551,17 +668,8 @@ impl<'a> BinXmlValue<'a> {
             BinXmlValue::Real32Type(num) => Cow::Owned(num.to_string()),
             BinXmlValue::Real64Type(num) => Cow::Owned(num.to_string()),
             BinXmlValue::BoolType(num) => Cow::Owned(num.to_string()),
-            BinXmlValue::BinaryType(bytes) => {
-                // Bytes will be formatted as const length of 2 with '0' padding.
-                let mut repr = String::with_capacity(bytes.len() * 2);
-
-                for b in bytes.iter() {
-                    write!(repr, "{:02X}", b).expect("Writing to a String cannot fail");
-                }
-
-                Cow::Owned(repr)
-            }
-            BinXmlValue::GuidType(guid) => Cow::Owned(guid.to_string()),
+            BinXmlValue::BinaryType(bytes) => Cow::Owned(to_hex_string(bytes)),
+            BinXmlValue::GuidType(guid) => Cow::Owned(to_guid_string(guid)),
             BinXmlValue::SizeTType(sz) => Cow::Owned(sz.to_string()),
             BinXmlValue::FileTimeType(tm) => Cow::Owned(tm.format(DATETIME_FORMAT).to_string()),
             BinXmlValue::SysTimeType(tm) => Cow::Owned(tm.format(DATETIME_FORMAT).to_string()),
@@ -580,9 +688,19 @@ impl<'a> BinXmlValue<'a> {
             BinXmlValue::Real32ArrayType(numbers) => Cow::Owned(to_delimited_list(numbers)),
             BinXmlValue::Real64ArrayType(numbers) => Cow::Owned(to_delimited_list(numbers)),
             BinXmlValue::BoolArrayType(bools) => Cow::Owned(to_delimited_list(bools)),
-            BinXmlValue::GuidArrayType(guids) => Cow::Owned(to_delimited_list(guids)),
-            BinXmlValue::FileTimeArrayType(filetimes) => Cow::Owned(to_delimited_list(filetimes)),
-            BinXmlValue::SysTimeArrayType(systimes) => Cow::Owned(to_delimited_list(systimes)),
+            BinXmlValue::GuidArrayType(guids) => Cow::Owned(
+                guids
+                    .iter()
+                    .map(to_guid_string)
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            BinXmlValue::FileTimeArrayType(filetimes) => {
+                Cow::Owned(to_timestamp_strings(filetimes).join(","))
+            }
+            BinXmlValue::SysTimeArrayType(systimes) => {
+                Cow::Owned(to_timestamp_strings(systimes).join(","))
+            }
             BinXmlValue::SidArrayType(sids) => Cow::Owned(to_delimited_list(sids)),
             BinXmlValue::HexInt32ArrayType(hex_strings) => Cow::Owned(hex_strings.join(",")),
             BinXmlValue::HexInt64ArrayType(hex_strings) => Cow::Owned(hex_strings.join(",")),
@@ -597,3 +715,457 @@ impl<'a> BinXmlValue<'a> {
         }
     }
 }
+
+/// How binary values are written as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryEncoding {
+    /// Upper case hex, two characters per byte (`0A1B`).
+    #[default]
+    Hex,
+    /// Standard base64 with padding (`Chs=`), a third shorter than hex.
+    Base64,
+}
+
+impl<'a> BinXmlValue<'a> {
+    /// Replaces binary values longer than `limit` bytes with the hex of their first `limit` bytes,
+    /// followed by `...`. Other values are returned as is.
+    pub fn truncate_binary<'v>(
+        value: Cow<'v, BinXmlValue<'a>>,
+        limit: Option<usize>,
+    ) -> Cow<'v, BinXmlValue<'a>> {
+        BinXmlValue::encode_binary(value, BinaryEncoding::Hex, limit)
+    }
+
+    /// Like `truncate_binary`, but binary values are written using `encoding`.
+    /// Other values are returned as is.
+    pub fn encode_binary<'v>(
+        value: Cow<'v, BinXmlValue<'a>>,
+        encoding: BinaryEncoding,
+        limit: Option<usize>,
+    ) -> Cow<'v, BinXmlValue<'a>> {
+        let bytes = match value.as_ref() {
+            BinXmlValue::BinaryType(bytes) => *bytes,
+            _ => return value,
+        };
+
+        let (bytes, suffix) = match limit {
+            Some(limit) if bytes.len() > limit => (&bytes[..limit], "..."),
+            // Hex is how binary values are rendered by default.
+            _ if encoding == BinaryEncoding::Hex => return value,
+            _ => (bytes, ""),
+        };
+
+        let encoded = match encoding {
+            BinaryEncoding::Hex => to_hex_string(bytes),
+            BinaryEncoding::Base64 => to_base64_string(bytes),
+        };
+
+        Cow::Owned(BinXmlValue::StringType(encoded + suffix))
+    }
+}
+
+/// Writes the values whose text has more than one common representation, see
+/// `ParserSettings::value_formatter`.
+///
+/// Every method defaults to the representation used when there is no formatter,
+/// so a formatter only overrides the types it cares about.
+/// Binary values are written according to `ParserSettings::binary_encoding` instead.
+pub trait ValueFormatter: Send + Sync {
+    /// `{54849625-5478-4994-A5BA-3E3B0328C30D}` by default.
+    fn format_guid(&self, guid: &Guid) -> String {
+        to_guid_string(guid)
+    }
+
+    /// `2019-03-26T19:24:43.107371Z` by default.
+    fn format_filetime(&self, filetime: &DateTime<Utc>) -> String {
+        filetime.format(DATETIME_FORMAT).to_string()
+    }
+
+    /// Same as `format_filetime` by default.
+    fn format_systemtime(&self, systemtime: &DateTime<Utc>) -> String {
+        self.format_filetime(systemtime)
+    }
+
+    /// `S-1-5-18` by default.
+    fn format_sid(&self, sid: &Sid) -> String {
+        sid.to_string()
+    }
+
+    /// Called with the text of `HexInt32` and `HexInt64` values, `0x` followed by lower case hex,
+    /// which is kept by default.
+    fn format_hex_int(&self, hex: &str) -> String {
+        hex.to_string()
+    }
+}
+
+/// Formats every value the same way as when there is no formatter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultFormatter;
+
+impl ValueFormatter for DefaultFormatter {}
+
+impl<'a> BinXmlValue<'a> {
+    /// Writes values of the types handled by `formatter` as strings (or arrays of strings),
+    /// other values are returned as is.
+    pub fn format_with<'v>(
+        value: Cow<'v, BinXmlValue<'a>>,
+        formatter: &dyn ValueFormatter,
+    ) -> Cow<'v, BinXmlValue<'a>> {
+        let formatted = match value.as_ref() {
+            BinXmlValue::GuidType(guid) => BinXmlValue::StringType(formatter.format_guid(guid)),
+            BinXmlValue::FileTimeType(tm) => BinXmlValue::StringType(formatter.format_filetime(tm)),
+            BinXmlValue::SysTimeType(tm) => {
+                BinXmlValue::StringType(formatter.format_systemtime(tm))
+            }
+            BinXmlValue::SidType(sid) => BinXmlValue::StringType(formatter.format_sid(sid)),
+            BinXmlValue::HexInt32Type(hex) | BinXmlValue::HexInt64Type(hex) => {
+                BinXmlValue::StringType(formatter.format_hex_int(hex))
+            }
+            BinXmlValue::GuidArrayType(guids) => BinXmlValue::StringArrayType(
+                guids
+                    .iter()
+                    .map(|guid| formatter.format_guid(guid))
+                    .collect(),
+            ),
+            BinXmlValue::FileTimeArrayType(filetimes) => BinXmlValue::StringArrayType(
+                filetimes
+                    .iter()
+                    .map(|tm| formatter.format_filetime(tm))
+                    .collect(),
+            ),
+            BinXmlValue::SysTimeArrayType(systimes) => BinXmlValue::StringArrayType(
+                systimes
+                    .iter()
+                    .map(|tm| formatter.format_systemtime(tm))
+                    .collect(),
+            ),
+            BinXmlValue::SidArrayType(sids) => BinXmlValue::StringArrayType(
+                sids.iter().map(|sid| formatter.format_sid(sid)).collect(),
+            ),
+            BinXmlValue::HexInt32ArrayType(hex_strings)
+            | BinXmlValue::HexInt64ArrayType(hex_strings) => BinXmlValue::StringArrayType(
+                hex_strings
+                    .iter()
+                    .map(|hex| formatter.format_hex_int(hex))
+                    .collect(),
+            ),
+            _ => return value,
+        };
+
+        Cow::Owned(formatted)
+    }
+}
+
+impl<'a> BinXmlValue<'a> {
+    /// For a nested BinXml fragment made of a template (as `EventData` usually is), the values
+    /// substituted in its template, in substitution slot order.
+    pub fn substitution_values(&self) -> Option<Vec<BinXmlValue<'a>>> {
+        match self {
+            BinXmlValue::BinXmlType(tokens) => template_substitution_values(tokens),
+            _ => None,
+        }
+    }
+}
+
+/// The values substituted in the first template instance of `tokens`, in substitution slot order.
+pub(crate) fn template_substitution_values<'a>(
+    tokens: &[BinXMLDeserializedTokens<'a>],
+) -> Option<Vec<BinXmlValue<'a>>> {
+    let template = tokens.iter().find_map(|token| match token {
+        BinXMLDeserializedTokens::TemplateInstance(template) => Some(template),
+        _ => None,
+    })?;
+
+    Some(
+        template
+            .substitution_array
+            .iter()
+            .map(|token| match token {
+                BinXMLDeserializedTokens::Value(value) => value.clone(),
+                _ => BinXmlValue::NullType,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        to_base64_string, BinXmlValue, BinXmlValueType, BinaryEncoding, DefaultFormatter,
+        ValueFormatter,
+    };
+    use crate::utils::OnInvalidUtf16;
+    use encoding::all::WINDOWS_1252;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::borrow::Cow;
+    use std::io::Cursor;
+
+    fn deserialize(
+        value_type: BinXmlValueType,
+        bytes: &[u8],
+        size: Option<u16>,
+    ) -> BinXmlValue<'_> {
+        let mut cursor = Cursor::new(bytes);
+        BinXmlValue::deserialize_value_type(
+            &value_type,
+            &mut cursor,
+            None,
+            size,
+            WINDOWS_1252,
+            OnInvalidUtf16::Error,
+        )
+        .unwrap()
+    }
+
+    /// `S-1-5-21-1004336348-1177238915-682003330-512`
+    fn sid_bytes() -> Vec<u8> {
+        let mut bytes = vec![1, 5, 0, 0, 0, 0, 0, 5];
+        for sub_authority in [21_u32, 1004336348, 1177238915, 682003330, 512] {
+            bytes.extend_from_slice(&sub_authority.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_64_bit_integers_are_rendered_exactly() {
+        let values = [
+            (BinXmlValue::UInt64Type(u64::MAX), "18446744073709551615"),
+            (BinXmlValue::Int64Type(i64::MIN), "-9223372036854775808"),
+            (
+                BinXmlValue::UInt64ArrayType(vec![u64::MAX, (1 << 53) + 1]),
+                "[18446744073709551615,9007199254740993]",
+            ),
+        ];
+
+        for (value, expected) in values {
+            let json = serde_json::Value::from(value).to_string();
+            assert_eq!(json, expected);
+            // And back, without going through a float.
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&json)
+                    .unwrap()
+                    .to_string(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_sid_is_rendered_as_a_string() {
+        let bytes = sid_bytes();
+        let value = deserialize(BinXmlValueType::SidType, &bytes, None);
+
+        assert_eq!(
+            value.as_cow_str(),
+            "S-1-5-21-1004336348-1177238915-682003330-512"
+        );
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!("S-1-5-21-1004336348-1177238915-682003330-512")
+        );
+    }
+
+    #[test]
+    fn test_sid_array_is_rendered_as_strings() {
+        let mut bytes = sid_bytes();
+        // `S-1-5-18`
+        bytes.extend_from_slice(&[1, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0]);
+
+        let value = deserialize(
+            BinXmlValueType::SidArrayType,
+            &bytes,
+            Some(bytes.len() as u16),
+        );
+
+        assert_eq!(
+            value.as_cow_str(),
+            "S-1-5-21-1004336348-1177238915-682003330-512,S-1-5-18"
+        );
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!(["S-1-5-21-1004336348-1177238915-682003330-512", "S-1-5-18"])
+        );
+    }
+
+    #[test]
+    fn test_filetime_is_rendered_as_a_timestamp() {
+        // 2019-03-05T12:00:00.123456Z
+        let bytes = 131962608001234560_u64.to_le_bytes();
+        let value = deserialize(BinXmlValueType::FileTimeType, &bytes, None);
+
+        assert_eq!(value.as_cow_str(), "2019-03-05T12:00:00.123456Z");
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!("2019-03-05T12:00:00.123456Z")
+        );
+    }
+
+    #[test]
+    fn test_filetime_array_is_rendered_as_timestamps() {
+        let mut bytes = 131962608001234560_u64.to_le_bytes().to_vec();
+        // 1601-01-01T00:00:00Z
+        bytes.extend_from_slice(&0_u64.to_le_bytes());
+
+        let value = deserialize(
+            BinXmlValueType::FileTimeArrayType,
+            &bytes,
+            Some(bytes.len() as u16),
+        );
+
+        assert_eq!(
+            value.as_cow_str(),
+            "2019-03-05T12:00:00.123456Z,1601-01-01T00:00:00.000000Z"
+        );
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!(["2019-03-05T12:00:00.123456Z", "1601-01-01T00:00:00.000000Z"])
+        );
+    }
+
+    #[test]
+    fn test_guid_is_rendered_in_braces() {
+        // `{54849625-5478-4994-A5BA-3E3B0328C30D}`, the first three groups are little-endian.
+        let bytes = [
+            0x25, 0x96, 0x84, 0x54, 0x78, 0x54, 0x94, 0x49, 0xA5, 0xBA, 0x3E, 0x3B, 0x03, 0x28,
+            0xC3, 0x0D,
+        ];
+        let value = deserialize(BinXmlValueType::GuidType, &bytes, None);
+
+        assert_eq!(value.as_cow_str(), "{54849625-5478-4994-A5BA-3E3B0328C30D}");
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!("{54849625-5478-4994-A5BA-3E3B0328C30D}")
+        );
+    }
+
+    #[test]
+    fn test_value_formatter() {
+        struct LowerCaseGuids;
+
+        impl ValueFormatter for LowerCaseGuids {
+            fn format_guid(&self, guid: &winstructs::guid::Guid) -> String {
+                guid.to_string().to_lowercase()
+            }
+        }
+
+        let guid_bytes = [
+            0x25, 0x96, 0x84, 0x54, 0x78, 0x54, 0x94, 0x49, 0xA5, 0xBA, 0x3E, 0x3B, 0x03, 0x28,
+            0xC3, 0x0D,
+        ];
+        let sid_bytes = sid_bytes();
+        let filetime_bytes = 131962608001234560_u64.to_le_bytes();
+        let values = [
+            deserialize(BinXmlValueType::GuidType, &guid_bytes, None),
+            deserialize(BinXmlValueType::SidType, &sid_bytes, None),
+            deserialize(BinXmlValueType::FileTimeArrayType, &filetime_bytes, Some(8)),
+            BinXmlValue::HexInt32Type(Cow::Borrowed("0x1f")),
+            BinXmlValue::UInt32Type(4624),
+        ];
+
+        // The default formatter writes values like they are written without a formatter.
+        for value in values.iter() {
+            let formatted = BinXmlValue::format_with(Cow::Borrowed(value), &DefaultFormatter);
+            assert_eq!(formatted.as_cow_str(), value.as_cow_str());
+            assert_eq!(
+                serde_json::Value::from(formatted.into_owned()),
+                serde_json::Value::from(value.clone())
+            );
+        }
+
+        let formatted: Vec<_> = values
+            .iter()
+            .map(|value| BinXmlValue::format_with(Cow::Borrowed(value), &LowerCaseGuids))
+            .collect();
+        assert_eq!(
+            formatted[0].as_ref(),
+            &BinXmlValue::StringType("54849625-5478-4994-a5ba-3e3b0328c30d".to_string())
+        );
+        assert_eq!(
+            formatted[1].as_cow_str(),
+            "S-1-5-21-1004336348-1177238915-682003330-512"
+        );
+        // Values of other types are not converted.
+        assert!(matches!(
+            formatted[4],
+            Cow::Borrowed(BinXmlValue::UInt32Type(4624))
+        ));
+    }
+
+    #[test]
+    fn test_binary_is_truncated_to_limit() {
+        let bytes = [0xDE, 0xAD, 0xBE, 0xEF];
+        let value = deserialize(BinXmlValueType::BinaryType, &bytes, Some(4));
+
+        assert_eq!(value.as_cow_str(), "DEADBEEF");
+        assert_eq!(
+            BinXmlValue::truncate_binary(Cow::Borrowed(&value), Some(2)).as_cow_str(),
+            "DEAD..."
+        );
+        assert_eq!(
+            BinXmlValue::truncate_binary(Cow::Borrowed(&value), Some(4)).as_cow_str(),
+            "DEADBEEF"
+        );
+    }
+
+    #[test]
+    fn test_binary_is_encoded_as_base64() {
+        let encode = |bytes: &[u8], limit| {
+            let value = BinXmlValue::BinaryType(bytes);
+            BinXmlValue::encode_binary(Cow::Borrowed(&value), BinaryEncoding::Base64, limit)
+                .as_cow_str()
+                .into_owned()
+        };
+
+        assert_eq!(encode(&[], None), "");
+        assert_eq!(encode(b"f", None), "Zg==");
+        assert_eq!(encode(b"fo", None), "Zm8=");
+        assert_eq!(encode(b"foo", None), "Zm9v");
+        assert_eq!(encode(&[0xDE, 0xAD, 0xBE, 0xEF], None), "3q2+7w==");
+        assert_eq!(encode(&[0xDE, 0xAD, 0xBE, 0xEF], Some(3)), "3q2+...");
+    }
+
+    #[test]
+    fn test_base64_rfc4648_vectors() {
+        // Test vectors from RFC 4648, section 10.
+        assert_eq!(to_base64_string(b""), "");
+        assert_eq!(to_base64_string(b"f"), "Zg==");
+        assert_eq!(to_base64_string(b"fo"), "Zm8=");
+        assert_eq!(to_base64_string(b"foo"), "Zm9v");
+        assert_eq!(to_base64_string(b"foob"), "Zm9vYg==");
+        assert_eq!(to_base64_string(b"fooba"), "Zm9vYmE=");
+        assert_eq!(to_base64_string(b"foobar"), "Zm9vYmFy");
+
+        // The last two characters of the alphabet.
+        assert_eq!(to_base64_string(&[0xFB, 0xFF, 0xBF]), "+/+/");
+    }
+
+    #[test]
+    fn test_invalid_utf16_follows_policy() {
+        // `a`, an unpaired high surrogate, `b`.
+        let bytes: Vec<u8> = [0x61_u16, 0xD800, 0x62]
+            .iter()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        let decode = |on_invalid_utf16| {
+            BinXmlValue::deserialize_value_type(
+                &BinXmlValueType::StringType,
+                &mut Cursor::new(bytes.as_slice()),
+                None,
+                Some(bytes.len() as u16),
+                WINDOWS_1252,
+                on_invalid_utf16,
+            )
+        };
+
+        assert!(decode(OnInvalidUtf16::Error).is_err());
+        assert_eq!(
+            decode(OnInvalidUtf16::Replace).unwrap(),
+            BinXmlValue::StringType("a\u{FFFD}b".to_owned())
+        );
+        assert_eq!(
+            decode(OnInvalidUtf16::Lossy).unwrap(),
+            BinXmlValue::StringType("ab".to_owned())
+        );
+    }
+}
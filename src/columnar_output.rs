@@ -0,0 +1,577 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::field_selector::FieldSelector;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::{BinXmlOutput, OnUnhandled};
+
+use chrono::{DateTime, Utc};
+use log::trace;
+use std::borrow::Cow;
+
+/// The type of the values of a column of `ColumnarOutput`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    String,
+    Int64,
+    /// Also accepts hex values, such as `Keywords` (`0x8020000000000000`).
+    UInt64,
+    Float64,
+    Boolean,
+    /// An RFC 3339 timestamp, such as `TimeCreated/@SystemTime`.
+    Timestamp,
+}
+
+impl ColumnType {
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::String => "string",
+            ColumnType::Int64 => "signed integer",
+            ColumnType::UInt64 => "unsigned integer",
+            ColumnType::Float64 => "floating point number",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Timestamp => "timestamp",
+        }
+    }
+}
+
+/// The values of a single column, missing fields are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnValues {
+    String(Vec<Option<String>>),
+    Int64(Vec<Option<i64>>),
+    UInt64(Vec<Option<u64>>),
+    Float64(Vec<Option<f64>>),
+    Boolean(Vec<Option<bool>>),
+    Timestamp(Vec<Option<DateTime<Utc>>>),
+}
+
+impl ColumnValues {
+    fn new(column_type: ColumnType, capacity: usize) -> Self {
+        match column_type {
+            ColumnType::String => ColumnValues::String(Vec::with_capacity(capacity)),
+            ColumnType::Int64 => ColumnValues::Int64(Vec::with_capacity(capacity)),
+            ColumnType::UInt64 => ColumnValues::UInt64(Vec::with_capacity(capacity)),
+            ColumnType::Float64 => ColumnValues::Float64(Vec::with_capacity(capacity)),
+            ColumnType::Boolean => ColumnValues::Boolean(Vec::with_capacity(capacity)),
+            ColumnType::Timestamp => ColumnValues::Timestamp(Vec::with_capacity(capacity)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnValues::String(values) => values.len(),
+            ColumnValues::Int64(values) => values.len(),
+            ColumnValues::UInt64(values) => values.len(),
+            ColumnValues::Float64(values) => values.len(),
+            ColumnValues::Boolean(values) => values.len(),
+            ColumnValues::Timestamp(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A value converted to the type of its column, before it is appended.
+enum Cell {
+    String(String),
+    Int64(i64),
+    UInt64(u64),
+    Float64(f64),
+    Boolean(bool),
+    Timestamp(DateTime<Utc>),
+}
+
+impl Cell {
+    fn parse(column_type: ColumnType, text: &str) -> Option<Cell> {
+        let trimmed = text.trim();
+        let hex = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"));
+
+        match column_type {
+            ColumnType::String => Some(Cell::String(text.to_owned())),
+            ColumnType::Int64 => match hex {
+                Some(hex) => u64::from_str_radix(hex, 16)
+                    .ok()
+                    .and_then(|n| i64::try_from(n).ok()),
+                None => trimmed.parse().ok(),
+            }
+            .map(Cell::Int64),
+            ColumnType::UInt64 => match hex {
+                Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                None => trimmed.parse().ok(),
+            }
+            .map(Cell::UInt64),
+            ColumnType::Float64 => trimmed.parse().ok().map(Cell::Float64),
+            ColumnType::Boolean => match trimmed {
+                "true" | "1" => Some(Cell::Boolean(true)),
+                "false" | "0" => Some(Cell::Boolean(false)),
+                _ => None,
+            },
+            ColumnType::Timestamp => DateTime::parse_from_rfc3339(trimmed)
+                .ok()
+                .map(|timestamp| Cell::Timestamp(timestamp.with_timezone(&Utc))),
+        }
+    }
+}
+
+/// A batch of records, stored column by column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnBatch {
+    names: Vec<String>,
+    columns: Vec<ColumnValues>,
+    num_rows: usize,
+}
+
+impl ColumnBatch {
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// The columns of the batch, in schema order, along with their names.
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &ColumnValues)> {
+        self.names
+            .iter()
+            .map(String::as_str)
+            .zip(self.columns.iter())
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnValues> {
+        self.columns()
+            .find(|(column_name, _)| *column_name == name)
+            .map(|(_, values)| values)
+    }
+
+    pub fn into_columns(self) -> Vec<(String, ColumnValues)> {
+        self.names.into_iter().zip(self.columns).collect()
+    }
+
+    /// Converts the batch to an Arrow `RecordBatch`, with a nullable field per column.
+    /// Timestamps are in microseconds, in UTC.
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self) -> SerializationResult<arrow::record_batch::RecordBatch> {
+        use arrow::array::{
+            ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray,
+            TimestampMicrosecondArray, UInt64Array,
+        };
+        use arrow::datatypes::{Field, Schema};
+        use std::sync::Arc;
+
+        let arrays: Vec<ArrayRef> = self
+            .columns
+            .iter()
+            .map(|values| -> ArrayRef {
+                match values {
+                    ColumnValues::String(values) => Arc::new(StringArray::from(values.clone())),
+                    ColumnValues::Int64(values) => Arc::new(Int64Array::from(values.clone())),
+                    ColumnValues::UInt64(values) => Arc::new(UInt64Array::from(values.clone())),
+                    ColumnValues::Float64(values) => Arc::new(Float64Array::from(values.clone())),
+                    ColumnValues::Boolean(values) => Arc::new(BooleanArray::from(values.clone())),
+                    ColumnValues::Timestamp(values) => Arc::new(
+                        TimestampMicrosecondArray::from(
+                            values
+                                .iter()
+                                .map(|t| t.map(|t| t.timestamp_micros()))
+                                .collect::<Vec<_>>(),
+                        )
+                        .with_timezone("UTC"),
+                    ),
+                }
+            })
+            .collect();
+
+        let fields: Vec<Field> = self
+            .names
+            .iter()
+            .zip(arrays.iter())
+            .map(|(name, array)| Field::new(name, array.data_type().clone(), true))
+            .collect();
+
+        Ok(arrow::record_batch::RecordBatch::try_new(
+            Arc::new(Schema::new(fields)),
+            arrays,
+        )?)
+    }
+}
+
+/// Accumulates records into `ColumnBatch`es of typed columns, for analytics engines.
+///
+/// The schema is given up front, as a path (in the same syntax as `CsvOutput`) and a type
+/// for every column:
+///
+/// ```text
+/// Event/System/TimeCreated/@SystemTime  Timestamp
+/// Event/System/EventID                  UInt64
+/// Event/EventData/TargetUserName        String
+/// ```
+///
+/// Missing fields are `None`, and values which cannot be converted to the type of their
+/// column fail the record. With the `arrow` feature, every `ColumnBatch` converts to an
+/// Arrow `RecordBatch` (with `ColumnBatch::to_record_batch`), which DataFusion can query.
+pub struct ColumnarOutput {
+    names: Vec<String>,
+    fields: FieldSelector,
+    types: Vec<ColumnType>,
+    batch_size: usize,
+    columns: Vec<ColumnValues>,
+    num_rows: usize,
+    batches: Vec<ColumnBatch>,
+}
+
+impl ColumnarOutput {
+    /// Creates an output which produces batches of (at most) `batch_size` records.
+    pub fn new<S: AsRef<str>>(schema: &[(S, ColumnType)], batch_size: usize) -> Self {
+        let names: Vec<String> = schema
+            .iter()
+            .map(|(name, _)| name.as_ref().to_owned())
+            .collect();
+        let types: Vec<ColumnType> = schema.iter().map(|(_, t)| *t).collect();
+        let batch_size = batch_size.max(1);
+
+        ColumnarOutput {
+            fields: FieldSelector::new(&names),
+            columns: types
+                .iter()
+                .map(|t| ColumnValues::new(*t, batch_size))
+                .collect(),
+            names,
+            types,
+            batch_size,
+            num_rows: 0,
+            batches: vec![],
+        }
+    }
+
+    /// Sets what is done with processing instructions and comments, which are not rendered.
+    pub fn on_unhandled(mut self, on_unhandled: OnUnhandled) -> Self {
+        self.fields.set_on_unhandled(on_unhandled);
+
+        self
+    }
+
+    /// Takes the batches which are complete, the current partial batch is kept.
+    pub fn take_batches(&mut self) -> Vec<ColumnBatch> {
+        std::mem::take(&mut self.batches)
+    }
+
+    /// Returns all the batches, the last one holding the remaining records.
+    pub fn into_batches(mut self) -> Vec<ColumnBatch> {
+        if self.num_rows > 0 {
+            self.flush_batch();
+        }
+
+        self.batches
+    }
+
+    fn flush_batch(&mut self) {
+        let columns = self
+            .types
+            .iter()
+            .map(|t| ColumnValues::new(*t, self.batch_size))
+            .collect();
+
+        self.batches.push(ColumnBatch {
+            names: self.names.clone(),
+            columns: std::mem::replace(&mut self.columns, columns),
+            num_rows: std::mem::take(&mut self.num_rows),
+        });
+    }
+
+    /// Converts the cells of the current record, so that nothing is appended if one is invalid.
+    fn convert_row(&mut self) -> SerializationResult<Vec<Option<Cell>>> {
+        let mut row = Vec::with_capacity(self.names.len());
+
+        for (i, cell) in self.fields.cells_mut().iter_mut().enumerate() {
+            let column_type = self.types[i];
+
+            row.push(match cell.take() {
+                None => None,
+                Some(text) => match Cell::parse(column_type, &text) {
+                    Some(cell) => Some(cell),
+                    None => {
+                        return Err(SerializationError::InvalidColumnValue {
+                            column: self.names[i].clone(),
+                            value: text,
+                            column_type: column_type.name(),
+                        })
+                    }
+                },
+            });
+        }
+
+        Ok(row)
+    }
+
+    fn append_row(&mut self, row: Vec<Option<Cell>>) {
+        for (values, cell) in self.columns.iter_mut().zip(row) {
+            match (values, cell) {
+                (ColumnValues::String(values), Some(Cell::String(s))) => values.push(Some(s)),
+                (ColumnValues::Int64(values), Some(Cell::Int64(n))) => values.push(Some(n)),
+                (ColumnValues::UInt64(values), Some(Cell::UInt64(n))) => values.push(Some(n)),
+                (ColumnValues::Float64(values), Some(Cell::Float64(n))) => values.push(Some(n)),
+                (ColumnValues::Boolean(values), Some(Cell::Boolean(b))) => values.push(Some(b)),
+                (ColumnValues::Timestamp(values), Some(Cell::Timestamp(t))) => values.push(Some(t)),
+                (ColumnValues::String(values), _) => values.push(None),
+                (ColumnValues::Int64(values), _) => values.push(None),
+                (ColumnValues::UInt64(values), _) => values.push(None),
+                (ColumnValues::Float64(values), _) => values.push(None),
+                (ColumnValues::Boolean(values), _) => values.push(None),
+                (ColumnValues::Timestamp(values), _) => values.push(None),
+            }
+        }
+
+        self.num_rows += 1;
+        if self.num_rows == self.batch_size {
+            self.flush_batch();
+        }
+    }
+}
+
+impl BinXmlOutput for ColumnarOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        let row = self.convert_row()?;
+        self.append_row(row);
+
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.fields.visit_open_start_element(element)
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.fields.visit_close_element(element)
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.fields.visit_characters(value)
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.fields.visit_cdata_section(cdata)
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.fields.visit_entity_reference(entity)
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.fields.visit_character_reference(char_ref)
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.fields.visit_processing_instruction(pi)
+    }
+
+    fn unhandled_policy(&self) -> OnUnhandled {
+        self.fields.unhandled_policy()
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        self.fields.visit_start_of_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColumnType, ColumnValues, ColumnarOutput};
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::err::SerializationError;
+    use crate::model::xml::test_helpers::element;
+    use crate::BinXmlOutput;
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
+
+    fn write_record(
+        output: &mut ColumnarOutput,
+        event_id: &str,
+        user: Option<&str>,
+    ) -> crate::err::SerializationResult<()> {
+        let event = element("Event", &[]);
+        let system = element("System", &[]);
+        let time_created = element(
+            "TimeCreated",
+            &[("SystemTime", "2019-03-13T13:19:52.000000Z")],
+        );
+        let event_id_element = element("EventID", &[]);
+        let event_data = element("EventData", &[]);
+
+        output.visit_start_of_stream()?;
+        output.visit_open_start_element(&event)?;
+        output.visit_open_start_element(&system)?;
+        output.visit_open_start_element(&time_created)?;
+        output.visit_close_element(&time_created)?;
+        output.visit_open_start_element(&event_id_element)?;
+        output.visit_characters(Cow::Owned(BinXmlValue::StringType(event_id.to_string())))?;
+        output.visit_close_element(&event_id_element)?;
+        output.visit_close_element(&system)?;
+
+        output.visit_open_start_element(&event_data)?;
+        if let Some(user) = user {
+            let data = element("Data", &[("Name", "TargetUserName")]);
+            output.visit_open_start_element(&data)?;
+            output.visit_characters(Cow::Owned(BinXmlValue::StringType(user.to_string())))?;
+            output.visit_close_element(&data)?;
+        }
+        output.visit_close_element(&event_data)?;
+        output.visit_close_element(&event)?;
+        output.visit_end_of_stream()
+    }
+
+    #[test]
+    fn test_columnar_output() {
+        let mut output = ColumnarOutput::new(
+            &[
+                (
+                    "Event/System/TimeCreated/@SystemTime",
+                    ColumnType::Timestamp,
+                ),
+                ("Event/System/EventID", ColumnType::UInt64),
+                ("Event/EventData/TargetUserName", ColumnType::String),
+            ],
+            2,
+        );
+
+        write_record(&mut output, "4624", Some("bob")).unwrap();
+        write_record(&mut output, "4625", None).unwrap();
+        assert_eq!(output.take_batches().len(), 1);
+
+        write_record(&mut output, "4634", Some("alice")).unwrap();
+        let batches = output.into_batches();
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 1);
+        assert_eq!(
+            batches[0].column("Event/System/TimeCreated/@SystemTime"),
+            Some(&ColumnValues::Timestamp(vec![Some(
+                Utc.with_ymd_and_hms(2019, 3, 13, 13, 19, 52).unwrap()
+            )]))
+        );
+        assert_eq!(
+            batches[0].column("Event/System/EventID"),
+            Some(&ColumnValues::UInt64(vec![Some(4634)]))
+        );
+        assert_eq!(
+            batches[0].column("Event/EventData/TargetUserName"),
+            Some(&ColumnValues::String(vec![Some("alice".to_string())]))
+        );
+    }
+
+    #[test]
+    fn test_columnar_output_invalid_value() {
+        let mut output = ColumnarOutput::new(
+            &[
+                ("Event/EventData/TargetUserName", ColumnType::String),
+                ("Event/System/EventID", ColumnType::UInt64),
+            ],
+            10,
+        );
+
+        write_record(&mut output, "4624", None).unwrap();
+        assert!(matches!(
+            write_record(&mut output, "not a number", Some("bob")),
+            Err(SerializationError::InvalidColumnValue { .. })
+        ));
+
+        // The invalid record is not appended at all.
+        let batch = output.into_batches().remove(0);
+        assert_eq!(
+            batch.into_columns(),
+            vec![
+                (
+                    "Event/EventData/TargetUserName".to_string(),
+                    ColumnValues::String(vec![None])
+                ),
+                (
+                    "Event/System/EventID".to_string(),
+                    ColumnValues::UInt64(vec![Some(4624)])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_columnar_output_hex_values() {
+        let mut keywords = ColumnarOutput::new(&[("Event/System/EventID", ColumnType::UInt64)], 10);
+        write_record(&mut keywords, "0x8020000000000000", None).unwrap();
+        assert_eq!(
+            keywords.into_batches()[0].column("Event/System/EventID"),
+            Some(&ColumnValues::UInt64(vec![Some(0x8020000000000000)]))
+        );
+
+        // Hex values which do not fit in a signed column are rejected instead of wrapping around.
+        let mut signed = ColumnarOutput::new(&[("Event/System/EventID", ColumnType::Int64)], 10);
+        write_record(&mut signed, "0x7fffffffffffffff", None).unwrap();
+        assert!(matches!(
+            write_record(&mut signed, "0x8020000000000000", None),
+            Err(SerializationError::InvalidColumnValue { .. })
+        ));
+        assert_eq!(
+            signed.into_batches()[0].column("Event/System/EventID"),
+            Some(&ColumnValues::Int64(vec![Some(i64::MAX)]))
+        );
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_columnar_output_to_record_batch() {
+        use arrow::array::{Array, StringArray, TimestampMicrosecondArray, UInt64Array};
+        use arrow::datatypes::{DataType, TimeUnit};
+
+        let mut output = ColumnarOutput::new(
+            &[
+                (
+                    "Event/System/TimeCreated/@SystemTime",
+                    ColumnType::Timestamp,
+                ),
+                ("Event/System/EventID", ColumnType::UInt64),
+                ("Event/EventData/TargetUserName", ColumnType::String),
+            ],
+            10,
+        );
+        write_record(&mut output, "4624", Some("bob")).unwrap();
+        write_record(&mut output, "4625", None).unwrap();
+
+        let batch = output.into_batches()[0].to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(
+            batch.schema().field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+
+        let timestamps = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(
+            timestamps.value(0),
+            Utc.with_ymd_and_hms(2019, 3, 13, 13, 19, 52)
+                .unwrap()
+                .timestamp_micros()
+        );
+
+        let event_ids = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(event_ids.values(), &[4624, 4625]);
+
+        let users = batch
+            .column_by_name("Event/EventData/TargetUserName")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(users.value(0), "bob");
+        assert!(users.is_null(1));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::err::SerializationResult;
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::field_selector::FieldSelector;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::{BinXmlOutput, OnUnhandled};
+
+use log::trace;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Writes one CSV row per record, with a column for every selected path.
+///
+/// Paths are `/` separated element names, starting from the root element.
+/// A trailing `@name` segment selects an attribute instead of the element's text,
+/// and `Data` nodes are addressed by their `Name` attribute, like in JSON output:
+///
+/// ```text
+/// Event/System/TimeCreated/@SystemTime
+/// Event/System/EventID
+/// Event/EventData/TargetUserName
+/// ```
+///
+/// Missing fields are written as empty cells. If a path matches several elements,
+/// only the first one is used. The header row is written once, before the first record.
+///
+/// Rows are buffered, and only reach the writer on `flush`, `into_writer` or when the
+/// output is dropped.
+pub struct CsvOutput<W: Write> {
+    writer: csv::Writer<W>,
+    header: Vec<String>,
+    fields: FieldSelector,
+    header_written: bool,
+}
+
+impl<W: Write> CsvOutput<W> {
+    pub fn new<S: AsRef<str>>(target: W, columns: &[S]) -> Self {
+        let header: Vec<String> = columns.iter().map(|c| c.as_ref().to_owned()).collect();
+
+        CsvOutput {
+            writer: csv::Writer::from_writer(target),
+            fields: FieldSelector::new(&header),
+            header,
+            header_written: false,
+        }
+    }
+
+    /// Sets what is done with processing instructions and comments, which are not rendered.
+    pub fn on_unhandled(mut self, on_unhandled: OnUnhandled) -> Self {
+        self.fields.set_on_unhandled(on_unhandled);
+
+        self
+    }
+
+    /// Writes any buffered rows to the underlying writer and flushes it.
+    pub fn flush(&mut self) -> SerializationResult<()> {
+        self.writer.flush().map_err(csv::Error::from)?;
+
+        Ok(())
+    }
+
+    /// Flushes any buffered rows and returns the underlying writer.
+    pub fn into_writer(self) -> SerializationResult<W> {
+        self.writer.into_inner().map_err(|e| {
+            let error = io::Error::new(e.error().kind(), e.error().to_string());
+            csv::Error::from(error).into()
+        })
+    }
+}
+
+impl<W: Write> BinXmlOutput for CsvOutput<W> {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        self.writer.write_record(
+            self.fields
+                .cells()
+                .iter()
+                .map(|c| c.as_deref().unwrap_or("")),
+        )?;
+
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.fields.visit_open_start_element(element)
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.fields.visit_close_element(element)
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.fields.visit_characters(value)
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.fields.visit_cdata_section(cdata)
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.fields.visit_entity_reference(entity)
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.fields.visit_character_reference(char_ref)
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.fields.visit_processing_instruction(pi)
+    }
+
+    fn unhandled_policy(&self) -> OnUnhandled {
+        self.fields.unhandled_policy()
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        if !self.header_written {
+            self.writer.write_record(&self.header)?;
+            self.header_written = true;
+        }
+
+        self.fields.visit_start_of_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CsvOutput;
+    use crate::binxml::name::BinXmlName;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::err::SerializationError;
+    use crate::model::xml::test_helpers::element;
+    use crate::model::xml::BinXmlPI;
+    use crate::{BinXmlOutput, OnUnhandled};
+    use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    fn write_record<W: Write>(output: &mut CsvOutput<W>, event_id: u16, user: Option<&str>) {
+        let event = element("Event", &[]);
+        let system = element("System", &[]);
+        let time_created = element("TimeCreated", &[("SystemTime", "2019-03-13 13:19:52")]);
+        let event_id_element = element("EventID", &[]);
+        let event_data = element("EventData", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&system).unwrap();
+        output.visit_open_start_element(&time_created).unwrap();
+        output.visit_close_element(&time_created).unwrap();
+        output.visit_open_start_element(&event_id_element).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::UInt16Type(event_id)))
+            .unwrap();
+        output.visit_close_element(&event_id_element).unwrap();
+        output.visit_close_element(&system).unwrap();
+
+        output.visit_open_start_element(&event_data).unwrap();
+        if let Some(user) = user {
+            let data = element("Data", &[("Name", "TargetUserName")]);
+            output.visit_open_start_element(&data).unwrap();
+            output
+                .visit_characters(Cow::Owned(BinXmlValue::StringType(user.to_string())))
+                .unwrap();
+            output.visit_close_element(&data).unwrap();
+        }
+        output.visit_close_element(&event_data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let mut output = CsvOutput::new(
+            vec![],
+            &[
+                "Event/System/TimeCreated/@SystemTime",
+                "Event/System/EventID",
+                "Event/EventData/TargetUserName",
+            ],
+        );
+
+        write_record(&mut output, 4624, Some("bob, \"the admin\""));
+        write_record(&mut output, 4625, None);
+
+        let csv = String::from_utf8(output.into_writer().unwrap()).unwrap();
+
+        assert_eq!(
+            csv,
+            "Event/System/TimeCreated/@SystemTime,Event/System/EventID,Event/EventData/TargetUserName\n\
+             2019-03-13 13:19:52,4624,\"bob, \"\"the admin\"\"\"\n\
+             2019-03-13 13:19:52,4625,\n"
+        );
+    }
+
+    #[test]
+    fn test_rows_are_buffered_until_flushed() {
+        /// A writer whose content can be looked at while the output owns it.
+        #[derive(Clone, Default)]
+        struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+        impl Write for SharedBuffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = SharedBuffer::default();
+        let mut output = CsvOutput::new(buffer.clone(), &["Event/System/EventID"]);
+
+        write_record(&mut output, 4624, None);
+        assert!(buffer.0.borrow().is_empty());
+
+        output.flush().unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+            "Event/System/EventID\n4624\n"
+        );
+    }
+
+    #[test]
+    fn test_unhandled_tokens() {
+        let pi = BinXmlPI {
+            name: Cow::Owned(BinXmlName::from_str("xml-stylesheet")),
+            data: Cow::Borrowed("href=\"event.xsl\""),
+        };
+
+        let mut output = CsvOutput::new(vec![], &["Event/System/EventID"]);
+        output.visit_processing_instruction(&pi).unwrap();
+        output.visit_comment("ignored").unwrap();
+
+        let mut output =
+            CsvOutput::new(vec![], &["Event/System/EventID"]).on_unhandled(OnUnhandled::Error);
+        assert!(matches!(
+            output.visit_processing_instruction(&pi),
+            Err(SerializationError::Unimplemented { .. })
+        ));
+        assert!(matches!(
+            output.visit_comment("rejected"),
+            Err(SerializationError::Unimplemented { .. })
+        ));
+    }
+}
@@ -164,10 +164,7 @@ pub enum SerializationError {
     // Since `quick-xml` maintains the stack for us, structural errors with the XML
     // Will be included in this generic error alongside IO errors.
     #[error("Writing to XML failed")]
-    XmlOutputError {
-        #[from]
-        source: quick_xml::Error,
-    },
+    XmlOutputError { source: quick_xml::Error },
 
     #[error("Building a JSON document failed with message: {message}")]
     JsonStructureError { message: String },
@@ -178,16 +175,110 @@ pub enum SerializationError {
         source: serde_json::error::Error,
     },
 
+    #[error("Writing to CSV failed")]
+    CsvOutputError {
+        #[from]
+        source: csv::Error,
+    },
+
+    #[cfg(feature = "arrow")]
+    #[error("Building an Arrow record batch failed")]
+    ArrowError {
+        #[from]
+        source: arrow::error::ArrowError,
+    },
+
+    #[error("Record is missing the `{field}` system field")]
+    MissingSystemField { field: String },
+
+    #[error("System field `{field}` has an unexpected value `{value}`")]
+    InvalidSystemField { field: String, value: String },
+
+    #[error("Column `{column}` has a value `{value}` which is not a valid {column_type}")]
+    InvalidColumnValue {
+        column: String,
+        value: String,
+        column_type: &'static str,
+    },
+
+    #[error("Failed to parse timestamp `{value}`")]
+    InvalidTimestamp {
+        value: String,
+        source: chrono::ParseError,
+    },
+
+    #[error("Unknown XML entity reference `&{name};`")]
+    UnknownEntityReference { name: String },
+
+    #[error("Character reference `&#{value};` does not refer to a valid unicode scalar")]
+    InvalidCharacterReference { value: String },
+
     #[error("Record data contains invalid UTF-8")]
     RecordContainsInvalidUTF8 {
         #[from]
         source: std::string::FromUtf8Error,
     },
 
+    /// The record is structurally broken, an element was closed but never opened.
+    #[error(
+        "Found a close element without a matching open element, after {closed_elements} close elements"
+    )]
+    UnbalancedStack { closed_elements: usize },
+
+    /// The record is structurally broken, the stream ended while elements were still open.
+    #[error("Reached the end of the stream with {open_elements} elements still open")]
+    UnexpectedEof { open_elements: usize },
+
+    /// A `Data` element has no `Name` attribute, which is an error with `strict_json`.
+    #[error("Found a `Data` element without a `Name` attribute")]
+    UnnamedDataNode,
+
+    /// An element is repeated, and would be renamed to be written, which is an error with `strict_json`.
+    #[error("Found a repeated `{name}` element, which can't be written without renaming it")]
+    DuplicateElement { name: String },
+
+    /// An element has two attributes with the same name, or an attribute without a name (usually
+    /// from a corrupted name table), which is an error with `strict_json`.
+    #[error("Found an invalid or repeated attribute name `{name}` in element `{element}`")]
+    DuplicateAttribute { element: String, name: String },
+
+    /// The record nests elements deeper than the configured `max_depth`.
+    #[error("Record exceeds the maximum depth of {max_depth} nested elements")]
+    MaxDepthExceeded { max_depth: usize },
+
+    /// The rendered record is larger than the configured `max_output_bytes`.
+    #[error("Record output exceeds the maximum of {max_output_bytes} bytes")]
+    OutputTooLarge { max_output_bytes: usize },
+
+    /// The record is truncated, the output was consumed before the end of the stream was visited.
+    #[error("Tried to return writer before EOF was reached, output is incomplete")]
+    IncompleteOutput,
+
     #[error("Unimplemented: {message}")]
     Unimplemented { message: String },
 }
 
+impl From<quick_xml::Error> for SerializationError {
+    /// `XmlOutput` limits its size with a writer which can only fail with an IO error,
+    /// `OutputTooLarge` is unwrapped from it so that it can still be matched on.
+    fn from(source: quick_xml::Error) -> Self {
+        match source {
+            quick_xml::Error::Io(e) => match e
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<SerializationError>())
+            {
+                Some(&SerializationError::OutputTooLarge { max_output_bytes }) => {
+                    SerializationError::OutputTooLarge { max_output_bytes }
+                }
+                _ => SerializationError::XmlOutputError {
+                    source: quick_xml::Error::Io(e),
+                },
+            },
+            source => SerializationError::XmlOutputError { source },
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum InputError {
     #[error("Failed to open file {}", path.display())]
@@ -220,7 +311,10 @@ pub enum ChunkError {
     #[error("Failed to parse chunk header")]
     FailedToParseChunkHeader(#[from] DeserializationError),
 
-    #[error("chunk data CRC32 invalid")]
+    #[error("chunk header CRC32 invalid, expected {expected:#010x} but found {found:#010x}")]
+    InvalidChunkHeaderChecksum { expected: u32, found: u32 },
+
+    #[error("chunk data CRC32 invalid, expected {expected:#010x} but found {found:#010x}")]
     InvalidChunkChecksum { expected: u32, found: u32 },
 
     #[error("Failed to build string cache")]
@@ -229,7 +323,7 @@ pub enum ChunkError {
     #[error("Failed to build template cache")]
     FailedToBuildTemplateCache {
         message: String,
-        source: DeserializationError,
+        source: Box<DeserializationError>,
     },
 }
 
@@ -248,14 +342,42 @@ pub enum EvtxError {
     DeserializationError(#[from] DeserializationError),
 
     #[error("Failed to parse chunk number {chunk_id}")]
-    FailedToParseChunk { chunk_id: u64, source: ChunkError },
+    FailedToParseChunk {
+        chunk_id: u64,
+        source: Box<ChunkError>,
+    },
 
     #[error("Failed to parse record number {record_id}")]
     FailedToParseRecord {
         record_id: RecordId,
+        /// The offset of the record header in the file.
+        file_offset: FileOffset,
         source: Box<EvtxError>,
     },
 
+    /// A record declares a size which runs past the used space of its chunk.
+    #[error(
+        "Record number {record_id} at offset {file_offset} is truncated, it declares {data_size} bytes but only {available} are left in the chunk"
+    )]
+    TruncatedRecord {
+        record_id: RecordId,
+        /// The offset of the record header in the file.
+        file_offset: FileOffset,
+        data_size: u32,
+        available: u64,
+    },
+
+    /// A record declares a size which cannot even hold its header and the copy of its size.
+    #[error(
+        "Record number {record_id} at offset {file_offset} declares {data_size} bytes, which is less than the size of a record header"
+    )]
+    InvalidRecordSize {
+        record_id: RecordId,
+        /// The offset of the record header in the file.
+        file_offset: FileOffset,
+        data_size: u32,
+    },
+
     #[error("Calculation Error, reason: {}", .0)]
     CalculationError(String),
 
@@ -269,6 +391,9 @@ pub enum EvtxError {
     // TODO: should we keep an `Unimplemented` variant at public API?
     #[error("Unimplemented: {name}")]
     Unimplemented { name: String },
+
+    #[error("Failed to encode record: {message}")]
+    FailedToEncodeRecord { message: String },
 }
 
 impl EvtxError {
@@ -279,7 +404,7 @@ impl EvtxError {
     pub fn incomplete_chunk(chunk_id: u64) -> EvtxError {
         EvtxError::FailedToParseChunk {
             chunk_id,
-            source: ChunkError::IncompleteChunk,
+            source: Box::new(ChunkError::IncompleteChunk),
         }
     }
 }
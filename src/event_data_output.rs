@@ -0,0 +1,331 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::json_output::{expand_character_reference, expand_entity_reference};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::{BinXmlOutput, OnUnhandled};
+
+use log::trace;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+
+/// Key under which unnamed `Data` nodes are collected, like `JsonOutput` does.
+const UNNAMED_DATA_KEY: &str = "Data";
+
+/// A direct child of `EventData` which is currently open.
+struct PendingField {
+    key: Option<String>,
+    value: Option<Value>,
+}
+
+/// Produces only the `EventData` of a record, as a single level `{name: value}` JSON object.
+///
+/// ```text
+/// {
+///   "TargetUserName": "bob",
+///   "LogonType": 3,
+///   "Data": ["first unnamed data node"]
+/// }
+/// ```
+///
+/// `Data` nodes use their `Name` attribute as a key, and are rendered as their scalar value,
+/// without `#text` or `#attributes`. Repeated names are accumulated into arrays, and unnamed
+/// `Data` nodes are kept in order under `Data`, like `JsonOutput` does.
+/// Records without `EventData` (such as `UserData` records) produce an empty object.
+pub struct EventDataOutput {
+    map: Map<String, Value>,
+    stack: Vec<String>,
+    field: Option<PendingField>,
+    closed_elements: usize,
+    eof_reached: bool,
+    on_unhandled: OnUnhandled,
+}
+
+impl Default for EventDataOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventDataOutput {
+    pub fn new() -> Self {
+        EventDataOutput {
+            map: Map::new(),
+            stack: vec![],
+            field: None,
+            closed_elements: 0,
+            eof_reached: false,
+            on_unhandled: OnUnhandled::default(),
+        }
+    }
+
+    /// Sets what is done with processing instructions and comments, which are not rendered.
+    pub fn on_unhandled(mut self, on_unhandled: OnUnhandled) -> Self {
+        self.on_unhandled = on_unhandled;
+
+        self
+    }
+
+    /// Whether the element being opened is a direct child of `/Event/EventData`.
+    fn is_event_data_child(&self) -> bool {
+        self.stack.len() == 2 && self.stack[0] == "Event" && self.stack[1] == "EventData"
+    }
+
+    /// Appends a value to the currently open field, concatenating with any existing text.
+    fn append(&mut self, value: Value) {
+        let field = match self.field.as_mut() {
+            Some(field) => field,
+            None => return,
+        };
+
+        field.value = match field.value.take() {
+            None => Some(value),
+            Some(Value::String(mut existing)) => {
+                match value {
+                    Value::String(s) => existing.push_str(&s),
+                    other => existing.push_str(&other.to_string()),
+                }
+                Some(Value::String(existing))
+            }
+            Some(existing) => {
+                let mut concatenated = existing.to_string();
+                match value {
+                    Value::String(s) => concatenated.push_str(&s),
+                    other => concatenated.push_str(&other.to_string()),
+                }
+                Some(Value::String(concatenated))
+            }
+        };
+    }
+
+    fn insert_field(&mut self, field: PendingField) {
+        let value = field.value.unwrap_or(Value::Null);
+
+        match field.key {
+            Some(key) => match self.map.get_mut(&key) {
+                None => {
+                    self.map.insert(key, value);
+                }
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array(vec![first, value]);
+                }
+            },
+            // Unnamed data nodes are positional, so we always keep them in an array.
+            None => match self.map.get_mut(UNNAMED_DATA_KEY) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array(vec![first, value]);
+                }
+                None => {
+                    self.map
+                        .insert(UNNAMED_DATA_KEY.to_owned(), Value::Array(vec![value]));
+                }
+            },
+        }
+    }
+
+    /// Fails with `IncompleteOutput` on a truncated record, or `UnexpectedEof` on a broken one.
+    pub fn into_value(self) -> SerializationResult<Value> {
+        if !self.eof_reached {
+            return Err(SerializationError::IncompleteOutput);
+        }
+
+        if !self.stack.is_empty() {
+            return Err(SerializationError::UnexpectedEof {
+                open_elements: self.stack.len(),
+            });
+        }
+
+        Ok(Value::Object(self.map))
+    }
+}
+
+impl BinXmlOutput for EventDataOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        self.eof_reached = true;
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        trace!("visit_open_start_element: {:?}", element.name);
+        let name = element.name.as_str();
+
+        if self.is_event_data_child() {
+            let key = if name == "Data" {
+                element.attribute_str("Name").map(Cow::into_owned)
+            } else {
+                Some(name.to_owned())
+            };
+
+            self.field = Some(PendingField { key, value: None });
+        }
+
+        self.stack.push(name.to_owned());
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        let name = self
+            .stack
+            .pop()
+            .ok_or(SerializationError::UnbalancedStack {
+                closed_elements: self.closed_elements,
+            })?;
+        trace!("visit_close_element: {:?}", name);
+        self.closed_elements += 1;
+
+        if self.is_event_data_child() {
+            if let Some(field) = self.field.take() {
+                self.insert_field(field);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        let value = match value {
+            Cow::Owned(BinXmlValue::StringType(value)) => json!(value),
+            value => value.into_owned().into(),
+        };
+
+        self.append(value);
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.append(json!(cdata));
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        let expanded = expand_entity_reference(entity)?;
+        self.append(json!(expanded));
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        let c = expand_character_reference(&char_ref)?;
+        self.append(json!(c.to_string()));
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> SerializationResult<()> {
+        self.on_unhandled.handle("processing instruction")
+    }
+
+    fn unhandled_policy(&self) -> OnUnhandled {
+        self.on_unhandled
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventDataOutput;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::model::xml::test_helpers::element;
+    use crate::model::xml::XmlElement;
+    use crate::BinXmlOutput;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::borrow::Cow;
+
+    fn data(name: &str) -> XmlElement<'static> {
+        element("Data", &[("Name", name)])
+    }
+
+    fn leaf(output: &mut EventDataOutput, element: &XmlElement, value: BinXmlValue) {
+        output.visit_open_start_element(element).unwrap();
+        output.visit_characters(Cow::Owned(value)).unwrap();
+        output.visit_close_element(element).unwrap();
+    }
+
+    #[test]
+    fn test_event_data_output() {
+        let mut output = EventDataOutput::new();
+        let event = element("Event", &[]);
+        let system = element("System", &[]);
+        let event_data = element("EventData", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&system).unwrap();
+        leaf(
+            &mut output,
+            &element("EventID", &[]),
+            BinXmlValue::UInt16Type(4624),
+        );
+        output.visit_close_element(&system).unwrap();
+
+        output.visit_open_start_element(&event_data).unwrap();
+        leaf(
+            &mut output,
+            &data("TargetUserName"),
+            BinXmlValue::StringType("bob".to_string()),
+        );
+        leaf(&mut output, &data("LogonType"), BinXmlValue::UInt32Type(3));
+        leaf(
+            &mut output,
+            &data("Group"),
+            BinXmlValue::StringType("Users".to_string()),
+        );
+        leaf(
+            &mut output,
+            &data("Group"),
+            BinXmlValue::StringType("Administrators".to_string()),
+        );
+        let empty = data("IpAddress");
+        output.visit_open_start_element(&empty).unwrap();
+        output.visit_close_element(&empty).unwrap();
+        leaf(
+            &mut output,
+            &element("Data", &[]),
+            BinXmlValue::StringType("unnamed".to_string()),
+        );
+        output.visit_close_element(&event_data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            output.into_value().unwrap(),
+            json!({
+                "TargetUserName": "bob",
+                "LogonType": 3,
+                "Group": ["Users", "Administrators"],
+                "IpAddress": null,
+                "Data": ["unnamed"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_event_data_output_without_event_data() {
+        let mut output = EventDataOutput::new();
+        let event = element("Event", &[]);
+        let user_data = element("UserData", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&user_data).unwrap();
+        leaf(
+            &mut output,
+            &element("Data", &[]),
+            BinXmlValue::StringType("ignored".to_string()),
+        );
+        output.visit_close_element(&user_data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(output.into_value().unwrap(), json!({}));
+    }
+}
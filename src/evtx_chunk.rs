@@ -1,8 +1,8 @@
 use crate::err::{
-    ChunkError, DeserializationError, DeserializationResult, EvtxChunkResult, EvtxError,
+    ChunkError, DeserializationError, DeserializationResult, EvtxChunkResult, EvtxError, Result,
 };
 
-use crate::evtx_record::{EvtxRecord, EvtxRecordHeader};
+use crate::evtx_record::{EvtxRecord, EvtxRecordHeader, RecordContext, SerializedEvtxRecord};
 
 use log::{debug, info, trace};
 use std::{
@@ -10,15 +10,22 @@ use std::{
     io::{Read, Seek, SeekFrom},
 };
 
+use crate::binxml::assemble::{scan_event_id, EventIdScan};
 use crate::binxml::deserializer::BinXmlDeserializer;
+use crate::model::deserialized::BinXMLDeserializedTokens;
 use crate::string_cache::StringCache;
-use crate::template_cache::TemplateCache;
-use crate::{ParserSettings, checksum_ieee};
+use crate::template_cache::{TemplateCache, TemplateDescriptor};
+use crate::NullOutput;
+use crate::{ParserSettings, checksum_ieee, FileOffset};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::sync::Arc;
 
-const EVTX_CHUNK_HEADER_SIZE: usize = 512;
+pub(crate) const EVTX_CHUNK_HEADER_SIZE: usize = 512;
+/// Every record starts with this magic, `**\0\0`.
+const RECORD_MAGIC: &[u8] = b"\x2a\x2a\x00\x00";
+/// A record header (24 bytes), followed by the copy of the size (4 bytes).
+const RECORD_MIN_SIZE: u64 = 28;
 
 bitflags! {
     pub struct ChunkFlags: u32 {
@@ -28,6 +35,32 @@ bitflags! {
     }
 }
 
+/// The outcome of verifying one of the CRC32 checksums of a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// The chunk is flagged with `NO_CRC32`, so there is nothing to verify.
+    Disabled,
+    /// The stored checksum matches the data.
+    Valid { value: u32 },
+    /// The stored checksum (`expected`) does not match the one computed over the data (`found`).
+    Mismatch { expected: u32, found: u32 },
+}
+
+impl ChecksumStatus {
+    fn new(expected: u32, found: u32) -> Self {
+        if expected == found {
+            ChecksumStatus::Valid { value: found }
+        } else {
+            ChecksumStatus::Mismatch { expected, found }
+        }
+    }
+
+    /// Whether the checksum is either valid or disabled.
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, ChecksumStatus::Mismatch { .. })
+    }
+}
+
 #[derive(Debug)]
 pub struct EvtxChunkHeader {
     pub first_event_record_number: u64,
@@ -58,6 +91,10 @@ pub struct EvtxChunkHeader {
 pub struct EvtxChunkData {
     pub header: EvtxChunkHeader,
     pub data: Vec<u8>,
+    /// The number of the chunk in the file, `0` for chunks which were not read from a file.
+    pub chunk_number: u64,
+    /// The offset of the chunk in the file, `0` for chunks which were not read from a file.
+    pub file_offset: FileOffset,
 }
 
 impl EvtxChunkData {
@@ -67,60 +104,61 @@ impl EvtxChunkData {
         let mut cursor = Cursor::new(data.as_slice());
         let header = EvtxChunkHeader::from_reader(&mut cursor)?;
 
-        let chunk = EvtxChunkData { header, data };
-        if validate_checksum && !chunk.validate_checksum() {
-            // TODO: return checksum here.
-            return Err(ChunkError::InvalidChunkChecksum {
-                expected: 0,
-                found: 0,
-            });
+        let chunk = EvtxChunkData {
+            header,
+            data,
+            chunk_number: 0,
+            file_offset: 0,
+        };
+        if validate_checksum {
+            if let ChecksumStatus::Mismatch { expected, found } = chunk.header_checksum_status() {
+                return Err(ChunkError::InvalidChunkHeaderChecksum { expected, found });
+            }
+
+            if let ChecksumStatus::Mismatch { expected, found } = chunk.data_checksum_status() {
+                return Err(ChunkError::InvalidChunkChecksum { expected, found });
+            }
         }
 
         Ok(chunk)
     }
 
     /// Require that the settings live at least as long as &self.
-    pub fn parse(&mut self, settings: Arc<ParserSettings>) -> EvtxChunkResult<EvtxChunk> {
-        EvtxChunk::new(&self.data, &self.header, Arc::clone(&settings))
+    pub fn parse(&mut self, settings: Arc<ParserSettings>) -> EvtxChunkResult<EvtxChunk<'_>> {
+        let mut chunk = EvtxChunk::new(&self.data, &self.header, Arc::clone(&settings))?;
+        chunk.chunk_number = self.chunk_number;
+        chunk.file_offset = self.file_offset;
+
+        Ok(chunk)
     }
 
-    pub fn validate_data_checksum(&self) -> bool {
+    /// Verifies the checksum of the event records, which is stored in the chunk header.
+    pub fn data_checksum_status(&self) -> ChecksumStatus {
         debug!("Validating data checksum");
 
-        let checksum_disabled = self.header.flags.contains(ChunkFlags::NO_CRC32);
-
-        let expected_checksum = if !checksum_disabled {
-            self.header.events_checksum
-        } else {
-            0
-        };
+        if self.header.flags.contains(ChunkFlags::NO_CRC32) {
+            return ChecksumStatus::Disabled;
+        }
 
-        let computed_checksum = if !checksum_disabled {
-            checksum_ieee(
-                &self.data[EVTX_CHUNK_HEADER_SIZE..self.header.free_space_offset as usize],
-            )
-        } else {
-            0
-        };
+        let computed_checksum = checksum_ieee(
+            &self.data[EVTX_CHUNK_HEADER_SIZE..self.header.free_space_offset as usize],
+        );
 
         debug!(
             "Expected checksum: {:?}, found: {:?}",
-            expected_checksum, computed_checksum
+            self.header.events_checksum, computed_checksum
         );
 
-        computed_checksum == expected_checksum
+        ChecksumStatus::new(self.header.events_checksum, computed_checksum)
     }
 
-    pub fn validate_header_checksum(&self) -> bool {
+    /// Verifies the checksum of the chunk header.
+    pub fn header_checksum_status(&self) -> ChecksumStatus {
         debug!("Validating header checksum");
 
-        let checksum_disabled = self.header.flags.contains(ChunkFlags::NO_CRC32);
-
-        let expected_checksum = if !checksum_disabled {
-            self.header.header_chunk_checksum
-        } else {
-            0
-        };
+        if self.header.flags.contains(ChunkFlags::NO_CRC32) {
+            return ChecksumStatus::Disabled;
+        }
 
         let header_bytes_1 = &self.data[..120];
         let header_bytes_2 = &self.data[128..512];
@@ -131,18 +169,22 @@ impl EvtxChunkData {
             .cloned()
             .collect();
 
-        let computed_checksum = if !checksum_disabled {
-            checksum_ieee(bytes_for_checksum.as_slice())
-        } else {
-            0
-        };
+        let computed_checksum = checksum_ieee(bytes_for_checksum.as_slice());
 
         debug!(
             "Expected checksum: {:?}, found: {:?}",
-            expected_checksum, computed_checksum
+            self.header.header_chunk_checksum, computed_checksum
         );
 
-        computed_checksum == expected_checksum
+        ChecksumStatus::new(self.header.header_chunk_checksum, computed_checksum)
+    }
+
+    pub fn validate_data_checksum(&self) -> bool {
+        self.data_checksum_status().is_valid()
+    }
+
+    pub fn validate_header_checksum(&self) -> bool {
+        self.header_checksum_status().is_valid()
     }
 
     pub fn validate_checksum(&self) -> bool {
@@ -160,6 +202,10 @@ pub struct EvtxChunk<'chunk> {
     pub header: &'chunk EvtxChunkHeader,
     pub string_cache: StringCache,
     pub template_table: TemplateCache<'chunk>,
+    /// See `EvtxChunkData::chunk_number`.
+    pub chunk_number: u64,
+    /// See `EvtxChunkData::file_offset`.
+    pub file_offset: FileOffset,
 
     pub settings: Arc<ParserSettings>,
 }
@@ -174,18 +220,29 @@ impl<'chunk> EvtxChunk<'chunk> {
         let _cursor = Cursor::new(data);
 
         info!("Initializing string cache");
-        let string_cache = StringCache::populate(data, &header.strings_offsets)
-            .map_err(|e| ChunkError::FailedToBuildStringCache { source: e })?;
+        let string_cache = StringCache::populate(
+            data,
+            &header.strings_offsets,
+            settings.get_on_invalid_utf16(),
+        )
+        .map_err(|e| ChunkError::FailedToBuildStringCache { source: e })?;
 
         info!("Initializing template cache");
-        let template_table =
-            TemplateCache::populate(data, &header.template_offsets, settings.get_ansi_codec())?;
+        let template_table = TemplateCache::populate(
+            data,
+            &header.template_offsets,
+            settings.get_ansi_codec(),
+            settings.get_on_invalid_utf16(),
+            settings.get_shared_template_cache(),
+        )?;
 
         Ok(EvtxChunk {
             header,
             data,
             string_cache,
             template_table,
+            chunk_number: 0,
+            file_offset: 0,
             settings,
         })
     }
@@ -193,14 +250,56 @@ impl<'chunk> EvtxChunk<'chunk> {
     /// Return an iterator of records from the chunk.
     /// See `IterChunkRecords` for a more detailed explanation regarding the lifetime scopes of the
     /// resulting records.
-    pub fn iter(&mut self) -> IterChunkRecords {
+    pub fn iter(&mut self) -> IterChunkRecords<'_> {
         IterChunkRecords {
             settings: Arc::clone(&self.settings),
             chunk: self,
             offset_from_chunk_start: EVTX_CHUNK_HEADER_SIZE as u64,
             exhausted: false,
+            recovering: false,
         }
     }
+
+    /// Return the templates defined in the chunk, ordered by offset.
+    ///
+    /// This describes the structure of the templates without rendering any records,
+    /// which is useful to see which events a chunk can hold.
+    pub fn templates(&self) -> Result<Vec<TemplateDescriptor<'_>>> {
+        let mut templates: Vec<_> = self.template_table.iter().collect();
+        templates.sort_by_key(|(offset, _)| *offset);
+
+        templates
+            .into_iter()
+            .map(|(offset, template)| TemplateDescriptor::new(offset, template, self))
+            .collect()
+    }
+
+    /// Return an iterator over the records of the chunk.
+    /// Records will be XML-formatted.
+    ///
+    /// Together with `EvtxParser::chunks`, this allows parsing chunks with any threading model.
+    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        self.iter()
+            .map(|record| record.and_then(EvtxRecord::into_xml))
+    }
+
+    /// Return an iterator over the records of the chunk.
+    /// Records will be JSON-formatted.
+    pub fn records_json(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        self.iter()
+            .map(|record| record.and_then(EvtxRecord::into_json))
+    }
+
+    /// Return an iterator over the records of the chunk.
+    /// Records will have a `serde_json::Value` data attribute.
+    pub fn records_json_value(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<serde_json::Value>>> + '_ {
+        self.iter()
+            .map(|record| record.and_then(EvtxRecord::into_json_value))
+    }
 }
 
 /// An iterator over a chunk, yielding records.
@@ -227,34 +326,180 @@ pub struct IterChunkRecords<'a> {
     chunk: &'a EvtxChunk<'a>,
     offset_from_chunk_start: u64,
     exhausted: bool,
+    /// Whether the declared records are over, and slack space is being scanned.
+    recovering: bool,
     settings: Arc<ParserSettings>,
 }
 
-impl<'a> Iterator for IterChunkRecords<'a> {
-    type Item = std::result::Result<EvtxRecord<'a>, EvtxError>;
-
-    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        if self.exhausted
-            || self.offset_from_chunk_start >= u64::from(self.chunk.header.free_space_offset)
-        {
-            return None;
+impl<'a> IterChunkRecords<'a> {
+    /// Whether the record passes the `event_id_filter` of the settings, if any.
+    fn matches_filter(&self, record: &EvtxRecord<'a>) -> bool {
+        match self.settings.get_event_id_filter() {
+            None => true,
+            Some(event_ids) => match scan_event_id(&record.tokens, self.chunk) {
+                EventIdScan::Found(event_id) => event_ids.contains(&event_id),
+                EventIdScan::Missing => false,
+                // Let the record be rendered, so that it can be inspected.
+                EventIdScan::Unknown => true,
+            },
         }
+    }
+
+    /// Whether the record header passes the `time_range` of the settings, if any.
+    fn matches_time_range(&self, record_header: &EvtxRecordHeader) -> bool {
+        let (start, end) = self.settings.get_time_range();
 
-        let mut cursor = Cursor::new(&self.chunk.data[self.offset_from_chunk_start as usize..]);
+        !matches!(start, Some(start) if record_header.timestamp < start)
+            && !matches!(end, Some(end) if record_header.timestamp > end)
+    }
+
+    /// Reads the header of the next record, skipping over the records outside of the `time_range`.
+    /// Returns the header along with its size.
+    fn next_record_header(
+        &mut self,
+    ) -> Option<std::result::Result<(EvtxRecordHeader, u64), EvtxError>> {
+        loop {
+            if self.exhausted
+                || self.offset_from_chunk_start >= u64::from(self.chunk.header.free_space_offset)
+            {
+                return None;
+            }
 
-        let record_header = match EvtxRecordHeader::from_reader(&mut cursor) {
-            Ok(record_header) => record_header,
-            Err(err) => {
-                // We currently do not try to recover after an invalid record.
+            let mut cursor = Cursor::new(&self.chunk.data[self.offset_from_chunk_start as usize..]);
+
+            let record_header = match EvtxRecordHeader::from_reader(&mut cursor) {
+                Ok(record_header) => record_header,
+                Err(err) => {
+                    // We currently do not try to recover after an invalid record.
+                    self.exhausted = true;
+
+                    return Some(Err(EvtxError::DeserializationError(err)));
+                }
+            };
+
+            // A smaller record would not move the offset forward (or not past its own header).
+            if u64::from(record_header.data_size) < RECORD_MIN_SIZE {
+                self.exhausted = true;
+
+                return Some(Err(EvtxError::InvalidRecordSize {
+                    record_id: record_header.event_record_id,
+                    file_offset: self.chunk.file_offset + self.offset_from_chunk_start,
+                    data_size: record_header.data_size,
+                }));
+            }
+
+            // Records never span chunks, so reading past the used space would only read garbage.
+            let available =
+                u64::from(self.chunk.header.free_space_offset) - self.offset_from_chunk_start;
+            if u64::from(record_header.data_size) > available {
                 self.exhausted = true;
 
-                return Some(Err(EvtxError::DeserializationError(err)));
+                return Some(Err(EvtxError::TruncatedRecord {
+                    record_id: record_header.event_record_id,
+                    file_offset: self.chunk.file_offset + self.offset_from_chunk_start,
+                    data_size: record_header.data_size,
+                    available,
+                }));
+            }
+
+            if self.matches_time_range(&record_header) {
+                return Some(Ok((record_header, cursor.position())));
             }
+
+            trace!(
+                "Skipping record {} outside of the time range",
+                record_header.event_record_id
+            );
+            self.offset_from_chunk_start += u64::from(record_header.data_size);
+
+            if self.chunk.header.last_event_record_id == record_header.event_record_id {
+                self.exhausted = true;
+            }
+        }
+    }
+
+    fn next_record(&mut self) -> Option<std::result::Result<EvtxRecord<'a>, EvtxError>> {
+        if self.recovering {
+            return self.next_recovered_record();
+        }
+
+        let (record_header, record_header_size) = match self.next_record_header() {
+            Some(Ok(record_header)) => record_header,
+            Some(Err(err)) => return Some(Err(err)),
+            None if self.settings.should_recover_records() => {
+                // Slack space starts where the records declared by the header end.
+                self.recovering = true;
+                self.offset_from_chunk_start = self
+                    .offset_from_chunk_start
+                    .max(u64::from(self.chunk.header.free_space_offset));
+                return self.next_recovered_record();
+            }
+            None => return None,
         };
 
         info!("Record id - {}", record_header.event_record_id);
         debug!("Record header - {:?}", record_header);
 
+        let file_offset = self.chunk.file_offset + self.offset_from_chunk_start;
+
+        if let Some(callback) = self.settings.get_record_callback() {
+            callback(&RecordContext {
+                chunk_number: self.chunk.chunk_number,
+                event_record_id: record_header.event_record_id,
+                file_offset,
+            });
+        }
+
+        let tokens = self.read_tokens(
+            self.offset_from_chunk_start,
+            &record_header,
+            record_header_size,
+            file_offset,
+        );
+        let binxml = self.binxml(
+            self.offset_from_chunk_start,
+            &record_header,
+            record_header_size,
+        );
+        self.offset_from_chunk_start += u64::from(record_header.data_size);
+
+        if self.chunk.header.last_event_record_id == record_header.event_record_id {
+            self.exhausted = true;
+        }
+
+        Some(tokens.map(|tokens| EvtxRecord {
+            chunk: self.chunk,
+            event_record_id: record_header.event_record_id,
+            timestamp: record_header.timestamp,
+            file_offset,
+            tokens,
+            binxml,
+            settings: Arc::clone(&self.settings),
+            recovered: false,
+        }))
+    }
+
+    /// The bytes of the BinXml of the record at `record_offset`, which follows its header.
+    fn binxml(
+        &self,
+        record_offset: u64,
+        record_header: &EvtxRecordHeader,
+        record_header_size: u64,
+    ) -> &'a [u8] {
+        let start = (record_offset + record_header_size) as usize;
+        let end = start + record_header.record_data_size() as usize;
+
+        self.chunk.data.get(start..end).unwrap_or_default()
+    }
+
+    /// Deserializes the BinXml of the record at `record_offset`.
+    fn read_tokens(
+        &self,
+        record_offset: u64,
+        record_header: &EvtxRecordHeader,
+        record_header_size: u64,
+        file_offset: FileOffset,
+    ) -> std::result::Result<Vec<BinXMLDeserializedTokens<'a>>, EvtxError> {
         let binxml_data_size = record_header.record_data_size();
 
         trace!("Need to deserialize {} bytes of binxml", binxml_data_size);
@@ -264,49 +509,131 @@ impl<'a> Iterator for IterChunkRecords<'a> {
         // We avoid creating new references so that `BinXmlDeserializer` can still generate 'a data.
         let deserializer = BinXmlDeserializer::init(
             self.chunk.data,
-            self.offset_from_chunk_start + cursor.position(),
+            record_offset + record_header_size,
             Some(self.chunk),
             false,
             self.settings.get_ansi_codec(),
+            self.settings.get_on_invalid_utf16(),
         );
 
-        let mut tokens = vec![];
-        let iter = match deserializer
-            .iter_tokens(Some(binxml_data_size))
-            .map_err(|e| EvtxError::FailedToParseRecord {
-                record_id: record_header.event_record_id,
-                source: Box::new(EvtxError::DeserializationError(e)),
-            }) {
-            Ok(iter) => iter,
-            Err(err) => return Some(Err(err)),
+        let to_record_error = |e| EvtxError::FailedToParseRecord {
+            record_id: record_header.event_record_id,
+            file_offset,
+            source: Box::new(EvtxError::DeserializationError(e)),
         };
 
-        for token in iter {
-            match token.map_err(|e| EvtxError::FailedToParseRecord {
-                source: Box::new(EvtxError::DeserializationError(e)),
-                record_id: record_header.event_record_id,
-            }) {
-                Ok(token) => tokens.push(token),
+        deserializer
+            .iter_tokens(Some(binxml_data_size))
+            .map_err(to_record_error)?
+            .map(|token| token.map_err(to_record_error))
+            .collect()
+    }
+
+    /// Looks for records in the slack space of the chunk, past its last record.
+    ///
+    /// Candidates are found by their magic, and must have a matching copy of their size.
+    /// The scan never reads past the end of the chunk, and candidates which fail to
+    /// deserialize are skipped silently.
+    fn next_recovered_record(&mut self) -> Option<std::result::Result<EvtxRecord<'a>, EvtxError>> {
+        let data = self.chunk.data;
+
+        while self.offset_from_chunk_start + RECORD_MIN_SIZE <= data.len() as u64 {
+            let offset = self.offset_from_chunk_start as usize;
+
+            let candidate = match data[offset..].windows(4).position(|w| w == RECORD_MAGIC) {
+                Some(position) => offset + position,
+                None => break,
+            };
+            // Move past the magic, in case this is not a record.
+            self.offset_from_chunk_start = (candidate + 1) as u64;
+
+            let mut cursor = Cursor::new(&data[candidate..]);
+            let record_header = match EvtxRecordHeader::from_reader(&mut cursor) {
+                Ok(record_header) => record_header,
+                Err(_) => continue,
+            };
+
+            let size = record_header.data_size as usize;
+            if (size as u64) < RECORD_MIN_SIZE
+                || candidate + size > data.len()
+                || data[candidate + size - 4..candidate + size]
+                    != record_header.data_size.to_le_bytes()
+                || !self.matches_time_range(&record_header)
+            {
+                continue;
+            }
+
+            let file_offset = self.chunk.file_offset + candidate as u64;
+            let tokens = match self.read_tokens(
+                candidate as u64,
+                &record_header,
+                cursor.position(),
+                file_offset,
+            ) {
+                Ok(tokens) => tokens,
                 Err(err) => {
-                    self.offset_from_chunk_start += u64::from(record_header.data_size);
-                    return Some(Err(err));
+                    trace!("Record candidate at {} is invalid: {}", file_offset, err);
+                    continue;
                 }
+            };
+            let record = EvtxRecord {
+                chunk: self.chunk,
+                event_record_id: record_header.event_record_id,
+                timestamp: record_header.timestamp,
+                file_offset,
+                tokens,
+                binxml: self.binxml(candidate as u64, &record_header, cursor.position()),
+                settings: Arc::clone(&self.settings),
+                recovered: true,
+            };
+
+            // Slack space is often partly overwritten, so only keep records which render.
+            let mut output = NullOutput::with_writer(std::io::sink(), &self.settings);
+            if let Err(err) = record
+                .clone()
+                .into_output(&mut output)
+                .and_then(|()| Ok(output.into_writer()?))
+            {
+                trace!(
+                    "Record candidate at {} does not render: {}",
+                    file_offset,
+                    err
+                );
+                continue;
+            }
+            self.offset_from_chunk_start = (candidate + size) as u64;
+
+            info!(
+                "Recovered record {} at offset {}",
+                record_header.event_record_id, file_offset
+            );
+
+            if let Some(callback) = self.settings.get_record_callback() {
+                callback(&RecordContext {
+                    chunk_number: self.chunk.chunk_number,
+                    event_record_id: record_header.event_record_id,
+                    file_offset,
+                });
             }
+
+            return Some(Ok(record));
         }
 
-        self.offset_from_chunk_start += u64::from(record_header.data_size);
+        self.offset_from_chunk_start = data.len() as u64;
+        None
+    }
+}
 
-        if self.chunk.header.last_event_record_id == record_header.event_record_id {
-            self.exhausted = true;
-        }
+impl<'a> Iterator for IterChunkRecords<'a> {
+    type Item = std::result::Result<EvtxRecord<'a>, EvtxError>;
 
-        Some(Ok(EvtxRecord {
-            chunk: self.chunk,
-            event_record_id: record_header.event_record_id,
-            timestamp: record_header.timestamp,
-            tokens,
-            settings: Arc::clone(&self.settings),
-        }))
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        loop {
+            match self.next_record()? {
+                Ok(record) if !self.matches_filter(&record) => continue,
+                record => return Some(record),
+            }
+        }
     }
 }
 
@@ -436,4 +763,45 @@ mod tests {
         let chunk = EvtxChunkData::new(chunk_data, false).unwrap();
         assert!(chunk.validate_checksum());
     }
+
+    #[test]
+    fn test_checksum_status() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut chunk_data =
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+
+        let chunk = EvtxChunkData::new(chunk_data.clone(), true).unwrap();
+        assert_eq!(
+            chunk.header_checksum_status(),
+            ChecksumStatus::Valid { value: 978_805_790 }
+        );
+        assert_eq!(
+            chunk.data_checksum_status(),
+            ChecksumStatus::Valid {
+                value: 4_252_479_141
+            }
+        );
+
+        // Corrupt the first record.
+        chunk_data[EVTX_CHUNK_HEADER_SIZE + 32] ^= 0xFF;
+
+        let chunk = EvtxChunkData::new(chunk_data.clone(), false).unwrap();
+        assert!(chunk.header_checksum_status().is_valid());
+        assert!(matches!(
+            chunk.data_checksum_status(),
+            ChecksumStatus::Mismatch {
+                expected: 4_252_479_141,
+                found,
+            } if found != 4_252_479_141
+        ));
+
+        assert!(matches!(
+            EvtxChunkData::new(chunk_data, true),
+            Err(ChunkError::InvalidChunkChecksum {
+                expected: 4_252_479_141,
+                ..
+            })
+        ));
+    }
 }
@@ -28,6 +28,16 @@ bitflags! {
 }
 
 impl EvtxFileHeader {
+    /// Whether the log was not closed cleanly, and the header may lag behind the chunks.
+    pub fn is_dirty(&self) -> bool {
+        self.flags.contains(HeaderFlags::DIRTY)
+    }
+
+    /// Whether the log reached its maximum size.
+    pub fn is_full(&self) -> bool {
+        self.flags.contains(HeaderFlags::FULL)
+    }
+
     pub fn from_stream<T: Read + Seek>(stream: &mut T) -> DeserializationResult<EvtxFileHeader> {
         let mut magic = [0_u8; 8];
         stream.take(8).read_exact(&mut magic).map_err(|e| {
@@ -102,5 +112,7 @@ mod tests {
                 checksum: checksum_ieee(&evtx_file[..120]),
             }
         );
+        assert!(parsing_result.is_dirty());
+        assert!(!parsing_result.is_full());
     }
 }
@@ -1,32 +1,59 @@
+use crate::binxml::value_variant::{BinaryEncoding, ValueFormatter};
 use crate::err::{ChunkError, EvtxError, InputError, Result};
 
-use crate::evtx_chunk::EvtxChunkData;
+use crate::evtx_chunk::{EvtxChunkData, EvtxChunkHeader, EVTX_CHUNK_HEADER_SIZE};
 use crate::evtx_file_header::EvtxFileHeader;
-use crate::evtx_record::SerializedEvtxRecord;
+use crate::evtx_record::{RecordContext, RecordPlaceholder, SerializedEvtxRecord};
+use crate::json_output::{ControlCharPolicy, EmptyElementStyle, JsonCompatibility, JsonIndent};
+use crate::template_cache::SharedTemplateCache;
+use crate::tree_output::TreeElement;
+use crate::utils::OnInvalidUtf16;
 #[cfg(feature = "multithreading")]
 use rayon::prelude::*;
 
-use log::trace;
-#[cfg(not(feature = "multithreading"))]
-use log::warn;
+use chrono::{DateTime, Utc};
 
-use log::{debug, info};
+use log::trace;
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
 use std::fs::File;
 use std::io::{self, Cursor, Read, Seek, SeekFrom};
 
 use crate::EvtxRecord;
 use encoding::all::WINDOWS_1252;
 use encoding::EncodingRef;
+use std::borrow::Cow;
 use std::cmp::max;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Debug;
 use std::iter::{IntoIterator, Iterator};
 use std::path::Path;
+#[cfg(feature = "multithreading")]
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 
+/// The size of every chunk.
+///
+/// The file header does not declare a chunk size (its size fields are `header_size`
+/// and `header_block_size`, which describe the header itself), and the offsets of records
+/// are only checked against this size.
 pub const EVTX_CHUNK_SIZE: usize = 65536;
 pub const EVTX_FILE_HEADER_SIZE: usize = 4096;
 
+/// The attributes written as numbers by `ParserSettings::numeric_json_attributes`.
+const NUMERIC_SYSTEM_ATTRIBUTES: [&str; 8] = [
+    "Qualifiers",
+    "Version",
+    "Level",
+    "Task",
+    "Opcode",
+    "Keywords",
+    "ProcessID",
+    "ThreadID",
+];
+
 // Stable shim until https://github.com/rust-lang/rust/issues/59359 is merged.
 // Taken from proposed std code.
 pub trait ReadSeek: Read + Seek {
@@ -107,6 +134,46 @@ impl<T: ReadSeek> Debug for EvtxParser<T> {
     }
 }
 
+/// What the record iterators of `EvtxParser` do with records (or chunks) which fail to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Yield an `Err` and continue with the next record.
+    #[default]
+    Yield,
+    /// Log the error and continue with the next record, only `Ok` records are yielded.
+    Skip,
+    /// Yield the first `Err`, and stop.
+    FailFast,
+    /// Yield a `RecordPlaceholder` describing the error in place of the record, and continue.
+    /// Only applies to `records`, `records_json` and `records_json_value` (and their `par_`
+    /// variants). Errors which are not tied to a single record (like a corrupt chunk) are yielded.
+    Placeholder,
+}
+
+/// How many records a file has, for each `System/EventID`, see `EvtxParser::summarize`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordSummary {
+    /// Every record which was read, including the ones without an id.
+    pub total_records: u64,
+    pub event_ids: BTreeMap<u32, u64>,
+    /// Records without an `EventID`, or whose id could not be read.
+    pub records_without_event_id: u64,
+    /// Errors yielded while reading the records (see `ErrorPolicy`), which are not counted
+    /// in `total_records`.
+    pub errors: u64,
+}
+
+/// Called with the location of every record, as it begins.
+pub type RecordCallback = Arc<dyn Fn(&RecordContext) + Send + Sync>;
+
+/// Called with the path and the text of every value, returns a replacement for the value,
+/// or `None` to keep it as is.
+pub type ValueTransform = Arc<dyn for<'v> Fn(&str, &'v str) -> Option<Cow<'v, str>> + Send + Sync>;
+
+/// Called with the provider name, the event id and the ordered substitution values of every
+/// record, returns the formatted message of the event, if it is known.
+pub type MessageResolver = Arc<dyn Fn(&str, u32, &[String]) -> Option<String> + Send + Sync>;
+
 #[derive(Clone)]
 pub struct ParserSettings {
     /// Controls the number of threads used for parsing chunks concurrently.
@@ -132,10 +199,83 @@ pub struct ParserSettings {
     ///   }
     /// }
     separate_json_attributes: bool,
+    /// If enabled, XML attributes will be written in JSON next to their element,
+    /// each under its own key. Example:
+    /// {
+    ///   "EventID": 4111,
+    ///   "EventID_Qualifiers": 16384
+    /// }
+    flatten_json_attributes: bool,
+    /// The key under which attributes are written in JSON, `#attributes` by default.
+    attribute_key: String,
+    /// The key under which text is written in JSON for elements which also have attributes,
+    /// `#text` by default.
+    text_key: String,
     /// If true, output will be indented.
     indent: bool,
+    /// How JSON is indented when `indent` is on.
+    json_indent: JsonIndent,
+    /// If true, every XML record starts with an `<?xml ...?>` declaration.
+    /// Disable to concatenate records under a root element of your own.
+    xml_declaration: bool,
+    /// If true, `SystemTime` attributes are re-serialized in JSON as canonical RFC3339 timestamps.
+    normalize_timestamps: bool,
+    /// If true, `xmlns` attributes are dropped and `prefix:` is trimmed from element names in JSON.
+    strip_namespaces: bool,
+    /// Binary values longer than this many bytes are truncated in the output, unlimited by default.
+    binary_byte_limit: Option<usize>,
+    /// How binary values are written, hex by default.
+    binary_encoding: BinaryEncoding,
+    /// How empty elements are rendered in JSON, `null` by default.
+    empty_element_style: EmptyElementStyle,
+    /// What is done with control characters in JSON strings, see `ControlCharPolicy`.
+    control_char_policy: ControlCharPolicy,
+    /// The layout of JSON records, see `JsonCompatibility`.
+    json_compatibility: JsonCompatibility,
+    /// The element names leading to the only subtree rendered in JSON, the whole record by default.
+    json_root_path: Option<Vec<String>>,
+    /// If true, the children of `EventData` and `UserData` are sorted by key in JSON.
+    canonicalize_json: bool,
+    /// If true, structural element and attribute names are matched ignoring ASCII case in JSON.
+    ascii_case_insensitive: bool,
+    /// If true, JSON records start with `_record_id` and `_timestamp` keys from the record header.
+    record_header_fields: bool,
+    /// If true, records which JSON can't represent as they are fail instead of being adapted.
+    strict_json: bool,
+    /// If true, text which is only made of whitespace is left out of JSON records.
+    trim_whitespace: bool,
+    /// If true, `System/Level` and `System/Opcode` get `LevelName` and `OpcodeName` siblings in JSON.
+    symbolic_system_names: bool,
+    /// If true, `System/Keywords` is written as a zero-padded hex string in JSON.
+    hex_keywords: bool,
+    /// Attributes whose textual values are written as JSON numbers, if set.
+    numeric_json_attributes: Option<HashSet<String>>,
+    /// The deepest nesting of elements allowed in JSON output, unlimited by default.
+    max_depth: Option<usize>,
+    /// The most bytes a single record may render to, unlimited by default.
+    max_output_bytes: Option<usize>,
+    /// Templates parsed by one chunk and reused by the others, if enabled.
+    template_cache: Option<Arc<SharedTemplateCache>>,
+    /// If set, only records with one of these `System/EventID`s are returned.
+    event_id_filter: Option<HashSet<u32>>,
+    /// If set, only records written within these (inclusive) bounds are returned.
+    time_range: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    /// If true, the slack space of chunks is scanned for leftover records.
+    recover_records: bool,
+    /// What to do with records which fail to parse, see `ErrorPolicy`.
+    error_policy: ErrorPolicy,
+    /// Called as each record begins, see `RecordContext`.
+    record_callback: Option<RecordCallback>,
+    /// Rewrites values before they are output, see `ParserSettings::value_transform`.
+    value_transform: Option<ValueTransform>,
+    /// Writes GUIDs, timestamps, SIDs and hex integers, see `ParserSettings::value_formatter`.
+    value_formatter: Option<Arc<dyn ValueFormatter>>,
+    /// Formats the message of events, see `ParserSettings::message_resolver`.
+    message_resolver: Option<MessageResolver>,
     /// Controls the ansi codec used to deserialize ansi strings inside the xml document.
     ansi_codec: EncodingRef,
+    /// What to do with invalid UTF-16 in names and string values, see `OnInvalidUtf16`.
+    on_invalid_utf16: OnInvalidUtf16,
 }
 
 impl Debug for ParserSettings {
@@ -144,8 +284,41 @@ impl Debug for ParserSettings {
             .field("num_threads", &self.num_threads)
             .field("validate_checksums", &self.validate_checksums)
             .field("separate_json_attributes", &self.separate_json_attributes)
+            .field("flatten_json_attributes", &self.flatten_json_attributes)
+            .field("attribute_key", &self.attribute_key)
+            .field("text_key", &self.text_key)
             .field("indent", &self.indent)
+            .field("json_indent", &self.json_indent)
+            .field("xml_declaration", &self.xml_declaration)
+            .field("normalize_timestamps", &self.normalize_timestamps)
+            .field("strip_namespaces", &self.strip_namespaces)
+            .field("binary_byte_limit", &self.binary_byte_limit)
+            .field("binary_encoding", &self.binary_encoding)
+            .field("empty_element_style", &self.empty_element_style)
+            .field("control_char_policy", &self.control_char_policy)
+            .field("json_compatibility", &self.json_compatibility)
+            .field("json_root_path", &self.json_root_path)
+            .field("canonicalize_json", &self.canonicalize_json)
+            .field("ascii_case_insensitive", &self.ascii_case_insensitive)
+            .field("strict_json", &self.strict_json)
+            .field("trim_whitespace", &self.trim_whitespace)
+            .field("symbolic_system_names", &self.symbolic_system_names)
+            .field("hex_keywords", &self.hex_keywords)
+            .field("numeric_json_attributes", &self.numeric_json_attributes)
+            .field("record_header_fields", &self.record_header_fields)
+            .field("max_depth", &self.max_depth)
+            .field("max_output_bytes", &self.max_output_bytes)
+            .field("template_cache", &self.template_cache.is_some())
+            .field("event_id_filter", &self.event_id_filter)
+            .field("time_range", &self.time_range)
+            .field("recover_records", &self.recover_records)
+            .field("error_policy", &self.error_policy)
+            .field("record_callback", &self.record_callback.is_some())
+            .field("value_transform", &self.value_transform.is_some())
+            .field("value_formatter", &self.value_formatter.is_some())
+            .field("message_resolver", &self.message_resolver.is_some())
             .field("ansi_codec", &self.ansi_codec.name())
+            .field("on_invalid_utf16", &self.on_invalid_utf16)
             .finish()
     }
 }
@@ -153,10 +326,59 @@ impl Debug for ParserSettings {
 impl PartialEq for ParserSettings {
     fn eq(&self, other: &ParserSettings) -> bool {
         self.ansi_codec.name() == other.ansi_codec.name()
+            && self.on_invalid_utf16 == other.on_invalid_utf16
             && self.num_threads == other.num_threads
             && self.validate_checksums == other.validate_checksums
             && self.separate_json_attributes == other.separate_json_attributes
+            && self.flatten_json_attributes == other.flatten_json_attributes
+            && self.attribute_key == other.attribute_key
+            && self.text_key == other.text_key
             && self.indent == other.indent
+            && self.json_indent == other.json_indent
+            && self.xml_declaration == other.xml_declaration
+            && self.normalize_timestamps == other.normalize_timestamps
+            && self.strip_namespaces == other.strip_namespaces
+            && self.binary_byte_limit == other.binary_byte_limit
+            && self.binary_encoding == other.binary_encoding
+            && self.empty_element_style == other.empty_element_style
+            && self.control_char_policy == other.control_char_policy
+            && self.json_compatibility == other.json_compatibility
+            && self.json_root_path == other.json_root_path
+            && self.canonicalize_json == other.canonicalize_json
+            && self.ascii_case_insensitive == other.ascii_case_insensitive
+            && self.strict_json == other.strict_json
+            && self.trim_whitespace == other.trim_whitespace
+            && self.symbolic_system_names == other.symbolic_system_names
+            && self.hex_keywords == other.hex_keywords
+            && self.numeric_json_attributes == other.numeric_json_attributes
+            && self.record_header_fields == other.record_header_fields
+            && self.max_depth == other.max_depth
+            && self.max_output_bytes == other.max_output_bytes
+            && self.template_cache.is_some() == other.template_cache.is_some()
+            && self.event_id_filter == other.event_id_filter
+            && self.time_range == other.time_range
+            && self.recover_records == other.recover_records
+            && self.error_policy == other.error_policy
+            && match (&self.record_callback, &other.record_callback) {
+                (Some(callback), Some(other_callback)) => Arc::ptr_eq(callback, other_callback),
+                (None, None) => true,
+                _ => false,
+            }
+            && match (&self.value_transform, &other.value_transform) {
+                (Some(transform), Some(other_transform)) => Arc::ptr_eq(transform, other_transform),
+                (None, None) => true,
+                _ => false,
+            }
+            && match (&self.value_formatter, &other.value_formatter) {
+                (Some(formatter), Some(other_formatter)) => Arc::ptr_eq(formatter, other_formatter),
+                (None, None) => true,
+                _ => false,
+            }
+            && match (&self.message_resolver, &other.message_resolver) {
+                (Some(resolver), Some(other_resolver)) => Arc::ptr_eq(resolver, other_resolver),
+                (None, None) => true,
+                _ => false,
+            }
     }
 }
 
@@ -166,8 +388,41 @@ impl Default for ParserSettings {
             num_threads: 0,
             validate_checksums: false,
             separate_json_attributes: false,
+            flatten_json_attributes: false,
+            attribute_key: "#attributes".to_owned(),
+            text_key: "#text".to_owned(),
             indent: true,
+            json_indent: JsonIndent::default(),
+            xml_declaration: true,
+            normalize_timestamps: false,
+            strip_namespaces: false,
+            binary_byte_limit: None,
+            binary_encoding: BinaryEncoding::default(),
+            empty_element_style: EmptyElementStyle::default(),
+            control_char_policy: ControlCharPolicy::default(),
+            json_compatibility: JsonCompatibility::default(),
+            json_root_path: None,
+            canonicalize_json: false,
+            ascii_case_insensitive: false,
+            strict_json: false,
+            trim_whitespace: false,
+            symbolic_system_names: false,
+            hex_keywords: false,
+            numeric_json_attributes: None,
+            record_header_fields: false,
+            max_depth: None,
+            max_output_bytes: None,
+            template_cache: None,
+            event_id_filter: None,
+            time_range: (None, None),
+            recover_records: false,
+            error_policy: ErrorPolicy::default(),
+            record_callback: None,
+            value_transform: None,
+            value_formatter: None,
+            message_resolver: None,
             ansi_codec: WINDOWS_1252,
+            on_invalid_utf16: OnInvalidUtf16::default(),
         }
     }
 }
@@ -206,6 +461,14 @@ impl ParserSettings {
         self
     }
 
+    /// Sets how invalid UTF-16 (unpaired surrogates) in element and attribute names,
+    /// and in string values, is decoded. By default it fails the record.
+    pub fn on_invalid_utf16(mut self, on_invalid_utf16: OnInvalidUtf16) -> Self {
+        self.on_invalid_utf16 = on_invalid_utf16;
+
+        self
+    }
+
     pub fn validate_checksums(mut self, validate_checksums: bool) -> Self {
         self.validate_checksums = validate_checksums;
 
@@ -218,25 +481,490 @@ impl ParserSettings {
         self
     }
 
+    /// Writes each attribute of an element as a sibling `{element}_{attribute}` key,
+    /// with the text of the element under the element's own name, so that no `#attributes`
+    /// or `#text` keys are produced.
+    ///
+    /// `<EventID Qualifiers="0">4902</EventID>` becomes `{"EventID": 4902, "EventID_Qualifiers": 0}`.
+    /// Takes precedence over `separate_json_attributes`.
+    pub fn flatten_json_attributes(mut self, flatten: bool) -> Self {
+        self.flatten_json_attributes = flatten;
+
+        self
+    }
+
+    /// Sets the key used for attributes in JSON, for example `@attributes`.
+    pub fn attribute_key(mut self, attribute_key: impl Into<String>) -> Self {
+        self.attribute_key = attribute_key.into();
+
+        self
+    }
+
+    /// Sets the key used for text in JSON, for example `$text`.
+    pub fn text_key(mut self, text_key: impl Into<String>) -> Self {
+        self.text_key = text_key.into();
+
+        self
+    }
+
     pub fn indent(mut self, pretty: bool) -> Self {
         self.indent = pretty;
 
         self
     }
 
+    /// Sets how JSON records are indented, two spaces per level by default.
+    /// Has no effect if `indent` is off, in which case JSON is always compact.
+    pub fn json_indent(mut self, json_indent: JsonIndent) -> Self {
+        self.json_indent = json_indent;
+
+        self
+    }
+
+    pub fn xml_declaration(mut self, xml_declaration: bool) -> Self {
+        self.xml_declaration = xml_declaration;
+
+        self
+    }
+
+    /// Parses `SystemTime` attributes and writes them to JSON as `2021-03-01T12:34:56.789000Z`.
+    /// Malformed timestamps fail the record.
+    pub fn normalize_timestamps(mut self, normalize_timestamps: bool) -> Self {
+        self.normalize_timestamps = normalize_timestamps;
+
+        self
+    }
+
+    /// Drops `xmlns` and `xmlns:*` attributes, and trims any `prefix:` from element names in JSON.
+    pub fn strip_namespaces(mut self, strip_namespaces: bool) -> Self {
+        self.strip_namespaces = strip_namespaces;
+
+        self
+    }
+
+    /// Truncates binary values to their first `limit` bytes in both XML and JSON,
+    /// their hex representation then ends with `...`.
+    pub fn binary_byte_limit(mut self, limit: usize) -> Self {
+        self.binary_byte_limit = Some(limit);
+
+        self
+    }
+
+    /// Writes binary values as `encoding` in both XML and JSON, instead of hex.
+    /// Truncated values (see `binary_byte_limit`) are encoded the same way.
+    pub fn binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+
+        self
+    }
+
+    /// Sets how elements without attributes or content are rendered in JSON.
+    pub fn empty_element_as(mut self, empty_element_style: EmptyElementStyle) -> Self {
+        self.empty_element_style = empty_element_style;
+
+        self
+    }
+
+    /// Sets what is done with control characters in the strings of JSON records,
+    /// they are escaped by default.
+    pub fn control_char_policy(mut self, control_char_policy: ControlCharPolicy) -> Self {
+        self.control_char_policy = control_char_policy;
+
+        self
+    }
+
+    /// Sets the layout of JSON records, for tooling expecting the keys of other EVTX converters.
+    pub fn json_compatibility(mut self, json_compatibility: JsonCompatibility) -> Self {
+        self.json_compatibility = json_compatibility;
+
+        self
+    }
+
+    /// Renders only the subtree at `root_path` in JSON, for example `Event/EventData`.
+    /// Elements outside of it are skipped before they are built, and records without it are `null`.
+    /// Takes precedence over `json_compatibility`, an empty path renders the whole record.
+    pub fn json_root_path(mut self, root_path: &str) -> Self {
+        let root_path: Vec<String> = root_path
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+            .collect();
+        self.json_root_path = Some(root_path).filter(|root_path| !root_path.is_empty());
+
+        self
+    }
+
+    /// If true, sorts the children of `EventData` (and of the element inside of `UserData`)
+    /// lexicographically by key in JSON, so that records with the same fields serialize
+    /// to the same bytes regardless of the order the provider wrote them in.
+    /// Other elements keep their document order.
+    pub fn canonicalize_json(mut self, canonicalize_json: bool) -> Self {
+        self.canonicalize_json = canonicalize_json;
+
+        self
+    }
+
+    /// If true, the names which give JSON its structure are matched ignoring ASCII case,
+    /// for logs which spell them inconsistently: `<data name="User">` is rendered like
+    /// `<Data Name="User">`, and `eventData` is sorted by `canonicalize_json` like `EventData`.
+    /// The names themselves are written as they are in the record.
+    pub fn ascii_case_insensitive(mut self, ascii_case_insensitive: bool) -> Self {
+        self.ascii_case_insensitive = ascii_case_insensitive;
+
+        self
+    }
+
+    /// If true, records are rendered to JSON exactly as they are or fail, instead of adapting
+    /// records which don't fit the usual shape of an event:
+    /// a `Data` element without a `Name` attribute fails with `UnnamedDataNode`,
+    /// a repeated element which would be renamed (`Header_1`) fails with `DuplicateElement`,
+    /// and a repeated or empty attribute name (`Name_2`) fails with `DuplicateAttribute`.
+    pub fn strict_json(mut self, strict_json: bool) -> Self {
+        self.strict_json = strict_json;
+
+        self
+    }
+
+    /// If true, text which is only made of whitespace (like the indentation of
+    /// `<EventData>\n  <Data>...</Data>\n</EventData>`) is left out of JSON records,
+    /// so that such elements don't get a `#text` of newlines, or a whitespace value.
+    /// Whitespace following other text of the same element is kept.
+    pub fn trim_whitespace(mut self, trim_whitespace: bool) -> Self {
+        self.trim_whitespace = trim_whitespace;
+
+        self
+    }
+
+    /// If true, the standard names of the numeric `System/Level` and `System/Opcode` are added
+    /// to JSON records, as the `LevelName` and `OpcodeName` siblings of those elements:
+    /// `"Level": 4` -> `"Level": 4, "LevelName": "Information"`.
+    /// Values without a standard name (such as provider defined opcodes) get no sibling.
+    pub fn symbolic_system_names(mut self, symbolic_system_names: bool) -> Self {
+        self.symbolic_system_names = symbolic_system_names;
+
+        self
+    }
+
+    /// If true, the `System/Keywords` bitmask of JSON records is written as a hex string padded
+    /// to 16 digits, like `wevtutil` shows it: `"Keywords": "0x8020000000000000"`.
+    /// Keywords are read from hex or decimal text (or numbers), other values are left as is.
+    pub fn hex_keywords(mut self, hex_keywords: bool) -> Self {
+        self.hex_keywords = hex_keywords;
+
+        self
+    }
+
+    /// If true, the usual numeric attributes of the `System` element (such as `Qualifiers`,
+    /// `Version`, `ProcessID` and `ThreadID`) are written as JSON numbers even when they were
+    /// stored as text, see `numeric_json_attribute_names` to choose the attributes.
+    /// Values which are not integers (decimal, or hex with a `0x` prefix) are kept as strings.
+    pub fn numeric_json_attributes(mut self, numeric_json_attributes: bool) -> Self {
+        self.numeric_json_attributes = numeric_json_attributes.then(|| {
+            NUMERIC_SYSTEM_ATTRIBUTES
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        });
+
+        self
+    }
+
+    /// Like `numeric_json_attributes`, but for the attributes named `names`.
+    pub fn numeric_json_attribute_names(mut self, names: &[&str]) -> Self {
+        self.numeric_json_attributes = Some(names.iter().map(|name| name.to_string()).collect());
+
+        self
+    }
+
+    /// If true, JSON records start with a `_record_id` and a `_timestamp` (RFC3339) key,
+    /// read from the binary record header rather than from the `System` element.
+    /// These are always present, even for records whose `System` element is incomplete,
+    /// and the header's id may differ from `System/EventRecordID`.
+    pub fn record_header_fields(mut self, record_header_fields: bool) -> Self {
+        self.record_header_fields = record_header_fields;
+
+        self
+    }
+
+    /// Fails records which nest elements deeper than `max_depth` when rendering them as JSON,
+    /// to guard against pathological (corrupt or crafted) records.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+
+        self
+    }
+
+    /// If true, templates are parsed once and reused by the chunks which define them identically,
+    /// instead of being parsed again by every chunk. This speeds up logs which repeat the same
+    /// events, and doesn't change the output.
+    ///
+    /// The cache is shared by clones of these settings, and emptied when this is called again.
+    pub fn template_cache(mut self, template_cache: bool) -> Self {
+        self.template_cache = Some(Arc::new(SharedTemplateCache::new())).filter(|_| template_cache);
+
+        self
+    }
+
+    /// Fails records which render to more than `max_output_bytes`, so that a single corrupt
+    /// record (for example a substitution loop) cannot exhaust memory.
+    ///
+    /// XML output is counted as it is written, JSON output counts the element names,
+    /// attributes and text as the record is built.
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_output_bytes);
+
+        self
+    }
+
+    /// Only returns records whose `System/EventID` is one of `event_ids`.
+    /// Other records are skipped before they are rendered, as soon as their id is known.
+    pub fn event_id_filter(mut self, event_ids: &[u32]) -> Self {
+        self.event_id_filter = Some(event_ids.iter().copied().collect());
+
+        self
+    }
+
+    /// Only returns records whose timestamp is between `start` and `end` (both inclusive),
+    /// a missing bound leaves that side of the range open.
+    /// The timestamp of the record header is used, so other records are skipped before any
+    /// of their BinXml is decoded.
+    pub fn time_range(mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Self {
+        self.time_range = (start, end);
+
+        self
+    }
+
+    /// Also returns the records left in the slack space of chunks, past their last record,
+    /// such as records which were deleted or only partly overwritten.
+    ///
+    /// Recovered records are flagged with `recovered`. They are found by their magic and size,
+    /// and are only returned if they deserialize, but they may still be incomplete or stale.
+    pub fn recover_records(mut self, recover_records: bool) -> Self {
+        self.recover_records = recover_records;
+
+        self
+    }
+
+    /// Sets what the record iterators do with records which fail to parse,
+    /// either fail-fast or best-effort. Errors are yielded, and iteration continues by default.
+    pub fn on_error(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+
+        self
+    }
+
+    /// Sets a callback which is called with the chunk number, record id and file offset of
+    /// every record as it begins, wherever records are iterated.
+    /// When parsing with multiple threads, it may be called concurrently and out of order.
+    pub fn record_callback(
+        mut self,
+        callback: impl Fn(&RecordContext) + Send + Sync + 'static,
+    ) -> Self {
+        self.record_callback = Some(Arc::new(callback));
+
+        self
+    }
+
+    /// Sets a transform which can rewrite values (for example to redact user names or hosts)
+    /// before they are written to any output.
+    ///
+    /// It is called with the path of every element text and attribute value, and with the value
+    /// rendered as text. Returning `Some` substitutes the value with the returned string,
+    /// returning `None` keeps the original value (and its type).
+    ///
+    /// Paths are the names of the elements from the root, separated by `/`, where `Data` elements
+    /// are named after their `Name` attribute like in JSON, and attributes are prefixed by `@`:
+    /// `Event/EventData/TargetUserName`, `Event/System/Provider/@Name`.
+    pub fn value_transform(
+        mut self,
+        transform: impl for<'v> Fn(&str, &'v str) -> Option<Cow<'v, str>> + Send + Sync + 'static,
+    ) -> Self {
+        self.value_transform = Some(Arc::new(transform));
+
+        self
+    }
+
+    /// Sets how GUIDs, timestamps, SIDs and hex integers are written, in every output.
+    ///
+    /// Values of these types are written as strings (or arrays of strings) made by `formatter`,
+    /// before they are passed to `value_transform`. See `ValueFormatter` for the defaults.
+    pub fn value_formatter(mut self, formatter: impl ValueFormatter + 'static) -> Self {
+        self.value_formatter = Some(Arc::new(formatter));
+
+        self
+    }
+
+    /// Sets a resolver for the human readable message of events, which the crate can't format
+    /// by itself since the message tables are part of the provider's binaries.
+    ///
+    /// It is called with the `Name` of the `Provider`, the `EventID` and the values of the
+    /// `EventData` (or `UserData`) fields in order, so that `%1` is `values[0]`.
+    /// When it returns `Some`, the message is written to the record as
+    /// `Event/RenderingInfo/Message`, like in events rendered by Windows.
+    /// Records which already have a `RenderingInfo` (like forwarded events) are left as is.
+    pub fn message_resolver(
+        mut self,
+        resolver: impl Fn(&str, u32, &[String]) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.message_resolver = Some(Arc::new(resolver));
+
+        self
+    }
+
     /// Gets the current ansi codec
     pub fn get_ansi_codec(&self) -> EncodingRef {
         self.ansi_codec
     }
 
+    pub fn get_on_invalid_utf16(&self) -> OnInvalidUtf16 {
+        self.on_invalid_utf16
+    }
+
     pub fn should_separate_json_attributes(&self) -> bool {
         self.separate_json_attributes
     }
 
+    pub fn should_flatten_json_attributes(&self) -> bool {
+        self.flatten_json_attributes
+    }
+
+    pub fn get_attribute_key(&self) -> &str {
+        &self.attribute_key
+    }
+
+    pub fn get_text_key(&self) -> &str {
+        &self.text_key
+    }
+
     pub fn should_indent(&self) -> bool {
         self.indent
     }
 
+    /// The indentation of JSON records, `Compact` if `indent` is off.
+    pub fn get_json_indent(&self) -> JsonIndent {
+        if self.indent {
+            self.json_indent
+        } else {
+            JsonIndent::Compact
+        }
+    }
+
+    pub fn should_emit_xml_declaration(&self) -> bool {
+        self.xml_declaration
+    }
+
+    pub fn should_normalize_timestamps(&self) -> bool {
+        self.normalize_timestamps
+    }
+
+    pub fn should_strip_namespaces(&self) -> bool {
+        self.strip_namespaces
+    }
+
+    pub fn get_binary_byte_limit(&self) -> Option<usize> {
+        self.binary_byte_limit
+    }
+
+    pub fn get_binary_encoding(&self) -> BinaryEncoding {
+        self.binary_encoding
+    }
+
+    pub fn get_empty_element_style(&self) -> EmptyElementStyle {
+        self.empty_element_style
+    }
+
+    pub fn get_control_char_policy(&self) -> ControlCharPolicy {
+        self.control_char_policy
+    }
+
+    pub fn get_json_compatibility(&self) -> JsonCompatibility {
+        self.json_compatibility
+    }
+
+    pub fn get_json_root_path(&self) -> Option<&[String]> {
+        self.json_root_path.as_deref()
+    }
+
+    pub fn should_canonicalize_json(&self) -> bool {
+        self.canonicalize_json
+    }
+
+    pub fn should_ignore_ascii_case(&self) -> bool {
+        self.ascii_case_insensitive
+    }
+
+    pub fn should_add_symbolic_system_names(&self) -> bool {
+        self.symbolic_system_names
+    }
+
+    pub fn should_write_hex_keywords(&self) -> bool {
+        self.hex_keywords
+    }
+
+    pub fn get_numeric_json_attributes(&self) -> Option<&HashSet<String>> {
+        self.numeric_json_attributes.as_ref()
+    }
+
+    pub fn should_use_strict_json(&self) -> bool {
+        self.strict_json
+    }
+
+    pub fn should_trim_whitespace(&self) -> bool {
+        self.trim_whitespace
+    }
+
+    pub fn should_add_record_header_fields(&self) -> bool {
+        self.record_header_fields
+    }
+
+    pub fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    pub fn get_max_output_bytes(&self) -> Option<usize> {
+        self.max_output_bytes
+    }
+
+    pub fn should_cache_templates(&self) -> bool {
+        self.template_cache.is_some()
+    }
+
+    pub(crate) fn get_shared_template_cache(&self) -> Option<&SharedTemplateCache> {
+        self.template_cache.as_deref()
+    }
+
+    pub fn get_event_id_filter(&self) -> Option<&HashSet<u32>> {
+        self.event_id_filter.as_ref()
+    }
+
+    pub fn get_time_range(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        self.time_range
+    }
+
+    pub fn should_recover_records(&self) -> bool {
+        self.recover_records
+    }
+
+    pub fn get_error_policy(&self) -> ErrorPolicy {
+        self.error_policy
+    }
+
+    pub fn get_record_callback(&self) -> Option<&RecordCallback> {
+        self.record_callback.as_ref()
+    }
+
+    pub fn get_value_transform(&self) -> Option<&ValueTransform> {
+        self.value_transform.as_ref()
+    }
+
+    pub fn get_value_formatter(&self) -> Option<&dyn ValueFormatter> {
+        self.value_formatter.as_deref()
+    }
+
+    pub fn get_message_resolver(&self) -> Option<&MessageResolver> {
+        self.message_resolver.as_ref()
+    }
+
     pub fn should_validate_checksums(&self) -> bool {
         self.validate_checksums
     }
@@ -270,7 +998,17 @@ impl EvtxParser<Cursor<Vec<u8>>> {
     }
 }
 
+impl<'a> EvtxParser<Cursor<&'a [u8]>> {
+    /// Attempts to load an evtx file from a borrowed byte slice (for example a log carved out of
+    /// a memory image), will fail if the evtx header is invalid.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self> {
+        let cursor = Cursor::new(bytes);
+        Self::from_read_seek(cursor)
+    }
+}
+
 impl<T: ReadSeek> EvtxParser<T> {
+    /// Attempts to load an evtx file from any `Read + Seek` stream, will fail if the evtx header is invalid.
     pub fn from_read_seek(mut read_seek: T) -> Result<Self> {
         let evtx_header = EvtxFileHeader::from_stream(&mut read_seek)?;
 
@@ -306,6 +1044,25 @@ impl<T: ReadSeek> EvtxParser<T> {
         self
     }
 
+    /// The file header, with the chunk and record counters and the `DIRTY` and `FULL` flags.
+    pub fn header(&self) -> &EvtxFileHeader {
+        &self.header
+    }
+
+    /// Only returns records whose `System/EventID` is one of `event_ids`,
+    /// see `ParserSettings::event_id_filter`.
+    pub fn set_event_id_filter(&mut self, event_ids: &[u32]) {
+        let settings = ParserSettings::clone(&self.config).event_id_filter(event_ids);
+        self.config = Arc::new(settings);
+    }
+
+    /// Only returns records whose timestamp is between `start` and `end`,
+    /// see `ParserSettings::time_range`.
+    pub fn set_time_range(&mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) {
+        let settings = ParserSettings::clone(&self.config).time_range(start, end);
+        self.config = Arc::new(settings);
+    }
+
     /// Allocate a new chunk from the given data, at the offset expected by `chunk_number`.
     /// If the read chunk contains valid data, an `Ok(Some(EvtxChunkData))` will be returned.
     /// If the read chunk contains invalid data (bad magic, bad checksum when `validate_checksum` is set to true),
@@ -329,29 +1086,25 @@ impl<T: ReadSeek> EvtxParser<T> {
         data.seek(SeekFrom::Start(chunk_offset as u64))
             .map_err(|e| EvtxError::FailedToParseChunk {
                 chunk_id: chunk_number,
-                source: ChunkError::FailedToSeekToChunk(e),
+                source: Box::new(ChunkError::FailedToSeekToChunk(e)),
             })?;
 
-        let amount_read = data
-            .take(EVTX_CHUNK_SIZE as u64)
+        data.take(EVTX_CHUNK_SIZE as u64)
             .read_to_end(&mut chunk_data)
             .map_err(|_| EvtxError::incomplete_chunk(chunk_number))?;
 
-        if amount_read != EVTX_CHUNK_SIZE {
-            return Err(EvtxError::incomplete_chunk(chunk_number));
-        }
+        load_chunk(chunk_data, chunk_number, validate_checksum)
+    }
 
-        // There might be empty chunks in the middle of a dirty file.
-        if chunk_data.iter().all(|x| *x == 0) {
-            return Ok(None);
-        }
+    /// Reads only the header of the chunk `chunk_number`, `None` if it is empty or invalid.
+    fn read_chunk_header(&mut self, chunk_number: u64) -> Option<EvtxChunkHeader> {
+        let chunk_offset = EVTX_FILE_HEADER_SIZE as u64 + chunk_number * EVTX_CHUNK_SIZE as u64;
+        let mut header_data = [0; EVTX_CHUNK_HEADER_SIZE];
 
-        EvtxChunkData::new(chunk_data, validate_checksum)
-            .map(Some)
-            .map_err(|e| EvtxError::FailedToParseChunk {
-                chunk_id: chunk_number,
-                source: e,
-            })
+        self.data.seek(SeekFrom::Start(chunk_offset)).ok()?;
+        self.data.read_exact(&mut header_data).ok()?;
+
+        EvtxChunkHeader::from_reader(&mut Cursor::new(&header_data[..])).ok()
     }
 
     /// Find the next chunk, staring at `chunk_number` (inclusive).
@@ -395,7 +1148,7 @@ impl<T: ReadSeek> EvtxParser<T> {
     /// Return an iterator over all the chunks.
     /// Each chunk supports iterating over it's records in their un-serialized state
     /// (before they are converted to XML or JSON).
-    pub fn chunks(&mut self) -> IterChunks<T> {
+    pub fn chunks(&mut self) -> IterChunks<'_, T> {
         IterChunks {
             parser: self,
             current_chunk_number: 0,
@@ -416,9 +1169,10 @@ impl<T: ReadSeek> EvtxParser<T> {
     pub fn serialized_records<'a, U: Send>(
         &'a mut self,
         f: impl FnMut(Result<EvtxRecord<'_>>) -> Result<U> + Send + Sync + Clone + 'a,
-    ) -> impl Iterator<Item = Result<U>> + '_ {
+    ) -> impl Iterator<Item = Result<U>> + 'a {
         // Retrieve parser settings here, while `self` is immutably borrowed.
         let num_threads = max(self.config.num_threads, 1);
+        let error_policy = self.config.error_policy;
         let chunk_settings = Arc::clone(&self.config);
 
         // `self` is mutably borrowed from here on.
@@ -446,22 +1200,9 @@ impl<T: ReadSeek> EvtxParser<T> {
 
                 // Serialize the records in each chunk.
                 let iterators: Vec<Vec<Result<U>>> = chunk_iter
-                    .enumerate()
-                    .map(|(i, chunk_res)| match chunk_res {
+                    .map(|chunk_res| match chunk_res {
                         Err(err) => vec![Err(err)],
-                        Ok(mut chunk) => {
-                            let chunk_records_res = chunk.parse(chunk_settings.clone());
-
-                            match chunk_records_res {
-                                Err(err) => vec![Err(EvtxError::FailedToParseChunk {
-                                    chunk_id: i as u64,
-                                    source: err,
-                                })],
-                                Ok(mut chunk_records) => {
-                                    chunk_records.iter().map(f.clone()).collect()
-                                }
-                            }
-                        }
+                        Ok(chunk) => serialize_chunk(chunk, chunk_settings.clone(), f.clone()),
                     })
                     .collect();
 
@@ -469,22 +1210,113 @@ impl<T: ReadSeek> EvtxParser<T> {
             }
         });
 
-        records_per_chunk.flatten()
+        apply_error_policy(records_per_chunk.flatten(), error_policy)
     }
 
-    /// Return an iterator over all the records.
-    /// Records will be XML-formatted.
-    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
-        // '_ is required in the signature because the iterator is bound to &self.
-        self.serialized_records(|record| record.and_then(|record| record.into_xml()))
-    }
+    /// Return an iterator over all the records, serializing whole chunks on the rayon thread pool.
+    ///
+    /// Up to `num_threads` chunks are serialized at once, and a new chunk is started as soon as
+    /// one is done. Records of a chunk are always yielded together and in order.
+    /// When `preserve_order` is `false`, chunks are yielded as soon as they are done,
+    /// otherwise they are yielded in file order.
+    #[cfg(feature = "multithreading")]
+    pub fn par_serialized_records<U, F>(
+        &mut self,
+        preserve_order: bool,
+        f: F,
+    ) -> impl Iterator<Item = Result<U>> + '_
+    where
+        U: Send + 'static,
+        F: Fn(Result<EvtxRecord<'_>>) -> Result<U> + Send + Sync + 'static,
+        T: Send,
+    {
+        let max_in_flight = max(self.config.num_threads, 1);
+        let error_policy = self.config.error_policy;
+        let settings = Arc::clone(&self.config);
+        let (sender, receiver) = mpsc::channel();
+
+        let records = ParRecords {
+            chunks: self.chunks(),
+            settings,
+            f: Arc::new(f),
+            preserve_order,
+            max_in_flight,
+            sender,
+            receiver,
+            in_flight: 0,
+            next_chunk: 0,
+            next_ordered_chunk: 0,
+            completed: BTreeMap::new(),
+            current: Vec::new().into_iter(),
+            chunks_exhausted: false,
+        };
+
+        apply_error_policy(records, error_policy)
+    }
+
+    /// Like `records`, but serializes chunks using `par_serialized_records`.
+    #[cfg(feature = "multithreading")]
+    pub fn par_records(
+        &mut self,
+        preserve_order: bool,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_
+    where
+        T: Send,
+    {
+        let error_policy = self.config.error_policy;
+        self.par_serialized_records(preserve_order, move |record| {
+            serialize_record(
+                record,
+                error_policy,
+                EvtxRecord::into_xml,
+                RecordPlaceholder::to_xml,
+            )
+        })
+    }
+
+    /// Like `records_json`, but serializes chunks using `par_serialized_records`.
+    #[cfg(feature = "multithreading")]
+    pub fn par_records_json(
+        &mut self,
+        preserve_order: bool,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_
+    where
+        T: Send,
+    {
+        let error_policy = self.config.error_policy;
+        self.par_serialized_records(preserve_order, move |record| {
+            serialize_record(record, error_policy, EvtxRecord::into_json, |placeholder| {
+                placeholder.to_json_value().to_string()
+            })
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be XML-formatted.
+    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        // '_ is required in the signature because the iterator is bound to &self.
+        let error_policy = self.config.error_policy;
+        self.serialized_records(move |record| {
+            serialize_record(
+                record,
+                error_policy,
+                EvtxRecord::into_xml,
+                RecordPlaceholder::to_xml,
+            )
+        })
+    }
 
     /// Return an iterator over all the records.
     /// Records will be JSON-formatted.
     pub fn records_json(
         &mut self,
     ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
-        self.serialized_records(|record| record.and_then(|record| record.into_json()))
+        let error_policy = self.config.error_policy;
+        self.serialized_records(move |record| {
+            serialize_record(record, error_policy, EvtxRecord::into_json, |placeholder| {
+                placeholder.to_json_value().to_string()
+            })
+        })
     }
 
     /// Return an iterator over all the records.
@@ -492,7 +1324,127 @@ impl<T: ReadSeek> EvtxParser<T> {
     pub fn records_json_value(
         &mut self,
     ) -> impl Iterator<Item = Result<SerializedEvtxRecord<serde_json::Value>>> + '_ {
-        self.serialized_records(|record| record.and_then(|record| record.into_json_value()))
+        let error_policy = self.config.error_policy;
+        self.serialized_records(move |record| {
+            serialize_record(
+                record,
+                error_policy,
+                EvtxRecord::into_json_value,
+                RecordPlaceholder::to_json_value,
+            )
+        })
+    }
+
+    /// Return an iterator over all the records, along with their raw BinXml (see `EvtxRecord::binxml`).
+    /// Records will be XML-formatted.
+    ///
+    /// `ErrorPolicy::Placeholder` does not apply, since a placeholder has no BinXml,
+    /// so errors are yielded instead.
+    pub fn records_with_raw(
+        &mut self,
+    ) -> impl Iterator<Item = Result<(Vec<u8>, SerializedEvtxRecord<String>)>> + '_ {
+        self.serialized_records(|record| {
+            record.and_then(|record| {
+                let binxml = record.binxml.to_vec();
+                record.into_xml().map(|record| (binxml, record))
+            })
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be collected into a tree of elements, see `TreeOutput`.
+    ///
+    /// `ErrorPolicy::Placeholder` does not apply, since a placeholder has no elements,
+    /// so errors are yielded instead.
+    pub fn records_tree(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<TreeElement>>> + '_ {
+        self.serialized_records(|record| record.and_then(EvtxRecord::into_tree))
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be deserialized from their JSON representation into `U`.
+    ///
+    /// `ErrorPolicy::Placeholder` does not apply, since a placeholder can't be made into a `U`,
+    /// so errors are yielded instead.
+    pub fn records_deserialized<U: DeserializeOwned + Send>(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<U>>> + '_ {
+        self.serialized_records(|record| record.and_then(EvtxRecord::into_deserialized))
+    }
+
+    /// Finds the record with the `EventRecordID` of `record_id`, and renders it as XML.
+    ///
+    /// Only the chunk headers are read until a chunk whose range of record ids holds `record_id`
+    /// is found, and only the records of that chunk are deserialized.
+    /// Returns `Ok(None)` if no chunk holds the record, or if the record is filtered out.
+    pub fn get_record(&mut self, record_id: u64) -> Result<Option<SerializedEvtxRecord<String>>> {
+        for chunk_number in 0..self.calculated_chunk_count {
+            let holds_record = self.read_chunk_header(chunk_number).is_some_and(|header| {
+                (header.first_event_record_id..=header.last_event_record_id).contains(&record_id)
+            });
+
+            if !holds_record {
+                continue;
+            }
+
+            let mut chunk_data = match EvtxParser::allocate_chunk(
+                &mut self.data,
+                chunk_number,
+                self.config.validate_checksums,
+            )? {
+                Some(chunk_data) => chunk_data,
+                None => continue,
+            };
+            let mut chunk = chunk_data.parse(Arc::clone(&self.config)).map_err(|e| {
+                EvtxError::FailedToParseChunk {
+                    chunk_id: chunk_number,
+                    source: Box::new(e),
+                }
+            })?;
+
+            for record in chunk.iter() {
+                match record {
+                    Ok(record) if record.event_record_id == record_id => {
+                        return record.into_xml().map(Some)
+                    }
+                    Ok(_) => {}
+                    // Other records failing to parse do not stop the lookup.
+                    Err(EvtxError::FailedToParseRecord {
+                        record_id: failed_record_id,
+                        ..
+                    }) if failed_record_id != record_id => {}
+                    // The requested record failed, or the rest of the chunk can't be read.
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Counts the records of every `System/EventID`, without rendering them.
+    ///
+    /// Only the tokens leading to the id are walked, and templates are not expanded, so this is
+    /// much faster than rendering all the records. Filters (such as `event_id_filter`) apply.
+    pub fn summarize(&mut self) -> RecordSummary {
+        let mut summary = RecordSummary::default();
+
+        for event_id in self.serialized_records(|record| record.map(|record| record.event_id())) {
+            match event_id {
+                Ok(Some(event_id)) => {
+                    summary.total_records += 1;
+                    *summary.event_ids.entry(event_id).or_insert(0) += 1;
+                }
+                Ok(None) => {
+                    summary.total_records += 1;
+                    summary.records_without_event_id += 1;
+                }
+                Err(_) => summary.errors += 1,
+            }
+        }
+
+        summary
     }
 }
 
@@ -541,12 +1493,221 @@ impl<T: ReadSeek> Iterator for IntoIterChunks<T> {
     }
 }
 
+/// Loads the data read for chunk number `chunk_number`, see `EvtxParser::allocate_chunk`.
+pub(crate) fn load_chunk(
+    chunk_data: Vec<u8>,
+    chunk_number: u64,
+    validate_checksum: bool,
+) -> Result<Option<EvtxChunkData>> {
+    if chunk_data.len() != EVTX_CHUNK_SIZE {
+        return Err(EvtxError::incomplete_chunk(chunk_number));
+    }
+
+    // There might be empty chunks in the middle of a dirty file.
+    if chunk_data.iter().all(|x| *x == 0) {
+        return Ok(None);
+    }
+
+    EvtxChunkData::new(chunk_data, validate_checksum)
+        .map(|mut chunk| {
+            chunk.chunk_number = chunk_number;
+            chunk.file_offset =
+                (EVTX_FILE_HEADER_SIZE + chunk_number as usize * EVTX_CHUNK_SIZE) as u64;
+            Some(chunk)
+        })
+        .map_err(|e| EvtxError::FailedToParseChunk {
+            chunk_id: chunk_number,
+            source: Box::new(e),
+        })
+}
+
+/// Serializes a record, replacing it with a placeholder if it fails with `ErrorPolicy::Placeholder`.
+pub(crate) fn serialize_record<'a, U>(
+    record: Result<EvtxRecord<'a>>,
+    error_policy: ErrorPolicy,
+    serialize: impl FnOnce(EvtxRecord<'a>) -> Result<SerializedEvtxRecord<U>>,
+    placeholder: impl FnOnce(&RecordPlaceholder) -> U,
+) -> Result<SerializedEvtxRecord<U>> {
+    let timestamp = record.as_ref().ok().map(|record| record.timestamp);
+
+    match record.and_then(serialize) {
+        Err(err) if error_policy == ErrorPolicy::Placeholder => {
+            Ok(RecordPlaceholder::from_error(&err).into_record(timestamp, placeholder))
+        }
+        record => record,
+    }
+}
+
+/// Applies an `ErrorPolicy` to an iterator of records.
+pub(crate) struct ErrorPolicyIter<I> {
+    records: I,
+    error_policy: ErrorPolicy,
+    stopped: bool,
+}
+
+pub(crate) fn apply_error_policy<I>(records: I, error_policy: ErrorPolicy) -> ErrorPolicyIter<I> {
+    ErrorPolicyIter {
+        records,
+        error_policy,
+        stopped: false,
+    }
+}
+
+impl<U, I: Iterator<Item = Result<U>>> Iterator for ErrorPolicyIter<I> {
+    type Item = Result<U>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.stopped {
+            return None;
+        }
+
+        loop {
+            match (self.records.next()?, self.error_policy) {
+                (Err(err), ErrorPolicy::Skip) => {
+                    warn!("Skipping a record which failed to parse: {}", err);
+                }
+                (Err(err), ErrorPolicy::FailFast) => {
+                    self.stopped = true;
+                    return Some(Err(err));
+                }
+                (record, _) => return Some(record),
+            }
+        }
+    }
+}
+
+/// Parses a chunk and maps all of it's records using `f`.
+pub(crate) fn serialize_chunk<U>(
+    mut chunk: EvtxChunkData,
+    settings: Arc<ParserSettings>,
+    f: impl FnMut(Result<EvtxRecord<'_>>) -> Result<U>,
+) -> Vec<Result<U>> {
+    match chunk.parse(settings) {
+        Err(err) => vec![Err(EvtxError::FailedToParseChunk {
+            chunk_id: chunk.chunk_number,
+            source: Box::new(err),
+        })],
+        Ok(mut chunk_records) => chunk_records.iter().map(f).collect(),
+    }
+}
+
+/// The iterator returned by `EvtxParser::par_serialized_records`.
+#[cfg(feature = "multithreading")]
+struct ParRecords<'c, T: ReadSeek, U, F> {
+    chunks: IterChunks<'c, T>,
+    settings: Arc<ParserSettings>,
+    f: Arc<F>,
+    preserve_order: bool,
+    max_in_flight: usize,
+    sender: Sender<(u64, Vec<Result<U>>)>,
+    receiver: Receiver<(u64, Vec<Result<U>>)>,
+    /// Chunks which were read, but whose records were not yielded yet.
+    in_flight: usize,
+    /// The sequence number of the next chunk to read.
+    next_chunk: u64,
+    /// The sequence number of the next chunk to yield, when preserving order.
+    next_ordered_chunk: u64,
+    /// Serialized chunks, by sequence number.
+    completed: BTreeMap<u64, Vec<Result<U>>>,
+    current: std::vec::IntoIter<Result<U>>,
+    chunks_exhausted: bool,
+}
+
+#[cfg(feature = "multithreading")]
+impl<'c, T, U, F> ParRecords<'c, T, U, F>
+where
+    T: ReadSeek,
+    U: Send + 'static,
+    F: Fn(Result<EvtxRecord<'_>>) -> Result<U> + Send + Sync + 'static,
+{
+    /// Reads chunks and hands them to the thread pool, until enough chunks are in flight.
+    fn start_chunks(&mut self) {
+        while !self.chunks_exhausted && self.in_flight < self.max_in_flight {
+            let sequence = self.next_chunk;
+
+            match self.chunks.next() {
+                None => {
+                    self.chunks_exhausted = true;
+                    return;
+                }
+                Some(Err(err)) => {
+                    self.completed.insert(sequence, vec![Err(err)]);
+                }
+                Some(Ok(chunk)) => {
+                    let settings = Arc::clone(&self.settings);
+                    let f = Arc::clone(&self.f);
+                    let sender = self.sender.clone();
+
+                    rayon::spawn(move || {
+                        let records = serialize_chunk(chunk, settings, &*f);
+                        // The iterator might have been dropped in the meantime.
+                        let _ = sender.send((sequence, records));
+                    });
+                }
+            }
+
+            self.next_chunk += 1;
+            self.in_flight += 1;
+        }
+    }
+
+    fn take_completed(&mut self) -> Option<Vec<Result<U>>> {
+        let records = if self.preserve_order {
+            let records = self.completed.remove(&self.next_ordered_chunk)?;
+            self.next_ordered_chunk += 1;
+            records
+        } else {
+            self.completed.pop_first()?.1
+        };
+
+        self.in_flight -= 1;
+        Some(records)
+    }
+}
+
+#[cfg(feature = "multithreading")]
+impl<'c, T, U, F> Iterator for ParRecords<'c, T, U, F>
+where
+    T: ReadSeek,
+    U: Send + 'static,
+    F: Fn(Result<EvtxRecord<'_>>) -> Result<U> + Send + Sync + 'static,
+{
+    type Item = Result<U>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        loop {
+            if let Some(record) = self.current.next() {
+                return Some(record);
+            }
+
+            self.start_chunks();
+
+            if let Some(records) = self.take_completed() {
+                self.current = records.into_iter();
+                continue;
+            }
+
+            if self.in_flight == 0 {
+                return None;
+            }
+
+            // We hold a sender, so this only fails if a worker panicked.
+            let (sequence, records) = self
+                .receiver
+                .recv()
+                .expect("A worker thread panicked while serializing a chunk");
+            self.completed.insert(sequence, records);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(unused_variables)]
 
     use super::*;
     use crate::ensure_env_logger_initialized;
+    use crate::err::SerializationError;
     use anyhow::anyhow;
 
     fn process_90_records(buffer: &'static [u8]) -> anyhow::Result<()> {
@@ -574,6 +1735,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parses_records_from_a_borrowed_slice() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let from_bytes: Vec<String> = EvtxParser::from_bytes(evtx_file)
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap().data)
+            .collect();
+        let from_buffer: Vec<String> = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap().data)
+            .collect();
+
+        assert_eq!(from_bytes.len(), 2261);
+        assert_eq!(from_bytes, from_buffer);
+    }
+
     #[test]
     fn test_sample_2() {
         let evtx_file = include_bytes!("../samples/system.evtx");
@@ -665,6 +1846,25 @@ mod tests {
         assert_eq!(record_ids.len(), 1000);
     }
 
+    #[test]
+    #[cfg(feature = "multithreading")]
+    fn test_par_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().num_threads(4));
+
+        let expected: Vec<_> = parser.records().map(|r| r.unwrap()).collect();
+
+        let ordered: Vec<_> = parser.par_records(true).map(|r| r.unwrap()).collect();
+        assert_eq!(ordered, expected);
+
+        let mut unordered: Vec<_> = parser.par_records(false).map(|r| r.unwrap()).collect();
+        unordered.sort_by_key(|r| r.event_record_id);
+        assert_eq!(unordered, expected);
+    }
+
     #[test]
     fn test_file_with_only_a_single_chunk() {
         ensure_env_logger_initialized();
@@ -707,6 +1907,36 @@ mod tests {
         assert_eq!(parser.into_chunks().count(), 1);
     }
 
+    #[test]
+    fn test_chunks_have_the_standard_size() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        // The header only describes its own size, there is no chunk size to honour.
+        assert_eq!(parser.header().header_size, 128);
+        assert_eq!(
+            usize::from(parser.header().header_block_size),
+            EVTX_FILE_HEADER_SIZE
+        );
+
+        // Empty chunks at the end of the file are skipped, but still take up their space.
+        let chunk_count = (evtx_file.len() - EVTX_FILE_HEADER_SIZE) / EVTX_CHUNK_SIZE;
+        let mut seen = 0;
+        for chunk in parser.chunks() {
+            let chunk = chunk.unwrap();
+            assert_eq!(chunk.data.len(), EVTX_CHUNK_SIZE);
+            assert_eq!(
+                chunk.file_offset,
+                EVTX_FILE_HEADER_SIZE as u64 + chunk.chunk_number * EVTX_CHUNK_SIZE as u64
+            );
+            assert!(chunk.chunk_number < chunk_count as u64);
+            seen += 1;
+        }
+
+        assert_eq!(seen, usize::from(parser.header().chunk_count));
+    }
+
     #[test]
     fn test_into_json_value_records() {
         ensure_env_logger_initialized();
@@ -722,4 +1952,362 @@ mod tests {
             assert!(record.data.as_object().unwrap().contains_key("Event"));
         }
     }
+
+    #[test]
+    fn test_record_callback_reports_record_locations() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let contexts = Arc::new(std::sync::Mutex::new(vec![]));
+        let contexts_clone = Arc::clone(&contexts);
+        let settings = ParserSettings::new()
+            .num_threads(1)
+            .record_callback(move |context| contexts_clone.lock().unwrap().push(*context));
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+        let records_count = parser.records().count();
+
+        let contexts = contexts.lock().unwrap();
+        assert_eq!(contexts.len(), records_count);
+
+        assert_eq!(contexts[0].chunk_number, 0);
+        assert_eq!(contexts[0].event_record_id, 1);
+        assert_eq!(
+            contexts[0].file_offset,
+            (EVTX_FILE_HEADER_SIZE + 512) as u64
+        );
+
+        let last = contexts.last().unwrap();
+        assert!(last.chunk_number > 0);
+
+        // Every reported offset points to a record header.
+        for context in contexts.iter() {
+            let offset = context.file_offset as usize;
+            assert_eq!(&evtx_file[offset..offset + 4], b"\x2a\x2a\x00\x00");
+        }
+    }
+
+    #[test]
+    fn test_value_transform_redacts_values() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let settings = ParserSettings::new().value_transform(|path, value| match path {
+            "Event/EventData/TargetUserName" => Some(Cow::Borrowed("REDACTED")),
+            "Event/System/Computer" => Some(Cow::Owned(value.to_lowercase())),
+            "Event/System/Provider/@Name" => Some(Cow::Borrowed("Provider")),
+            _ => None,
+        });
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        let records: Vec<_> = parser.records_json_value().map(|r| r.unwrap()).collect();
+        let record = records
+            .iter()
+            .find(|r| r.data["Event"]["EventData"].get("TargetUserName").is_some())
+            .unwrap();
+
+        let event = &record.data["Event"];
+        assert_eq!(event["EventData"]["TargetUserName"], "REDACTED");
+        assert_eq!(event["System"]["Computer"], "37l4247f27-25");
+        assert_eq!(
+            event["System"]["Provider"]["#attributes"]["Name"],
+            "Provider"
+        );
+        // Values which are kept as is also keep their type.
+        assert!(event["System"]["EventID"].is_number());
+
+        let xml = parser.records().next().unwrap().unwrap().data;
+        assert!(xml.contains("<Provider Name=\"Provider\""));
+        assert!(xml.contains("<Computer>37l4247f27-25</Computer>"));
+    }
+
+    #[test]
+    fn test_value_formatter_formats_timestamps() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        struct UnixTimestamps;
+
+        impl ValueFormatter for UnixTimestamps {
+            fn format_filetime(&self, filetime: &DateTime<Utc>) -> String {
+                filetime.timestamp().to_string()
+            }
+        }
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected = parser.records_json_value().next().unwrap().unwrap();
+
+        let mut parser =
+            parser.with_configuration(ParserSettings::new().value_formatter(UnixTimestamps));
+        let record = parser.records_json_value().next().unwrap().unwrap();
+
+        let system_time = |data: &serde_json::Value| {
+            data["Event"]["System"]["TimeCreated"]["#attributes"]["SystemTime"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let timestamp = DateTime::parse_from_rfc3339(&system_time(&expected.data))
+            .unwrap()
+            .timestamp();
+        assert_eq!(system_time(&record.data), timestamp.to_string());
+        // Values of other types are not changed.
+        assert_eq!(
+            record.data["Event"]["System"]["EventID"],
+            expected.data["Event"]["System"]["EventID"]
+        );
+
+        let xml = parser.records().next().unwrap().unwrap().data;
+        assert!(xml.contains(&format!("SystemTime=\"{}\"", timestamp)));
+    }
+
+    #[test]
+    fn test_records_deserialized() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        type Event = std::collections::HashMap<String, serde_json::Map<String, serde_json::Value>>;
+
+        let expected: Vec<_> = parser.records_json_value().map(|r| r.unwrap()).collect();
+        let records: Vec<_> = parser
+            .records_deserialized::<Event>()
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(records.len(), expected.len());
+        for (record, expected) in records.iter().zip(expected.iter()) {
+            assert_eq!(record.event_record_id, expected.event_record_id);
+            assert_eq!(
+                record.data["Event"]["System"],
+                expected.data["Event"]["System"]
+            );
+        }
+
+        // Records which don't match `T` fail, without stopping the iteration.
+        let errors: Vec<_> = parser.records_deserialized::<Vec<u8>>().collect();
+        assert_eq!(errors.len(), expected.len());
+        assert!(matches!(
+            errors[0],
+            Err(EvtxError::SerializationError(
+                SerializationError::JsonError { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_message_resolver_adds_the_message() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let settings =
+            ParserSettings::new().message_resolver(|provider, event_id, values| {
+                match (provider, event_id) {
+                    ("Microsoft-Windows-Security-Auditing", 4624) => Some(format!(
+                        "An account was successfully logged on: {}\\{} ({} values)",
+                        values[6],
+                        values[5],
+                        values.len()
+                    )),
+                    _ => None,
+                }
+            });
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        let records: Vec<_> = parser.records_json_value().map(|r| r.unwrap()).collect();
+
+        let event = &records[1].data["Event"];
+        assert_eq!(
+            event["RenderingInfo"]["Message"],
+            "An account was successfully logged on: NT AUTHORITY\\SYSTEM (20 values)"
+        );
+        // Records the resolver doesn't know are left as is.
+        assert!(records[0].data["Event"].get("RenderingInfo").is_none());
+
+        let xml = parser.records().nth(1).unwrap().unwrap().data;
+        assert!(xml.contains(
+            "<RenderingInfo>\n    <Message>An account was successfully logged on: NT AUTHORITY\\SYSTEM (20 values)</Message>\n  </RenderingInfo>\n</Event>"
+        ));
+    }
+
+    #[test]
+    fn test_max_output_bytes() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+        let limit = expected[0].len();
+        assert!(expected.iter().any(|data| data.len() > limit));
+
+        parser = parser.with_configuration(ParserSettings::new().max_output_bytes(limit));
+        for (record, expected) in parser.records().zip(expected.iter()) {
+            match record {
+                Ok(record) => assert_eq!(&record.data, expected),
+                Err(EvtxError::FailedToParseRecord { source, .. }) => {
+                    assert!(expected.len() > limit);
+                    assert!(matches!(
+                        *source,
+                        EvtxError::SerializationError(SerializationError::OutputTooLarge {
+                            max_output_bytes
+                        }) if max_output_bytes == limit
+                    ));
+                }
+                Err(other) => panic!("Expected the record to be too large, got {:?}", other),
+            }
+        }
+
+        parser = parser.with_configuration(ParserSettings::new().max_output_bytes(100));
+        let record = parser.records_json_value().next().unwrap();
+        match record {
+            Err(EvtxError::FailedToParseRecord { source, .. }) => assert!(matches!(
+                *source,
+                EvtxError::SerializationError(SerializationError::OutputTooLarge {
+                    max_output_bytes: 100
+                })
+            )),
+            other => panic!("Expected the record to be too large, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_template_cache_does_not_change_output() {
+        ensure_env_logger_initialized();
+
+        for evtx_file in [
+            &include_bytes!("../samples/security.evtx")[..],
+            &include_bytes!("../samples/system.evtx")[..],
+        ] {
+            let mut parser = EvtxParser::from_bytes(evtx_file).unwrap();
+            let expected: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+
+            let settings = ParserSettings::new().num_threads(1).template_cache(true);
+            let template_cache = Arc::clone(settings.template_cache.as_ref().unwrap());
+
+            parser = parser.with_configuration(settings);
+            let records: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+
+            assert_eq!(records, expected);
+            assert!(template_cache.len() > 0);
+        }
+    }
+
+    #[test]
+    fn test_event_id_filter() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let event_id = |record: &SerializedEvtxRecord<serde_json::Value>| {
+            record.data["Event"]["System"]["EventID"].as_u64().unwrap()
+        };
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected: Vec<u64> = parser
+            .records_json_value()
+            .map(|record| record.unwrap())
+            .filter(|record| [4624, 4672].contains(&event_id(record)))
+            .map(|record| record.event_record_id)
+            .collect();
+        assert!(!expected.is_empty());
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        parser.set_event_id_filter(&[4624, 4672]);
+        let filtered: Vec<u64> = parser
+            .records_json_value()
+            .map(|record| record.unwrap())
+            .inspect(|record| assert!([4624, 4672].contains(&event_id(record))))
+            .map(|record| record.event_record_id)
+            .collect();
+
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_summarize() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let mut expected = BTreeMap::new();
+        for record in parser.records_json_value() {
+            let event_id = record.unwrap().data["Event"]["System"]["EventID"]
+                .as_u64()
+                .unwrap();
+            *expected.entry(event_id as u32).or_insert(0) += 1;
+        }
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let summary = parser.summarize();
+
+        assert_eq!(summary.event_ids, expected);
+        assert_eq!(summary.total_records, expected.values().sum::<u64>());
+        assert_eq!(summary.records_without_event_id, 0);
+        assert_eq!(summary.errors, 0);
+    }
+
+    #[test]
+    fn test_time_range() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let records: Vec<_> = parser.records().map(|record| record.unwrap()).collect();
+        let start = records[10].timestamp;
+        let end = records[records.len() - 10].timestamp;
+        assert!(start < end);
+
+        let expected: Vec<u64> = records
+            .iter()
+            .filter(|record| start <= record.timestamp && record.timestamp <= end)
+            .map(|record| record.event_record_id)
+            .collect();
+        assert!(expected.len() < records.len());
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        parser.set_time_range(Some(start), Some(end));
+        let filtered: Vec<u64> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+        assert_eq!(filtered, expected);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        parser.set_time_range(Some(end), None);
+        assert!(parser
+            .records()
+            .all(|record| record.unwrap().timestamp >= end));
+    }
+
+    #[test]
+    fn test_record_context() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let mut chunk = parser.chunks().nth(1).unwrap().unwrap();
+        assert_eq!(chunk.chunk_number, 1);
+        assert_eq!(
+            chunk.file_offset,
+            (EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE) as u64
+        );
+
+        let mut chunk = chunk.parse(Arc::new(ParserSettings::default())).unwrap();
+        let record = chunk.iter().next().unwrap().unwrap();
+        let context = record.context();
+
+        assert_eq!(context.chunk_number, 1);
+        assert_eq!(context.event_record_id, record.event_record_id);
+        assert_eq!(
+            context.file_offset,
+            (EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE + 512) as u64
+        );
+    }
 }
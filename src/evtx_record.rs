@@ -1,16 +1,24 @@
-use crate::binxml::assemble::parse_tokens;
+use crate::binxml::assemble::{parse_tokens, scan_event_id, scan_system, EventIdScan};
+use crate::binxml::value_variant::{template_substitution_values, BinXmlValue};
 use crate::err::{
     DeserializationError, DeserializationResult, EvtxError, Result, SerializationError,
 };
-use crate::json_output::JsonOutput;
+use crate::event_data_output::EventDataOutput;
+use crate::flat_json_output::FlatJsonOutput;
+use crate::json_output::{JsonCompatibility, JsonOutput};
 use crate::model::deserialized::BinXMLDeserializedTokens;
+use crate::system_fields::SystemFields;
+use crate::tree_output::{TreeElement, TreeOutput};
 use crate::xml_output::{BinXmlOutput, XmlOutput};
-use crate::{EvtxChunk, ParserSettings};
+use crate::{EvtxChunk, FileOffset, ParserSettings};
 
 use byteorder::ReadBytesExt;
 use chrono::prelude::*;
-use std::io::{Cursor, Read};
+use quick_xml::escape::escape;
+use serde::de::DeserializeOwned;
+use std::io::{Cursor, Read, Write};
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 
 pub type RecordId = u64;
 
@@ -19,8 +27,25 @@ pub struct EvtxRecord<'a> {
     pub chunk: &'a EvtxChunk<'a>,
     pub event_record_id: RecordId,
     pub timestamp: DateTime<Utc>,
+    /// The offset of the record header in the file.
+    pub file_offset: FileOffset,
     pub tokens: Vec<BinXMLDeserializedTokens<'a>>,
+    /// The raw BinXml of the record (without the record header), as stored in the chunk.
+    /// Names and templates are referred to by their chunk offset, so parsing it again
+    /// requires the chunk.
+    pub binxml: &'a [u8],
     pub settings: Arc<ParserSettings>,
+    /// Whether the record was found in the slack space of its chunk, see `recover_records`.
+    pub recovered: bool,
+}
+
+/// Where a record is located, reported as each record begins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordContext {
+    pub chunk_number: u64,
+    pub event_record_id: RecordId,
+    /// The offset of the record header in the file.
+    pub file_offset: FileOffset,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,6 +60,85 @@ pub struct SerializedEvtxRecord<T> {
     pub event_record_id: RecordId,
     pub timestamp: DateTime<Utc>,
     pub data: T,
+    /// Whether the record was found in the slack space of its chunk, see `recover_records`.
+    pub recovered: bool,
+}
+
+/// Stands in for a record which failed to parse, when using `ErrorPolicy::Placeholder`.
+///
+/// ```text
+/// {"Error": "Failed to parse record number 7: ...", "EventRecordID": 7, "RecordOffset": 12345}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordPlaceholder {
+    /// The error, followed by all of it's sources.
+    pub error: String,
+    pub event_record_id: Option<RecordId>,
+    /// The offset of the record header in the file.
+    pub file_offset: Option<FileOffset>,
+}
+
+impl RecordPlaceholder {
+    pub fn from_error(error: &EvtxError) -> Self {
+        let mut message = error.to_string();
+        let mut source = std::error::Error::source(error);
+        while let Some(err) = source {
+            message.push_str(": ");
+            message.push_str(&err.to_string());
+            source = err.source();
+        }
+
+        let (event_record_id, file_offset) = match error {
+            EvtxError::FailedToParseRecord {
+                record_id,
+                file_offset,
+                ..
+            } => (Some(*record_id), Some(*file_offset)),
+            _ => (None, None),
+        };
+
+        RecordPlaceholder {
+            error: message,
+            event_record_id,
+            file_offset,
+        }
+    }
+
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::json!({
+            "Error": self.error,
+            "EventRecordID": self.event_record_id,
+            "RecordOffset": self.file_offset,
+        })
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<Error");
+        if let Some(event_record_id) = self.event_record_id {
+            xml.push_str(&format!(" EventRecordID=\"{}\"", event_record_id));
+        }
+        if let Some(file_offset) = self.file_offset {
+            xml.push_str(&format!(" RecordOffset=\"{}\"", file_offset));
+        }
+        xml.push('>');
+        xml.push_str(&escape(&self.error));
+        xml.push_str("</Error>");
+        xml
+    }
+
+    /// Wraps the placeholder as a record, the timestamp is the Unix epoch if it is not known.
+    pub fn into_record<T>(
+        self,
+        timestamp: Option<DateTime<Utc>>,
+        data: impl FnOnce(&Self) -> T,
+    ) -> SerializedEvtxRecord<T> {
+        SerializedEvtxRecord {
+            event_record_id: self.event_record_id.unwrap_or(0),
+            timestamp: timestamp.unwrap_or_else(|| DateTime::<Utc>::from(UNIX_EPOCH)),
+            data: data(&self),
+            recovered: false,
+        }
+    }
 }
 
 impl EvtxRecordHeader {
@@ -57,20 +161,32 @@ impl EvtxRecordHeader {
         })
     }
 
+    /// The size of the BinXml of the record, `0` if the declared size is too small to hold any.
     pub fn record_data_size(&self) -> u32 {
         // 24 - record header size
         // 4 - copy of size record size
-        self.data_size - 24 - 4
+        self.data_size.saturating_sub(24 + 4)
     }
 }
 
 impl<'a> EvtxRecord<'a> {
+    /// Where this record is located.
+    pub fn context(&self) -> RecordContext {
+        RecordContext {
+            chunk_number: self.chunk.chunk_number,
+            event_record_id: self.event_record_id,
+            file_offset: self.file_offset,
+        }
+    }
+
     /// Consumes the record, processing it using the given `output_builder`.
     pub fn into_output<T: BinXmlOutput>(self, output_builder: &mut T) -> Result<()> {
         let event_record_id = self.event_record_id;
+        let file_offset = self.file_offset;
         parse_tokens(self.tokens, self.chunk, output_builder).map_err(|e| {
             EvtxError::FailedToParseRecord {
                 record_id: event_record_id,
+                file_offset,
                 source: Box::new(e),
             }
         })?;
@@ -84,49 +200,281 @@ impl<'a> EvtxRecord<'a> {
 
         let event_record_id = self.event_record_id;
         let timestamp = self.timestamp;
+        let recovered = self.recovered;
+        let record_header_fields = self.settings.should_add_record_header_fields();
         self.into_output(&mut output_builder)?;
 
+        let mut data = output_builder.into_value()?;
+        if record_header_fields {
+            data = with_record_header_fields(data, event_record_id, timestamp);
+        }
+
         Ok(SerializedEvtxRecord {
             event_record_id,
             timestamp,
-            data: output_builder.into_value()?,
+            data,
+            recovered,
+        })
+    }
+
+    /// Consumes the record, deserializing its JSON representation into `T`.
+    ///
+    /// This goes through the `serde_json::Value` of the record, so `T` sees the same layout as
+    /// `into_json_value` (and the same settings), without rendering the record to text.
+    pub fn into_deserialized<T: DeserializeOwned>(self) -> Result<SerializedEvtxRecord<T>> {
+        let record = self.into_json_value()?;
+
+        Ok(SerializedEvtxRecord {
+            event_record_id: record.event_record_id,
+            timestamp: record.timestamp,
+            data: serde_json::from_value(record.data).map_err(SerializationError::from)?,
+            recovered: record.recovered,
         })
     }
 
     /// Consumes the record and parse it, producing a JSON serialized record.
     pub fn into_json(self) -> Result<SerializedEvtxRecord<String>> {
-        let indent = self.settings.should_indent();
+        let indent = self.settings.get_json_indent();
         let record_with_json_value = self.into_json_value()?;
 
-        let data = if indent {
-            serde_json::to_string_pretty(&record_with_json_value.data)
-                .map_err(SerializationError::from)?
-        } else {
-            serde_json::to_string(&record_with_json_value.data).map_err(SerializationError::from)?
-        };
+        let data = indent.to_string(&record_with_json_value.data)?;
 
         Ok(SerializedEvtxRecord {
             event_record_id: record_with_json_value.event_record_id,
             timestamp: record_with_json_value.timestamp,
             data,
+            recovered: record_with_json_value.recovered,
         })
     }
 
+    /// Consumes the record and writes it as JSON into `writer`, like `into_json`.
+    pub fn write_json<W: Write>(self, writer: W) -> Result<()> {
+        let indent = self.settings.get_json_indent();
+        let record_with_json_value = self.into_json_value()?;
+
+        indent
+            .to_writer(writer, &record_with_json_value.data)
+            .map_err(SerializationError::from)?;
+
+        Ok(())
+    }
+
+    /// Consumes the record and writes it as a single line of compact JSON, followed by a `\n`.
+    ///
+    /// Records written this way form newline-delimited JSON (JSONL), regardless of `indent`.
+    pub fn write_json_line<W: Write>(self, mut writer: W) -> Result<()> {
+        let record_with_json_value = self.into_json_value()?;
+
+        serde_json::to_writer(&mut writer, &record_with_json_value.data)
+            .map_err(SerializationError::from)?;
+        writer.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    /// Reads the well known fields of the record's `System` element, without rendering it.
+    ///
+    /// The `System` element is read from the record's tokens in place, which is much cheaper
+    /// than rendering the record, so records can be filtered before only the kept ones are
+    /// rendered:
+    ///
+    /// ```rust
+    /// # use evtx::EvtxParser;
+    /// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+    /// let mut parser = EvtxParser::from_path(fp).unwrap();
+    ///
+    /// let logons = parser.serialized_records(|record| {
+    ///     let record = record?;
+    ///     if record.system_fields()?.event_id == 4624 {
+    ///         record.into_xml().map(Some)
+    ///     } else {
+    ///         Ok(None)
+    ///     }
+    /// });
+    ///
+    /// for logon in logons.filter_map(|r| r.transpose()) {
+    ///     println!("{}", logon.unwrap().data);
+    /// }
+    /// ```
+    pub fn system_fields(&self) -> Result<SystemFields> {
+        let system = scan_system(&self.tokens, self.chunk).ok_or_else(|| {
+            SerializationError::MissingSystemField {
+                field: "System".to_owned(),
+            }
+        })?;
+
+        Ok(SystemFields::from_json_value(&system)?)
+    }
+
+    /// The `System/EventID` of the record, found without expanding its templates.
+    ///
+    /// This is cheaper than `system_fields`, since the walk stops at the id.
+    /// Returns `None` if the record has no id, or if it could not be read.
+    pub fn event_id(&self) -> Option<u32> {
+        match scan_event_id(&self.tokens, self.chunk) {
+            EventIdScan::Found(event_id) => Some(event_id),
+            EventIdScan::Missing | EventIdScan::Unknown => None,
+        }
+    }
+
+    /// The values substituted in the record's template, in substitution slot order.
+    ///
+    /// These are the raw values, before the template is expanded, as used to format `%1`, `%2`...
+    /// Slots which are empty in the record are kept as `BinXmlValue::NullType`, so that indices
+    /// always match the template.
+    ///
+    /// Note that the record's template usually holds the values of `System`, while `EventData`
+    /// (or `UserData`) is a nested BinXml fragment with its own template, whose values are
+    /// available with `BinXmlValue::substitution_values`.
+    /// Records which are not made of a template have no substitution values.
+    pub fn substitution_values(&self) -> Vec<BinXmlValue<'a>> {
+        template_substitution_values(&self.tokens).unwrap_or_default()
+    }
+
+    /// Consumes the record, returning the well known fields of its `System` element.
+    pub fn into_system_fields(self) -> Result<SystemFields> {
+        // `SystemFields` expects the default JSON keys and layout.
+        let settings = ParserSettings::clone(&self.settings)
+            .attribute_key("#attributes")
+            .text_key("#text")
+            .flatten_json_attributes(false)
+            .json_compatibility(JsonCompatibility::Native)
+            .json_root_path("");
+        let mut output_builder = JsonOutput::new(&settings);
+        self.into_output(&mut output_builder)?;
+
+        Ok(SystemFields::from_json_value(
+            &output_builder.into_value()?,
+        )?)
+    }
+
+    /// Consumes the record, returning a flat `serde_json::Value` object with dotted keys.
+    pub fn into_flat_json_value(self) -> Result<SerializedEvtxRecord<serde_json::Value>> {
+        let mut output_builder = FlatJsonOutput::new();
+
+        let event_record_id = self.event_record_id;
+        let timestamp = self.timestamp;
+        let recovered = self.recovered;
+        self.into_output(&mut output_builder)?;
+
+        Ok(SerializedEvtxRecord {
+            event_record_id,
+            timestamp,
+            data: output_builder.into_value()?,
+            recovered,
+        })
+    }
+
+    /// Consumes the record, returning only it's `EventData` as a `{name: value}` object.
+    pub fn into_event_data_json_value(self) -> Result<SerializedEvtxRecord<serde_json::Value>> {
+        let mut output_builder = EventDataOutput::new();
+
+        let event_record_id = self.event_record_id;
+        let timestamp = self.timestamp;
+        let recovered = self.recovered;
+        self.into_output(&mut output_builder)?;
+
+        Ok(SerializedEvtxRecord {
+            event_record_id,
+            timestamp,
+            data: output_builder.into_value()?,
+            recovered,
+        })
+    }
+
+    /// Consumes the record, collecting it into a tree of elements, see `TreeOutput`.
+    pub fn into_tree(self) -> Result<SerializedEvtxRecord<TreeElement>> {
+        let mut output_builder = TreeOutput::new();
+
+        let event_record_id = self.event_record_id;
+        let timestamp = self.timestamp;
+        let recovered = self.recovered;
+        self.into_output(&mut output_builder)?;
+
+        Ok(SerializedEvtxRecord {
+            event_record_id,
+            timestamp,
+            data: output_builder.into_root()?,
+            recovered,
+        })
+    }
+
+    /// Consumes the record and writes it as XML into `writer`, like `into_xml`.
+    pub fn write_xml<W: Write>(self, writer: W) -> Result<()> {
+        let mut output_builder = XmlOutput::with_writer(writer, &self.settings);
+        self.into_output(&mut output_builder)?;
+        output_builder.into_writer()?;
+
+        Ok(())
+    }
+
     /// Consumes the record and parse it, producing an XML serialized record.
     pub fn into_xml(self) -> Result<SerializedEvtxRecord<String>> {
         let mut output_builder = XmlOutput::with_writer(Vec::new(), &self.settings);
 
         let event_record_id = self.event_record_id;
         let timestamp = self.timestamp;
+        let recovered = self.recovered;
         self.into_output(&mut output_builder)?;
 
         let data =
-            String::from_utf8(output_builder.into_writer()).map_err(SerializationError::from)?;
+            String::from_utf8(output_builder.into_writer()?).map_err(SerializationError::from)?;
 
         Ok(SerializedEvtxRecord {
             event_record_id,
             timestamp,
             data,
+            recovered,
         })
     }
 }
+
+/// Prepends the record header's id and timestamp to a JSON record, see `record_header_fields`.
+fn with_record_header_fields(
+    data: serde_json::Value,
+    event_record_id: RecordId,
+    timestamp: DateTime<Utc>,
+) -> serde_json::Value {
+    match data {
+        serde_json::Value::Object(fields) => {
+            let mut record = serde_json::Map::with_capacity(fields.len() + 2);
+            record.insert("_record_id".to_owned(), event_record_id.into());
+            record.insert(
+                "_timestamp".to_owned(),
+                timestamp
+                    .to_rfc3339_opts(SecondsFormat::Micros, true)
+                    .into(),
+            );
+            record.extend(fields);
+            serde_json::Value::Object(record)
+        }
+        // A `json_root_path` may select a value which is not an object, which is kept as is.
+        other => other,
+    }
+}
+
+/// Renders a single record as XML, using the record's settings.
+pub fn render_record_to_xml(record: EvtxRecord) -> Result<String> {
+    Ok(record.into_xml()?.data)
+}
+
+/// Renders a single record as JSON, using the record's settings.
+pub fn render_record_to_json(record: EvtxRecord) -> Result<String> {
+    Ok(record.into_json()?.data)
+}
+
+/// Like `render_record_to_xml`, but renders the record into `buf`, which is cleared first.
+///
+/// Reusing the same buffer for every record avoids allocating a new one per record.
+pub fn render_record_to_xml_into(record: EvtxRecord, buf: &mut Vec<u8>) -> Result<()> {
+    buf.clear();
+    record.write_xml(buf)
+}
+
+/// Like `render_record_to_json`, but renders the record into `buf`, which is cleared first.
+///
+/// Reusing the same buffer for every record avoids allocating a new one per record.
+pub fn render_record_to_json_into(record: EvtxRecord, buf: &mut Vec<u8>) -> Result<()> {
+    buf.clear();
+    record.write_json(buf)
+}
@@ -0,0 +1,246 @@
+use crate::err::{EvtxError, Result};
+use crate::evtx_chunk::EvtxChunkData;
+use crate::evtx_file_header::EvtxFileHeader;
+use crate::evtx_parser::{
+    apply_error_policy, load_chunk, serialize_chunk, serialize_record, ParserSettings,
+    EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE,
+};
+use crate::evtx_record::{EvtxRecord, RecordPlaceholder, SerializedEvtxRecord};
+
+use log::{debug, trace};
+use serde::de::DeserializeOwned;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+
+/// Parses an evtx file from a stream which can only be read forward, such as a download in
+/// progress.
+///
+/// Unlike `EvtxParser`, the stream does not need to implement `Seek`, and only a single chunk
+/// (64KB) is buffered at a time. Records are yielded as soon as the chunk containing them was
+/// read completely, so a partially downloaded log yields the records of all of it's complete
+/// chunks, followed by an error for the incomplete one.
+///
+/// Since the size of the stream is unknown, chunks are serialized one after the other on the
+/// calling thread, and `num_threads` is ignored.
+///
+/// ```rust
+/// # use evtx::EvtxStreamParser;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let file = std::fs::File::open(fp).unwrap();
+/// let mut parser = EvtxStreamParser::from_reader(file).unwrap();
+///
+/// for record in parser.records() {
+///     match record {
+///         Ok(r) => println!("Record {}\n{}", r.event_record_id, r.data),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+pub struct EvtxStreamParser<R: Read> {
+    data: R,
+    header: EvtxFileHeader,
+    config: Arc<ParserSettings>,
+    next_chunk_number: u64,
+    exhausted: bool,
+}
+
+impl<R: Read> EvtxStreamParser<R> {
+    /// Reads the file header from `reader`, will fail if the evtx header is invalid.
+    pub fn from_reader(mut reader: R) -> Result<Self> {
+        let mut header_block = vec![0; EVTX_FILE_HEADER_SIZE];
+        reader.read_exact(&mut header_block)?;
+
+        let header = EvtxFileHeader::from_stream(&mut Cursor::new(header_block))?;
+
+        debug!("EVTX Header: {:#?}", header);
+        Ok(EvtxStreamParser {
+            data: reader,
+            header,
+            config: Arc::new(ParserSettings::default()),
+            next_chunk_number: 0,
+            exhausted: false,
+        })
+    }
+
+    pub fn with_configuration(mut self, configuration: ParserSettings) -> Self {
+        self.config = Arc::new(configuration);
+        self
+    }
+
+    /// The file header, with the chunk and record counters and the `DIRTY` and `FULL` flags.
+    pub fn header(&self) -> &EvtxFileHeader {
+        &self.header
+    }
+
+    /// Reads the next non-empty chunk from the stream.
+    /// Returns `None` once the stream is exhausted, or after an incomplete chunk was read.
+    fn next_chunk(&mut self) -> Option<Result<EvtxChunkData>> {
+        while !self.exhausted {
+            let chunk_number = self.next_chunk_number;
+            let mut chunk_data = Vec::with_capacity(EVTX_CHUNK_SIZE);
+
+            trace!("Reading chunk number `{}` from stream", chunk_number);
+
+            let amount_read = match (&mut self.data)
+                .take(EVTX_CHUNK_SIZE as u64)
+                .read_to_end(&mut chunk_data)
+            {
+                Ok(amount_read) => amount_read,
+                Err(err) => {
+                    self.exhausted = true;
+                    return Some(Err(EvtxError::IoError(err)));
+                }
+            };
+
+            if amount_read == 0 {
+                self.exhausted = true;
+                return None;
+            }
+
+            // A short read means the stream ended in the middle of this chunk.
+            self.exhausted = amount_read != EVTX_CHUNK_SIZE;
+            self.next_chunk_number = chunk_number.checked_add(1)?;
+
+            match load_chunk(
+                chunk_data,
+                chunk_number,
+                self.config.should_validate_checksums(),
+            ) {
+                // Empty chunks are skipped, like `EvtxParser` does for dirty files.
+                Ok(None) => continue,
+                Ok(Some(chunk)) => return Some(Ok(chunk)),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        None
+    }
+
+    /// Return an iterator over all the chunks, reading them from the stream one at a time.
+    pub fn chunks(&mut self) -> impl Iterator<Item = Result<EvtxChunkData>> + '_ {
+        std::iter::from_fn(move || self.next_chunk())
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be mapped `f`, which must produce owned data from the records.
+    pub fn serialized_records<'a, U: 'a>(
+        &'a mut self,
+        mut f: impl FnMut(Result<EvtxRecord<'_>>) -> Result<U> + 'a,
+    ) -> impl Iterator<Item = Result<U>> + 'a {
+        let error_policy = self.config.get_error_policy();
+        let chunk_settings = Arc::clone(&self.config);
+
+        let records = self.chunks().flat_map(move |chunk_res| match chunk_res {
+            Err(err) => vec![Err(err)],
+            Ok(chunk) => serialize_chunk(chunk, Arc::clone(&chunk_settings), &mut f),
+        });
+
+        apply_error_policy(records, error_policy)
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be XML-formatted.
+    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        let error_policy = self.config.get_error_policy();
+        self.serialized_records(move |record| {
+            serialize_record(
+                record,
+                error_policy,
+                EvtxRecord::into_xml,
+                RecordPlaceholder::to_xml,
+            )
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be JSON-formatted.
+    pub fn records_json(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        let error_policy = self.config.get_error_policy();
+        self.serialized_records(move |record| {
+            serialize_record(record, error_policy, EvtxRecord::into_json, |placeholder| {
+                placeholder.to_json_value().to_string()
+            })
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will have a `serde_json::Value` data attribute.
+    pub fn records_json_value(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<serde_json::Value>>> + '_ {
+        let error_policy = self.config.get_error_policy();
+        self.serialized_records(move |record| {
+            serialize_record(
+                record,
+                error_policy,
+                EvtxRecord::into_json_value,
+                RecordPlaceholder::to_json_value,
+            )
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be deserialized from their JSON representation into `U`.
+    pub fn records_deserialized<'a, U: DeserializeOwned + 'a>(
+        &'a mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<U>>> + 'a {
+        self.serialized_records(|record| record.and_then(EvtxRecord::into_deserialized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use crate::EvtxParser;
+
+    #[test]
+    fn test_stream_yields_the_same_records_as_the_parser() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        // `&[u8]` implements `Read`, but not `Seek`.
+        let mut stream_parser = EvtxStreamParser::from_reader(&evtx_file[..]).unwrap();
+        let streamed: Vec<String> = stream_parser.records().map(|r| r.unwrap().data).collect();
+
+        let mut parser = EvtxParser::from_bytes(evtx_file).unwrap();
+        let expected: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+
+        assert_eq!(streamed, expected);
+        assert_eq!(stream_parser.header(), parser.header());
+    }
+
+    #[test]
+    fn test_stream_of_a_partial_download_yields_complete_chunks() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let partial = &evtx_file[..EVTX_FILE_HEADER_SIZE + 2 * EVTX_CHUNK_SIZE + 1000];
+
+        let mut stream_parser = EvtxStreamParser::from_reader(partial).unwrap();
+        let records: Vec<_> = stream_parser.records_json_value().collect();
+
+        let mut parser = EvtxParser::from_bytes(evtx_file).unwrap();
+        let expected: usize = parser
+            .chunks()
+            .take(2)
+            .map(|chunk| chunk.unwrap().header.last_event_record_number as usize)
+            .last()
+            .unwrap();
+
+        assert_eq!(records.len(), expected + 1);
+        assert!(records[..expected].iter().all(|r| r.is_ok()));
+        assert!(matches!(
+            records.last(),
+            Some(Err(EvtxError::FailedToParseChunk { chunk_id: 2, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_stream_with_a_truncated_header_fails() {
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        assert!(EvtxStreamParser::from_reader(&evtx_file[..100]).is_err());
+    }
+}
@@ -0,0 +1,580 @@
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::err::{EvtxError, Result};
+use crate::evtx_chunk::EVTX_CHUNK_HEADER_SIZE;
+use crate::evtx_parser::{EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE};
+use crate::evtx_record::RecordId;
+use crate::model::xml::{XmlAttribute, XmlElement};
+use crate::{checksum_ieee, ChunkOffset};
+
+use chrono::{DateTime, Utc};
+use log::{debug, trace};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom, Write};
+
+/// Offset of the string table (64 hash buckets) in the chunk header.
+const STRING_TABLE_OFFSET: usize = 0x80;
+const STRING_TABLE_BUCKETS: usize = 64;
+/// Offset of the checksum of the chunk header, which covers everything but itself and the flags.
+const CHUNK_HEADER_CHECKSUM_OFFSET: usize = 0x7C;
+
+/// Seconds between the FILETIME epoch (1601-01-01) and the unix epoch.
+const FILETIME_EPOCH_OFFSET_SECONDS: i64 = 11_644_473_600;
+
+/// Encodes rendered XML events back into an evtx file, the inverse of `XmlOutput`.
+///
+/// This is meant for building fixtures and fuzzing corpora, not for producing logs which
+/// look like the ones Windows writes:
+/// - Records are written as plain BinXml, without templates, so every value is a string
+///   (`EventID` is rendered as `"4624"` in JSON, for example).
+/// - Text which is only made of whitespace (such as indentation) is dropped, and comments
+///   and the XML declaration are ignored.
+///
+/// The record id is taken from `Event/System/EventRecordID`, and the record timestamp from
+/// `Event/System/TimeCreated/@SystemTime` when they are present. Otherwise, records are
+/// numbered after the previous one, and stamped with the unix epoch.
+///
+/// ```rust
+/// # use evtx::{EvtxParser, EvtxWriter};
+/// # use std::io::Cursor;
+/// let mut writer = EvtxWriter::new(Cursor::new(Vec::new())).unwrap();
+/// writer
+///     .write_xml_record(r#"<Event><System><EventID>4624</EventID></System></Event>"#)
+///     .unwrap();
+/// let file = writer.finish().unwrap().into_inner();
+///
+/// let mut parser = EvtxParser::from_buffer(file).unwrap();
+/// assert_eq!(parser.records().count(), 1);
+/// ```
+pub struct EvtxWriter<W: Write + Seek> {
+    writer: W,
+    chunk: ChunkBuilder,
+    chunk_count: u64,
+    next_record_id: u64,
+}
+
+impl<W: Write + Seek> EvtxWriter<W> {
+    /// Starts a new evtx file in `writer`, the file header is written by `finish`.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&[0; EVTX_FILE_HEADER_SIZE])?;
+
+        Ok(EvtxWriter {
+            writer,
+            chunk: ChunkBuilder::new(),
+            chunk_count: 0,
+            next_record_id: 1,
+        })
+    }
+
+    /// Encodes a single rendered event (an `<Event>` document), and returns its record id.
+    pub fn write_xml_record(&mut self, xml: &str) -> Result<RecordId> {
+        let nodes = parse_xml(xml)?;
+        let (record_id, timestamp) = record_id_and_timestamp(&nodes);
+        let record_id = record_id.unwrap_or(self.next_record_id);
+        let timestamp = timestamp.unwrap_or_else(|| DateTime::<Utc>::from(std::time::UNIX_EPOCH));
+
+        if !self.chunk.try_write_record(record_id, timestamp, &nodes) {
+            if self.chunk.is_empty() {
+                return Err(EvtxError::FailedToEncodeRecord {
+                    message: format!("record {} does not fit in a single chunk", record_id),
+                });
+            }
+
+            self.flush_chunk()?;
+
+            if !self.chunk.try_write_record(record_id, timestamp, &nodes) {
+                return Err(EvtxError::FailedToEncodeRecord {
+                    message: format!("record {} does not fit in a single chunk", record_id),
+                });
+            }
+        }
+
+        self.next_record_id = record_id.saturating_add(1);
+        Ok(record_id)
+    }
+
+    fn flush_chunk(&mut self) -> Result<()> {
+        let chunk = std::mem::replace(&mut self.chunk, ChunkBuilder::new());
+        debug!("Writing chunk number `{}`", self.chunk_count);
+
+        self.writer.write_all(&chunk.into_bytes())?;
+        self.chunk_count += 1;
+
+        Ok(())
+    }
+
+    /// Writes the last chunk and the file header, and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.chunk.is_empty() {
+            self.flush_chunk()?;
+        }
+
+        let chunk_count =
+            u16::try_from(self.chunk_count).map_err(|_| EvtxError::FailedToEncodeRecord {
+                message: format!("too many chunks for a single file ({})", self.chunk_count),
+            })?;
+
+        let mut header = Vec::with_capacity(EVTX_FILE_HEADER_SIZE);
+        header.extend_from_slice(b"ElfFile\x00");
+        // Oldest chunk, current chunk, next record id.
+        header.extend_from_slice(&0_u64.to_le_bytes());
+        header.extend_from_slice(&self.chunk_count.saturating_sub(1).to_le_bytes());
+        header.extend_from_slice(&self.next_record_id.to_le_bytes());
+        // Header size, minor version, major version, header block size, chunk count.
+        header.extend_from_slice(&128_u32.to_le_bytes());
+        header.extend_from_slice(&1_u16.to_le_bytes());
+        header.extend_from_slice(&3_u16.to_le_bytes());
+        header.extend_from_slice(&(EVTX_FILE_HEADER_SIZE as u16).to_le_bytes());
+        header.extend_from_slice(&chunk_count.to_le_bytes());
+        header.resize(120, 0);
+        // Flags, the file is closed properly.
+        header.extend_from_slice(&0_u32.to_le_bytes());
+        let checksum = checksum_ieee(&header[..120]);
+        header.extend_from_slice(&checksum.to_le_bytes());
+        header.resize(EVTX_FILE_HEADER_SIZE, 0);
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// A node of a rendered event, in document order.
+enum XmlNode {
+    Open(XmlElement<'static>),
+    /// An element without content, `<Correlation/>`.
+    Empty(XmlElement<'static>),
+    Close,
+    Text(String),
+    CData(String),
+    PI(String, String),
+}
+
+fn parse_xml(xml: &str) -> Result<Vec<XmlNode>> {
+    let invalid_xml = |e: quick_xml::Error| EvtxError::FailedToEncodeRecord {
+        message: format!("invalid XML: {}", e),
+    };
+
+    let mut reader = Reader::from_str(xml);
+    let mut nodes = vec![];
+    // `<Data></Data>` holds an empty string, while `<Data/>` (or indentation only) is empty.
+    let mut element_has_no_content = false;
+
+    loop {
+        let event = reader.read_event().map_err(invalid_xml)?;
+        let previous_element_has_no_content = element_has_no_content;
+        element_has_no_content = matches!(event, Event::Start(_));
+
+        match event {
+            Event::Start(start) => nodes.push(XmlNode::Open(to_element(&start)?)),
+            Event::Empty(empty) => nodes.push(XmlNode::Empty(to_element(&empty)?)),
+            Event::End(_) => {
+                if previous_element_has_no_content {
+                    nodes.push(XmlNode::Text(String::new()));
+                }
+                nodes.push(XmlNode::Close)
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(invalid_xml)?;
+                if text.is_empty() {
+                    element_has_no_content = previous_element_has_no_content;
+                } else if !text.trim().is_empty() {
+                    nodes.push(XmlNode::Text(text.into_owned()));
+                }
+            }
+            Event::CData(cdata) => nodes.push(XmlNode::CData(
+                String::from_utf8_lossy(&cdata.into_inner()).into_owned(),
+            )),
+            Event::PI(pi) => {
+                let content = String::from_utf8_lossy(&pi);
+                let (target, data) = content.split_once(' ').unwrap_or((&content, ""));
+                nodes.push(XmlNode::PI(target.to_owned(), data.to_owned()));
+            }
+            Event::Comment(_) | Event::Decl(_) | Event::DocType(_) => {}
+            Event::Eof => break,
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn to_element(start: &BytesStart) -> Result<XmlElement<'static>> {
+    let mut attributes = vec![];
+
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| EvtxError::FailedToEncodeRecord {
+            message: format!("invalid XML attribute: {}", e),
+        })?;
+        let value = attribute
+            .unescape_value()
+            .map_err(|e| EvtxError::FailedToEncodeRecord {
+                message: format!("invalid XML attribute: {}", e),
+            })?;
+
+        attributes.push(XmlAttribute {
+            name: Cow::Owned(BinXmlName::from_string(
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+            )),
+            value: Cow::Owned(BinXmlValue::StringType(value.into_owned())),
+        });
+    }
+
+    Ok(XmlElement {
+        name: Cow::Owned(BinXmlName::from_string(
+            String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        )),
+        attributes,
+    })
+}
+
+/// Reads `Event/System/EventRecordID` and `Event/System/TimeCreated/@SystemTime`.
+fn record_id_and_timestamp(nodes: &[XmlNode]) -> (Option<u64>, Option<DateTime<Utc>>) {
+    let mut path: Vec<&str> = vec![];
+    let mut record_id = None;
+    let mut timestamp = None;
+
+    for node in nodes {
+        match node {
+            XmlNode::Open(element) | XmlNode::Empty(element) => {
+                let name = element.name.as_str();
+                if path == ["Event", "System"] && name == "TimeCreated" {
+                    timestamp = element
+                        .attributes
+                        .iter()
+                        .find(|attribute| attribute.name.as_str() == "SystemTime")
+                        .and_then(|attribute| {
+                            DateTime::parse_from_rfc3339(&attribute.value.as_cow_str()).ok()
+                        })
+                        .map(|timestamp| timestamp.with_timezone(&Utc));
+                }
+                if let XmlNode::Open(_) = node {
+                    path.push(name);
+                }
+            }
+            XmlNode::Close => {
+                path.pop();
+            }
+            XmlNode::Text(text) if path == ["Event", "System", "EventRecordID"] => {
+                record_id = text.trim().parse().ok();
+            }
+            _ => {}
+        }
+    }
+
+    (record_id, timestamp)
+}
+
+fn to_filetime(timestamp: DateTime<Utc>) -> u64 {
+    let seconds = timestamp.timestamp() + FILETIME_EPOCH_OFFSET_SECONDS;
+    let intervals =
+        i128::from(seconds) * 10_000_000 + i128::from(timestamp.timestamp_subsec_nanos() / 100);
+
+    u64::try_from(intervals).unwrap_or(0)
+}
+
+/// The hash Windows stores next to names in the string table.
+fn name_hash(name: &[u16]) -> u32 {
+    name.iter().fold(0_u32, |hash, &c| {
+        hash.wrapping_mul(65599).wrapping_add(u32::from(c))
+    })
+}
+
+/// A chunk which is being filled with records.
+struct ChunkBuilder {
+    data: Vec<u8>,
+    /// The offset of every name which was already defined in this chunk.
+    names: HashMap<String, ChunkOffset>,
+    /// The first and the last name of every bucket of the string table.
+    string_table: [Option<(ChunkOffset, ChunkOffset)>; STRING_TABLE_BUCKETS],
+    first_record_id: Option<u64>,
+    last_record_id: u64,
+    last_record_offset: ChunkOffset,
+}
+
+impl ChunkBuilder {
+    fn new() -> Self {
+        let mut data = Vec::with_capacity(EVTX_CHUNK_SIZE);
+        data.resize(EVTX_CHUNK_HEADER_SIZE, 0);
+
+        ChunkBuilder {
+            data,
+            names: HashMap::new(),
+            string_table: [None; STRING_TABLE_BUCKETS],
+            first_record_id: None,
+            last_record_id: 0,
+            last_record_offset: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.first_record_id.is_none()
+    }
+
+    /// Appends a record, returns `false` (leaving the chunk as it was) if it does not fit.
+    fn try_write_record(
+        &mut self,
+        record_id: u64,
+        timestamp: DateTime<Utc>,
+        nodes: &[XmlNode],
+    ) -> bool {
+        let record_offset = self.data.len();
+        let names = self.names.clone();
+        let string_table = self.string_table;
+
+        self.data.extend_from_slice(b"\x2a\x2a\x00\x00");
+        let size_offset = self.reserve_u32();
+        self.data.extend_from_slice(&record_id.to_le_bytes());
+        self.data
+            .extend_from_slice(&to_filetime(timestamp).to_le_bytes());
+
+        self.write_binxml(nodes);
+
+        let size = (self.data.len() - record_offset + 4) as u32;
+        self.data.extend_from_slice(&size.to_le_bytes());
+        self.patch_u32(size_offset, size);
+
+        if self.data.len() > EVTX_CHUNK_SIZE {
+            trace!("Record {} does not fit in the chunk", record_id);
+            self.data.truncate(record_offset);
+            // Unlink the names of this record from the names which were already in the chunk.
+            for (bucket, previous) in string_table.iter().enumerate() {
+                if let Some((_, last)) = previous {
+                    if self.string_table[bucket] != *previous {
+                        self.patch_u32(*last as usize, 0);
+                    }
+                }
+            }
+            self.names = names;
+            self.string_table = string_table;
+            return false;
+        }
+
+        self.first_record_id.get_or_insert(record_id);
+        self.last_record_id = record_id;
+        self.last_record_offset = record_offset as ChunkOffset;
+
+        true
+    }
+
+    fn write_binxml(&mut self, nodes: &[XmlNode]) {
+        // Fragment header, version 1.1.
+        self.data.extend_from_slice(&[0x0f, 0x01, 0x01, 0x00]);
+
+        // The offsets of the `data_size` of the open elements, and whether their start tag is open.
+        let mut open_elements: Vec<(usize, bool)> = vec![];
+
+        for node in nodes {
+            // Anything but the end of an element means that its start tag has content.
+            if !matches!(node, XmlNode::Close) {
+                if let Some((_, start_tag_open)) = open_elements.last_mut() {
+                    if std::mem::replace(start_tag_open, false) {
+                        // CloseStartElement
+                        self.data.push(0x02);
+                    }
+                }
+            }
+
+            match node {
+                XmlNode::Open(element) => {
+                    let data_size_offset = self.write_start_element(element);
+                    open_elements.push((data_size_offset, true));
+                }
+                XmlNode::Empty(element) => {
+                    let data_size_offset = self.write_start_element(element);
+                    // CloseEmptyElement
+                    self.data.push(0x03);
+                    self.patch_data_size(data_size_offset);
+                }
+                XmlNode::Close => {
+                    if let Some((data_size_offset, start_tag_open)) = open_elements.pop() {
+                        // CloseEmptyElement or CloseElement
+                        self.data.push(if start_tag_open { 0x03 } else { 0x04 });
+                        self.patch_data_size(data_size_offset);
+                    }
+                }
+                XmlNode::Text(text) => self.write_text(text),
+                XmlNode::CData(cdata) => {
+                    self.data.push(0x07);
+                    self.write_len_prefixed_str(cdata);
+                }
+                XmlNode::PI(target, data) => {
+                    self.data.push(0x0a);
+                    self.write_name(target);
+                    self.data.push(0x0b);
+                    self.write_len_prefixed_str(data);
+                }
+            }
+        }
+
+        // EndOfStream
+        self.data.push(0x00);
+    }
+
+    /// Writes the start tag of `element`, without closing it, returns the offset of its size.
+    fn write_start_element(&mut self, element: &XmlElement) -> usize {
+        let has_attributes = !element.attributes.is_empty();
+
+        // OpenStartElement, with a dependency identifier of -1.
+        self.data.push(if has_attributes { 0x41 } else { 0x01 });
+        self.data.extend_from_slice(&0xFFFF_u16.to_le_bytes());
+        let data_size_offset = self.reserve_u32();
+        self.write_name(element.name.as_str());
+
+        if has_attributes {
+            let attributes_size_offset = self.reserve_u32();
+            let last = element.attributes.len() - 1;
+
+            for (i, attribute) in element.attributes.iter().enumerate() {
+                // Attribute, with the "more attributes follow" flag.
+                self.data.push(if i == last { 0x06 } else { 0x46 });
+                self.write_name(attribute.name.as_str());
+                self.write_text(&attribute.value.as_cow_str());
+            }
+
+            let size = self.data.len() - attributes_size_offset - 4;
+            self.patch_u32(attributes_size_offset, size as u32);
+        }
+
+        data_size_offset
+    }
+
+    /// Writes `text` as string values, split if it is too long for a single one.
+    fn write_text(&mut self, text: &str) {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let mut rest = units.as_slice();
+
+        loop {
+            let mut len = rest.len().min(usize::from(u16::MAX));
+            // Do not split a surrogate pair.
+            if len < rest.len() && (0xD800..0xDC00).contains(&rest[len - 1]) {
+                len -= 1;
+            }
+            let (part, remaining) = rest.split_at(len);
+
+            // Value, of type string.
+            self.data.extend_from_slice(&[0x05, 0x01]);
+            self.data
+                .extend_from_slice(&(part.len() as u16).to_le_bytes());
+            self.write_utf16(part);
+
+            rest = remaining;
+            if rest.is_empty() {
+                break;
+            }
+        }
+    }
+
+    fn write_len_prefixed_str(&mut self, s: &str) {
+        let units: Vec<u16> = s.encode_utf16().take(usize::from(u16::MAX)).collect();
+        self.data
+            .extend_from_slice(&(units.len() as u16).to_le_bytes());
+        self.write_utf16(&units);
+    }
+
+    /// Writes a reference to `name`, which is defined in place the first time it is used in
+    /// the chunk, and added to the string table.
+    fn write_name(&mut self, name: &str) {
+        if let Some(&offset) = self.names.get(name) {
+            self.data.extend_from_slice(&offset.to_le_bytes());
+            return;
+        }
+
+        let offset = (self.data.len() + 4) as ChunkOffset;
+        let units: Vec<u16> = name.encode_utf16().take(usize::from(u16::MAX)).collect();
+        let hash = name_hash(&units);
+
+        self.data.extend_from_slice(&offset.to_le_bytes());
+        // Offset of the next name in the same bucket, patched when it is added.
+        self.data.extend_from_slice(&0_u32.to_le_bytes());
+        self.data.extend_from_slice(&(hash as u16).to_le_bytes());
+        self.data
+            .extend_from_slice(&(units.len() as u16).to_le_bytes());
+        self.write_utf16(&units);
+        self.data.extend_from_slice(&0_u16.to_le_bytes());
+
+        let bucket = hash as usize % STRING_TABLE_BUCKETS;
+        self.string_table[bucket] = match self.string_table[bucket] {
+            None => Some((offset, offset)),
+            Some((first, last)) => {
+                self.patch_u32(last as usize, offset);
+                Some((first, offset))
+            }
+        };
+        self.names.insert(name.to_owned(), offset);
+    }
+
+    fn write_utf16(&mut self, units: &[u16]) {
+        for unit in units {
+            self.data.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    fn reserve_u32(&mut self) -> usize {
+        let offset = self.data.len();
+        self.data.extend_from_slice(&0_u32.to_le_bytes());
+        offset
+    }
+
+    fn patch_u32(&mut self, offset: usize, value: u32) {
+        self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// The size of an element is counted from after its size field, to its end.
+    fn patch_data_size(&mut self, data_size_offset: usize) {
+        let size = self.data.len() - data_size_offset - 4;
+        self.patch_u32(data_size_offset, size as u32);
+    }
+
+    /// Writes the chunk header, and pads the chunk to its full size.
+    fn into_bytes(mut self) -> Vec<u8> {
+        let free_space_offset = self.data.len() as u32;
+        let first_record_id = self.first_record_id.unwrap_or(0);
+        let events_checksum = checksum_ieee(&self.data[EVTX_CHUNK_HEADER_SIZE..]);
+
+        let mut header = Vec::with_capacity(EVTX_CHUNK_HEADER_SIZE);
+        header.extend_from_slice(b"ElfChnk\x00");
+        // Record numbers and record ids are the same.
+        header.extend_from_slice(&first_record_id.to_le_bytes());
+        header.extend_from_slice(&self.last_record_id.to_le_bytes());
+        header.extend_from_slice(&first_record_id.to_le_bytes());
+        header.extend_from_slice(&self.last_record_id.to_le_bytes());
+        header.extend_from_slice(&128_u32.to_le_bytes());
+        header.extend_from_slice(&self.last_record_offset.to_le_bytes());
+        header.extend_from_slice(&free_space_offset.to_le_bytes());
+        header.extend_from_slice(&events_checksum.to_le_bytes());
+        // Reserved, then no flags.
+        header.resize(CHUNK_HEADER_CHECKSUM_OFFSET - 4, 0);
+        header.extend_from_slice(&0_u32.to_le_bytes());
+        // Checksum, written below.
+        header.extend_from_slice(&0_u32.to_le_bytes());
+
+        debug_assert_eq!(header.len(), STRING_TABLE_OFFSET);
+        for bucket in self.string_table.iter() {
+            let first = bucket.map_or(0, |(first, _)| first);
+            header.extend_from_slice(&first.to_le_bytes());
+        }
+        // No templates.
+        header.resize(EVTX_CHUNK_HEADER_SIZE, 0);
+
+        let checksum = checksum_ieee(
+            &[
+                &header[..CHUNK_HEADER_CHECKSUM_OFFSET - 4],
+                &header[CHUNK_HEADER_CHECKSUM_OFFSET + 4..],
+            ]
+            .concat(),
+        );
+        header[CHUNK_HEADER_CHECKSUM_OFFSET..CHUNK_HEADER_CHECKSUM_OFFSET + 4]
+            .copy_from_slice(&checksum.to_le_bytes());
+
+        self.data[..EVTX_CHUNK_HEADER_SIZE].copy_from_slice(&header);
+        self.data.resize(EVTX_CHUNK_SIZE, 0);
+        self.data
+    }
+}
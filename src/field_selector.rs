@@ -0,0 +1,183 @@
+use crate::err::SerializationResult;
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::json_output::{expand_character_reference, expand_entity_reference};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::{BinXmlOutput, OnUnhandled};
+
+use log::trace;
+use std::borrow::Cow;
+
+/// A single field, selecting either the text of an element or one of its attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldPath {
+    path: Vec<String>,
+    attribute: Option<String>,
+}
+
+impl FieldPath {
+    fn parse(spec: &str) -> Self {
+        let mut path: Vec<String> = spec
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        let attribute = match path.last() {
+            Some(last) if last.starts_with('@') => {
+                let attribute = last[1..].to_owned();
+                path.pop();
+                Some(attribute)
+            }
+            _ => None,
+        };
+
+        FieldPath { path, attribute }
+    }
+}
+
+/// The path segment of an element, `Data` nodes are addressed by their `Name` attribute.
+fn path_segment(element: &XmlElement) -> String {
+    let name = element.name.as_str();
+
+    if name == "Data" {
+        element
+            .attribute_str("Name")
+            .map(Cow::into_owned)
+            .unwrap_or_else(|| name.to_owned())
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Collects the text of a fixed list of fields from a record, for outputs which write
+/// one row per record (`CsvOutput`, `ColumnarOutput`).
+///
+/// Paths are `/` separated element names, starting from the root element.
+/// A trailing `@name` segment selects an attribute instead of the element's text,
+/// and `Data` nodes are addressed by their `Name` attribute, like in JSON output.
+/// If a path matches several elements, only the first one is used.
+///
+/// The selector is driven like any other output, and is reset by `visit_start_of_stream`.
+#[derive(Debug)]
+pub(crate) struct FieldSelector {
+    paths: Vec<FieldPath>,
+    cells: Vec<Option<String>>,
+    closed: Vec<bool>,
+    stack: Vec<String>,
+    on_unhandled: OnUnhandled,
+}
+
+impl FieldSelector {
+    pub(crate) fn new<S: AsRef<str>>(paths: &[S]) -> Self {
+        FieldSelector {
+            paths: paths.iter().map(|p| FieldPath::parse(p.as_ref())).collect(),
+            cells: vec![None; paths.len()],
+            closed: vec![false; paths.len()],
+            stack: vec![],
+            on_unhandled: OnUnhandled::default(),
+        }
+    }
+
+    /// Sets what is done with processing instructions and comments, which are not rendered.
+    pub(crate) fn set_on_unhandled(&mut self, on_unhandled: OnUnhandled) {
+        self.on_unhandled = on_unhandled;
+    }
+
+    /// The text of every field of the current record, `None` if it is missing.
+    pub(crate) fn cells(&self) -> &[Option<String>] {
+        &self.cells
+    }
+
+    /// Mutable access to the cells, so that their values can be taken.
+    pub(crate) fn cells_mut(&mut self) -> &mut [Option<String>] {
+        &mut self.cells
+    }
+
+    fn append_text(&mut self, text: &str) {
+        for (i, field) in self.paths.iter().enumerate() {
+            if field.attribute.is_none() && !self.closed[i] && field.path == self.stack {
+                self.cells[i].get_or_insert_with(String::new).push_str(text);
+            }
+        }
+    }
+}
+
+impl BinXmlOutput for FieldSelector {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        trace!("visit_open_start_element: {:?}", element.name);
+        self.stack.push(path_segment(element));
+
+        for (i, field) in self.paths.iter().enumerate() {
+            if self.closed[i] || field.path != self.stack {
+                continue;
+            }
+
+            if let Some(attribute_name) = &field.attribute {
+                if let Some(value) = element.attribute_str(attribute_name) {
+                    self.cells[i] = Some(value.into_owned());
+                }
+                self.closed[i] = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        for (i, field) in self.paths.iter().enumerate() {
+            if field.attribute.is_none() && field.path == self.stack {
+                self.closed[i] = true;
+            }
+        }
+
+        let p = self.stack.pop();
+        trace!("visit_close_element: {:?}", p);
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        trace!("visit_chars {:?}", &self.stack);
+        self.append_text(&value.as_cow_str());
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.append_text(&cdata);
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        let expanded = expand_entity_reference(entity)?;
+        self.append_text(expanded);
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        let c = expand_character_reference(&char_ref)?;
+        self.append_text(c.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> SerializationResult<()> {
+        self.on_unhandled.handle("processing instruction")
+    }
+
+    fn unhandled_policy(&self) -> OnUnhandled {
+        self.on_unhandled
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        self.cells.iter_mut().for_each(|cell| *cell = None);
+        self.closed.iter_mut().for_each(|closed| *closed = false);
+        self.stack.clear();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::json_output::{expand_character_reference, expand_entity_reference};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+
+use log::trace;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A node which is currently open, and it's full dotted path.
+struct FlatNode {
+    path: String,
+    /// How many times each child name was seen, used to disambiguate repeated nodes.
+    children: HashMap<String, usize>,
+    has_content: bool,
+    has_attributes: bool,
+}
+
+/// Produces a single level JSON object, with dotted keys for every leaf value.
+///
+/// ```text
+/// {
+///   "Event.System.EventID": 4624,
+///   "Event.System.Provider_attributes.Name": "Microsoft-Windows-Security-Auditing",
+///   "Event.EventData.TargetUserName": "bob",
+///   "Event.EventData.Data.0": "first unnamed data node"
+/// }
+/// ```
+///
+/// Attributes are written under `{element}_attributes`, like when `separate_json_attributes` is set.
+/// `Data` nodes use their `Name` attribute as a key, unnamed `Data` nodes are numbered in order.
+/// Other repeated nodes are disambiguated using the same `_1`, `_2` suffixes as `JsonOutput`.
+pub struct FlatJsonOutput {
+    map: Map<String, Value>,
+    stack: Vec<FlatNode>,
+    closed_elements: usize,
+    eof_reached: bool,
+}
+
+impl Default for FlatJsonOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlatJsonOutput {
+    pub fn new() -> Self {
+        FlatJsonOutput {
+            map: Map::new(),
+            stack: vec![],
+            closed_elements: 0,
+            eof_reached: false,
+        }
+    }
+
+    fn current_path(&self) -> &str {
+        self.stack
+            .last()
+            .map(|node| node.path.as_str())
+            .unwrap_or("")
+    }
+
+    fn join(parent: &str, key: &str) -> String {
+        if parent.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{}", parent, key)
+        }
+    }
+
+    /// The key of the next child of the current node.
+    fn next_child_key(&mut self, element: &XmlElement) -> String {
+        let name = element.name.as_str();
+
+        let data_name = if name == "Data" {
+            element.attribute_str("Name").map(Cow::into_owned)
+        } else {
+            None
+        };
+
+        let (counter_key, is_unnamed_data) = match data_name {
+            Some(data_name) => (data_name, false),
+            None => (name.to_owned(), name == "Data"),
+        };
+
+        let seen = match self.stack.last_mut() {
+            Some(parent) => {
+                parent.has_content = true;
+                let seen = parent.children.entry(counter_key.clone()).or_insert(0);
+                *seen += 1;
+                *seen - 1
+            }
+            None => 0,
+        };
+
+        if is_unnamed_data {
+            format!("{}.{}", counter_key, seen)
+        } else if seen == 0 {
+            counter_key
+        } else {
+            format!("{}_{}", counter_key, seen)
+        }
+    }
+
+    /// Inserts a value at `{current path}{suffix}`, concatenating with any existing text.
+    fn insert_text(&mut self, suffix: &str, value: Value) {
+        let key = self.current_path().to_owned() + suffix;
+
+        if let Some(node) = self.stack.last_mut() {
+            node.has_content = true;
+        }
+
+        match self.map.get_mut(&key) {
+            Some(Value::String(existing)) => match value {
+                Value::String(s) => existing.push_str(&s),
+                other => existing.push_str(&other.to_string()),
+            },
+            Some(existing) if !existing.is_null() => {
+                let mut concatenated = existing.to_string();
+                match value {
+                    Value::String(s) => concatenated.push_str(&s),
+                    other => concatenated.push_str(&other.to_string()),
+                }
+                *existing = Value::String(concatenated);
+            }
+            _ => {
+                self.map.insert(key, value);
+            }
+        }
+    }
+
+    /// Fails with `IncompleteOutput` on a truncated record, or `UnexpectedEof` on a broken one.
+    pub fn into_value(self) -> SerializationResult<Value> {
+        if !self.eof_reached {
+            return Err(SerializationError::IncompleteOutput);
+        }
+
+        if !self.stack.is_empty() {
+            return Err(SerializationError::UnexpectedEof {
+                open_elements: self.stack.len(),
+            });
+        }
+
+        Ok(Value::Object(self.map))
+    }
+}
+
+impl BinXmlOutput for FlatJsonOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        self.eof_reached = true;
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        trace!("visit_open_start_element: {:?}", element.name);
+        let key = self.next_child_key(element);
+        let path = Self::join(self.current_path(), &key);
+
+        let is_data = element.name.as_str() == "Data";
+        let mut has_attributes = false;
+
+        for attribute in element.attributes.iter() {
+            let name = attribute.name.as_str();
+            if is_data && name == "Name" {
+                continue;
+            }
+
+            let value: Value = attribute.value.clone().into_owned().into();
+            if !value.is_null() {
+                has_attributes = true;
+                self.map
+                    .insert(format!("{}_attributes.{}", path, name), value);
+            }
+        }
+
+        self.stack.push(FlatNode {
+            path,
+            children: HashMap::new(),
+            has_content: false,
+            has_attributes,
+        });
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        let node = self
+            .stack
+            .pop()
+            .ok_or(SerializationError::UnbalancedStack {
+                closed_elements: self.closed_elements,
+            })?;
+        trace!("visit_close_element: {:?}", node.path);
+        self.closed_elements += 1;
+
+        // Keep empty nodes, so that they are not lost entirely.
+        if !node.has_content && !node.has_attributes {
+            self.map.insert(node.path, Value::Null);
+        }
+
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        trace!("visit_chars {:?}", self.current_path());
+        let value = match value {
+            Cow::Owned(BinXmlValue::StringType(value)) => json!(value),
+            value => value.into_owned().into(),
+        };
+
+        self.insert_text("", value);
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.insert_text(".#cdata", json!(cdata));
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        let expanded = expand_entity_reference(entity)?;
+        self.insert_text("", json!(expanded));
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        let c = expand_character_reference(&char_ref)?;
+        self.insert_text("", json!(c.to_string()));
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        let key = Self::join(self.current_path(), "#processing_instructions");
+        let pi = json!({"target": pi.name.as_str(), "data": pi.data});
+
+        match self.map.get_mut(&key) {
+            Some(Value::Array(instructions)) => instructions.push(pi),
+            _ => {
+                self.map.insert(key, json!([pi]));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlatJsonOutput;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::err::SerializationError;
+    use crate::model::xml::test_helpers::element;
+    use crate::model::xml::XmlElement;
+    use crate::BinXmlOutput;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::borrow::Cow;
+
+    fn leaf(output: &mut FlatJsonOutput, element: &XmlElement, value: BinXmlValue) {
+        output.visit_open_start_element(element).unwrap();
+        output.visit_characters(Cow::Owned(value)).unwrap();
+        output.visit_close_element(element).unwrap();
+    }
+
+    #[test]
+    fn test_flat_json_output() {
+        let mut output = FlatJsonOutput::new();
+        let event = element("Event", &[]);
+        let system = element("System", &[]);
+        let event_data = element("EventData", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&system).unwrap();
+        let provider = element("Provider", &[("Name", "Security")]);
+        output.visit_open_start_element(&provider).unwrap();
+        output.visit_close_element(&provider).unwrap();
+        leaf(
+            &mut output,
+            &element("EventID", &[]),
+            BinXmlValue::UInt16Type(4624),
+        );
+        let keywords = element("Keywords", &[]);
+        output.visit_open_start_element(&keywords).unwrap();
+        output.visit_close_element(&keywords).unwrap();
+        output.visit_close_element(&system).unwrap();
+
+        output.visit_open_start_element(&event_data).unwrap();
+        leaf(
+            &mut output,
+            &element("Data", &[("Name", "TargetUserName")]),
+            BinXmlValue::StringType("bob".to_string()),
+        );
+        leaf(
+            &mut output,
+            &element("Data", &[]),
+            BinXmlValue::StringType("first".to_string()),
+        );
+        leaf(
+            &mut output,
+            &element("Data", &[]),
+            BinXmlValue::StringType("second".to_string()),
+        );
+        output.visit_close_element(&event_data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            output.into_value().unwrap(),
+            json!({
+                "Event.System.Provider_attributes.Name": "Security",
+                "Event.System.EventID": 4624,
+                "Event.System.Keywords": null,
+                "Event.EventData.TargetUserName": "bob",
+                "Event.EventData.Data.0": "first",
+                "Event.EventData.Data.1": "second",
+            })
+        );
+    }
+
+    #[test]
+    fn test_flat_json_output_repeated_nodes() {
+        let mut output = FlatJsonOutput::new();
+        let info = element("HTTPResponseHeadersInfo", &[]);
+        let header = element("Header", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&info).unwrap();
+        leaf(
+            &mut output,
+            &header,
+            BinXmlValue::StringType("HTTP/1.1 200 OK".to_string()),
+        );
+        leaf(
+            &mut output,
+            &header,
+            BinXmlValue::StringType("x-ms-version: 2009-09-19".to_string()),
+        );
+        output.visit_close_element(&info).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            output.into_value().unwrap(),
+            json!({
+                "HTTPResponseHeadersInfo.Header": "HTTP/1.1 200 OK",
+                "HTTPResponseHeadersInfo.Header_1": "x-ms-version: 2009-09-19",
+            })
+        );
+    }
+
+    #[test]
+    fn test_flat_json_output_truncated_and_unbalanced() {
+        let event = element("Event", &[]);
+
+        let mut truncated = FlatJsonOutput::new();
+        truncated.visit_start_of_stream().unwrap();
+        truncated.visit_open_start_element(&event).unwrap();
+        assert!(matches!(
+            truncated.into_value(),
+            Err(SerializationError::IncompleteOutput)
+        ));
+
+        let mut unbalanced = FlatJsonOutput::new();
+        unbalanced.visit_start_of_stream().unwrap();
+        unbalanced.visit_open_start_element(&event).unwrap();
+        unbalanced.visit_end_of_stream().unwrap();
+        assert!(matches!(
+            unbalanced.into_value(),
+            Err(SerializationError::UnexpectedEof { open_elements: 1 })
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::err::SerializationResult;
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::json_output::{expand_character_reference, expand_entity_reference};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+
+use log::trace;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+
+const START_ELEMENT: u8 = 1;
+const END_ELEMENT: u8 = 2;
+const TEXT: u8 = 3;
+const PROCESSING_INSTRUCTION: u8 = 4;
+
+/// Computes a SHA-256 digest of the visited records, instead of rendering them.
+///
+/// The digest is computed over a canonical serialization of the visitor events, so it does not
+/// depend on any output formatting. Adjacent text (including CDATA sections, entity and character
+/// references) is joined and trimmed, and whitespace-only text is ignored.
+pub struct HashingOutput {
+    hasher: Sha256,
+    text: String,
+}
+
+impl Default for HashingOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashingOutput {
+    pub fn new() -> Self {
+        HashingOutput {
+            hasher: Sha256::new(),
+            text: String::new(),
+        }
+    }
+
+    /// Returns the digest of everything visited so far.
+    pub fn into_writer(mut self) -> [u8; 32] {
+        self.flush_text();
+        self.hasher.finalize().into()
+    }
+
+    fn update_str(&mut self, s: &str) {
+        self.hasher.update((s.len() as u64).to_le_bytes());
+        self.hasher.update(s.as_bytes());
+    }
+
+    fn flush_text(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        let trimmed = text.trim();
+
+        if !trimmed.is_empty() {
+            self.hasher.update([TEXT]);
+            self.update_str(trimmed);
+        }
+    }
+}
+
+impl BinXmlOutput for HashingOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        self.flush_text();
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        trace!("visit_open_start_element: {:?}", element.name);
+        self.flush_text();
+
+        self.hasher.update([START_ELEMENT]);
+        self.update_str(element.name.as_str());
+        self.hasher
+            .update((element.attributes.len() as u64).to_le_bytes());
+
+        for attribute in element.attributes.iter() {
+            self.update_str(attribute.name.as_str());
+            self.update_str(&attribute.value.as_cow_str());
+        }
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        self.flush_text();
+        self.hasher.update([END_ELEMENT]);
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.text.push_str(&value.as_cow_str());
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.text.push_str(&cdata);
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.text.push_str(expand_entity_reference(entity)?);
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.text.push(expand_character_reference(&char_ref)?);
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.flush_text();
+        self.hasher.update([PROCESSING_INSTRUCTION]);
+        self.update_str(pi.name.as_str());
+        self.update_str(&pi.data);
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashingOutput;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::model::xml::test_helpers::element;
+    use crate::BinXmlOutput;
+    use std::borrow::Cow;
+
+    fn hash(texts: &[&str], attribute: &str) -> [u8; 32] {
+        let mut output = HashingOutput::new();
+        let event = element("Event", &[]);
+        let data = element("Data", &[("Name", attribute)]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::StringType("\n  ".to_string())))
+            .unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        for text in texts {
+            output
+                .visit_characters(Cow::Owned(BinXmlValue::StringType(text.to_string())))
+                .unwrap();
+        }
+        output.visit_close_element(&data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        output.into_writer()
+    }
+
+    #[test]
+    fn test_whitespace_does_not_change_the_hash() {
+        assert_eq!(hash(&["value"], "User"), hash(&["  value\n"], "User"));
+        assert_eq!(hash(&["val", "ue"], "User"), hash(&["value"], "User"));
+    }
+
+    #[test]
+    fn test_content_changes_the_hash() {
+        assert_ne!(hash(&["value"], "User"), hash(&["other"], "User"));
+        assert_ne!(hash(&["value"], "User"), hash(&["value"], "Path"));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::err::SerializationResult;
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::json_output::{JsonIndent, JsonOutput};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+use crate::ParserSettings;
+
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Writes many records as a single JSON array, reusing one `JsonOutput`.
+///
+/// Call `finish_record` after every record, and `into_writer` once all records were written
+/// to close the array.
+///
+/// ```rust
+/// # use evtx::{EvtxParser, JsonArrayOutput, ParserSettings};
+/// # use std::sync::Arc;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let settings = ParserSettings::new().indent(false);
+/// let mut parser = EvtxParser::from_path(fp).unwrap();
+/// let mut output = JsonArrayOutput::new(Vec::new(), &settings);
+///
+/// for chunk in parser.chunks() {
+///     let mut chunk = chunk.unwrap();
+///     for record in chunk.parse(Arc::new(settings.clone())).unwrap().iter() {
+///         record.unwrap().into_output(&mut output).unwrap();
+///         output.finish_record().unwrap();
+///     }
+/// }
+///
+/// let records: serde_json::Value = serde_json::from_slice(&output.into_writer().unwrap()).unwrap();
+/// assert!(records.is_array());
+/// ```
+pub struct JsonArrayOutput<W: Write> {
+    output: JsonOutput,
+    writer: W,
+    indent: JsonIndent,
+    records_written: usize,
+}
+
+impl<W: Write> JsonArrayOutput<W> {
+    pub fn new(target: W, settings: &ParserSettings) -> Self {
+        JsonArrayOutput {
+            output: JsonOutput::new(settings),
+            writer: target,
+            indent: settings.get_json_indent(),
+            records_written: 0,
+        }
+    }
+
+    /// Writes the current record as the next element of the array.
+    pub fn finish_record(&mut self) -> SerializationResult<()> {
+        let value = self.output.finish_record()?;
+
+        let compact = self.indent == JsonIndent::Compact;
+        let separator: &[u8] = match (self.records_written, compact) {
+            (0, true) => b"[",
+            (0, false) => b"[\n",
+            (_, true) => b",",
+            (_, false) => b",\n",
+        };
+        self.writer
+            .write_all(separator)
+            .map_err(serde_json::Error::io)?;
+
+        self.indent.to_writer(&mut self.writer, &value)?;
+
+        self.records_written += 1;
+
+        Ok(())
+    }
+
+    /// The number of records written so far.
+    pub fn records_written(&self) -> usize {
+        self.records_written
+    }
+
+    /// Closes the array, and returns the underlying writer.
+    pub fn into_writer(mut self) -> SerializationResult<W> {
+        let end: &[u8] = match (self.records_written, self.indent) {
+            (0, _) => b"[]",
+            (_, JsonIndent::Compact) => b"]",
+            (_, _) => b"\n]",
+        };
+        self.writer.write_all(end).map_err(serde_json::Error::io)?;
+        self.writer.flush().map_err(serde_json::Error::io)?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> BinXmlOutput for JsonArrayOutput<W> {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        self.output.visit_end_of_stream()
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.output.visit_open_start_element(element)
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.output.visit_close_element(element)
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.output.visit_characters(value)
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.output.visit_cdata_section(cdata)
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.output.visit_entity_reference(entity)
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.output.visit_character_reference(char_ref)
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.output.visit_processing_instruction(pi)
+    }
+
+    fn visit_comment(&mut self, comment: &str) -> SerializationResult<()> {
+        self.output.visit_comment(comment)
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        self.output.visit_start_of_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonArrayOutput;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::model::xml::test_helpers::element;
+    use crate::{BinXmlOutput, ParserSettings};
+    use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
+
+    fn write_record(output: &mut JsonArrayOutput<Vec<u8>>, event_id: u16) {
+        let event = element("EventID", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::UInt16Type(event_id)))
+            .unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+        output.finish_record().unwrap();
+    }
+
+    #[test]
+    fn test_json_array_output() {
+        let settings = ParserSettings::new().indent(false);
+
+        let output = JsonArrayOutput::new(vec![], &settings);
+        assert_eq!(output.into_writer().unwrap(), b"[]");
+
+        let mut output = JsonArrayOutput::new(vec![], &settings);
+        write_record(&mut output, 4624);
+        write_record(&mut output, 4625);
+        write_record(&mut output, 4634);
+        assert_eq!(output.records_written(), 3);
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            r#"[{"EventID":4624},{"EventID":4625},{"EventID":4634}]"#
+        );
+    }
+
+    #[test]
+    fn test_json_array_output_indented() {
+        let mut output = JsonArrayOutput::new(vec![], &ParserSettings::new());
+        write_record(&mut output, 4624);
+        write_record(&mut output, 4625);
+
+        let json = String::from_utf8(output.into_writer().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            "[\n{\n  \"EventID\": 4624\n},\n{\n  \"EventID\": 4625\n}\n]"
+        );
+    }
+
+    #[test]
+    fn test_unfinished_record_is_an_error() {
+        let mut output = JsonArrayOutput::new(vec![], &ParserSettings::new());
+        let event = element("Event", &[]);
+
+        output.visit_open_start_element(&event).unwrap();
+        assert!(output.finish_record().is_err());
+    }
+}
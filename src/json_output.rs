@@ -1,23 +1,221 @@
 use crate::err::{SerializationError, SerializationResult};
 
-use crate::binxml::value_variant::BinXmlValue;
-use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::binxml::value_variant::{BinXmlValue, BinaryEncoding};
+use crate::model::xml::{BinXmlPI, XmlAttribute, XmlElement};
 use crate::xml_output::BinXmlOutput;
 use crate::ParserSettings;
 
-use core::borrow::BorrowMut;
+use chrono::{DateTime, SecondsFormat, Utc};
 use log::trace;
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
 use serde_json::{json, Map, Value};
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
 
 use crate::binxml::name::BinXmlName;
-use crate::err::SerializationError::JsonStructureError;
-use quick_xml::events::BytesText;
+
+/// How elements without attributes or content are rendered in JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyElementStyle {
+    /// `<Correlation/>` -> `"Correlation": null`
+    #[default]
+    Null,
+    /// `<Correlation/>` -> `"Correlation": {}`
+    EmptyObject,
+    /// `<Correlation/>` -> `"Correlation": ""`
+    EmptyString,
+}
+
+/// What is done with the control characters (`U+0000` to `U+001F`, including `\t`, `\n` and `\r`)
+/// of strings rendered in JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlCharPolicy {
+    /// Kept, and escaped by JSON (`\u0001`, `\n`).
+    #[default]
+    Escape,
+    /// Removed from the string.
+    Strip,
+    /// Each control character is replaced with the given character.
+    Replace(char),
+}
+
+impl ControlCharPolicy {
+    /// Applies the policy to `text`, which is returned as is if it has no control characters.
+    pub fn apply_str(self, text: Cow<'_, str>) -> Cow<'_, str> {
+        let is_control = |c: char| c <= '\u{1f}';
+
+        match self {
+            ControlCharPolicy::Escape => text,
+            _ if !text.contains(is_control) => text,
+            ControlCharPolicy::Strip => Cow::Owned(text.replace(is_control, "")),
+            ControlCharPolicy::Replace(replacement) => Cow::Owned(
+                text.chars()
+                    .map(|c| if is_control(c) { replacement } else { c })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Applies the policy to string values, other values are returned as is.
+    fn apply<'v, 'a>(self, value: Cow<'v, BinXmlValue<'a>>) -> Cow<'v, BinXmlValue<'a>> {
+        if self == ControlCharPolicy::Escape {
+            return value;
+        }
+
+        let text = match value.as_ref() {
+            BinXmlValue::StringType(s) => s.as_str(),
+            BinXmlValue::AnsiStringType(s) => s.as_ref(),
+            _ => return value,
+        };
+
+        match self.apply_str(Cow::Borrowed(text)) {
+            Cow::Borrowed(_) => value,
+            Cow::Owned(text) => Cow::Owned(BinXmlValue::StringType(text)),
+        }
+    }
+}
+
+/// How records rendered as JSON are indented, when `indent` is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonIndent {
+    /// Each level is indented by this many spaces, `Spaces(2)` by default.
+    Spaces(usize),
+    /// Each level is indented by a tab.
+    Tabs,
+    /// The whole record is written on a single line, without any whitespace.
+    Compact,
+}
+
+impl Default for JsonIndent {
+    fn default() -> Self {
+        JsonIndent::Spaces(2)
+    }
+}
+
+impl JsonIndent {
+    /// Serializes `value` as JSON into `writer`, indented accordingly.
+    pub fn to_writer<W: Write, T: Serialize + ?Sized>(
+        self,
+        writer: W,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        let indent = match self {
+            JsonIndent::Compact => return serde_json::to_writer(writer, value),
+            JsonIndent::Spaces(width) => vec![b' '; width],
+            JsonIndent::Tabs => b"\t".to_vec(),
+        };
+
+        let formatter = PrettyFormatter::with_indent(&indent);
+        value.serialize(&mut serde_json::Serializer::with_formatter(
+            writer, formatter,
+        ))
+    }
+
+    /// Serializes `value` as a JSON string, indented accordingly.
+    pub fn to_string<T: Serialize + ?Sized>(self, value: &T) -> SerializationResult<String> {
+        let mut json = Vec::with_capacity(128);
+        self.to_writer(&mut json, value)?;
+
+        Ok(String::from_utf8(json)?)
+    }
+}
+
+/// The layout of records rendered as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonCompatibility {
+    /// The record as an `Event` object, mirroring its XML.
+    #[default]
+    Native,
+    /// A flat object with the snake case keys used by `winlogbeat` (under `winlog.*`):
+    ///
+    /// | Native                                        | WinEventLog           |
+    /// |-----------------------------------------------|-----------------------|
+    /// | `Event/System/Provider/@Name`                 | `provider_name`       |
+    /// | `Event/System/Provider/@Guid`                 | `provider_guid`       |
+    /// | `Event/System/EventID`                        | `event_id`            |
+    /// | `Event/System/Version`                        | `version`             |
+    /// | `Event/System/Level`                          | `level`               |
+    /// | `Event/System/Task`                           | `task`                |
+    /// | `Event/System/Opcode`                         | `opcode`              |
+    /// | `Event/System/Keywords`                       | `keywords`            |
+    /// | `Event/System/TimeCreated/@SystemTime`        | `time_created`        |
+    /// | `Event/System/EventRecordID`                  | `record_id`           |
+    /// | `Event/System/Correlation/@ActivityID`        | `activity_id`         |
+    /// | `Event/System/Correlation/@RelatedActivityID` | `related_activity_id` |
+    /// | `Event/System/Execution/@ProcessID`           | `process.pid`         |
+    /// | `Event/System/Execution/@ThreadID`            | `process.thread.id`   |
+    /// | `Event/System/Channel`                        | `channel`             |
+    /// | `Event/System/Computer`                       | `computer_name`       |
+    /// | `Event/System/Security/@UserID`               | `user.identifier`     |
+    /// | `Event/EventData`                             | `event_data`          |
+    /// | `Event/UserData`                              | `user_data`           |
+    ///
+    /// Values keep their JSON type, missing or empty fields are omitted,
+    /// and other elements (like `RenderingInfo`) are dropped.
+    WinEventLog,
+}
+
+/// Key under which a `Data` node is built, until it is closed and merged into its parent.
+const PENDING_DATA_KEY: &str = "#pending_data";
+/// Key under which unnamed `Data` nodes are collected.
+const UNNAMED_DATA_KEY: &str = "Data";
 
 pub struct JsonOutput {
     map: Value,
-    stack: Vec<String>,
+    /// The names of the open nodes, interned in `names`.
+    stack: Vec<Arc<str>>,
+    /// Every node name seen so far, so that repeated names (`System`, `EventData`, `Data`, ...)
+    /// are only allocated once, even across records when the output is reused.
+    names: HashSet<Arc<str>>,
+    /// The values of every open node but the innermost one, which stays in its parent.
+    ///
+    /// Each node is detached from its parent (leaving a placeholder) when a child opens,
+    /// and put back when the child closes, so that the current node and its parent
+    /// are always one lookup away instead of a walk from the root.
+    frames: Vec<Value>,
+    /// Whether each open node already received text, so that consecutive text is appended
+    /// instead of being treated as the text of a repeated node.
+    has_text: Vec<bool>,
+    /// Open `Data` nodes, as the depth of their pending slot and their `Name` attribute.
+    data_nodes: Vec<(usize, Option<String>)>,
     separate_json_attributes: bool,
+    flatten_json_attributes: bool,
+    normalize_timestamps: bool,
+    strip_namespaces: bool,
+    binary_byte_limit: Option<usize>,
+    binary_encoding: BinaryEncoding,
+    empty_element_style: EmptyElementStyle,
+    control_chars: ControlCharPolicy,
+    compatibility: JsonCompatibility,
+    /// The element names leading to the only subtree which is rendered, if set.
+    root_path: Option<Vec<String>>,
+    /// How many elements deep we are inside of a subtree which is not rendered.
+    skipped_depth: usize,
+    canonicalize: bool,
+    /// If true, structural names (`Data`, `EventData`, `UserData` and `Name`) ignore ASCII case.
+    ascii_case_insensitive: bool,
+    /// If true, records which don't fit the usual shape of an event fail instead of being adapted.
+    strict: bool,
+    /// If true, whitespace-only text is skipped, see `ParserSettings::trim_whitespace`.
+    trim_whitespace: bool,
+    /// If true, `LevelName` and `OpcodeName` are added next to `System/Level` and `System/Opcode`.
+    symbolic_system_names: bool,
+    /// If true, `System/Keywords` is padded to 16 hex digits, see `ParserSettings::hex_keywords`.
+    hex_keywords: bool,
+    /// Attributes whose textual values are written as numbers, see `numeric_json_attributes`.
+    numeric_attributes: Option<HashSet<String>>,
+    max_depth: Option<usize>,
+    max_output_bytes: Option<usize>,
+    /// How many bytes of names, attributes and text were added to the current record.
+    output_bytes: usize,
+    attribute_key: String,
+    text_key: String,
+    /// How many elements were closed in the current record, reported on unbalanced streams.
+    closed_elements: usize,
+    eof_reached: bool,
 }
 
 impl JsonOutput {
@@ -25,98 +223,324 @@ impl JsonOutput {
         JsonOutput {
             map: Value::Object(Map::new()),
             stack: vec![],
-            separate_json_attributes: settings.should_separate_json_attributes(),
+            names: HashSet::new(),
+            frames: vec![],
+            has_text: vec![],
+            data_nodes: vec![],
+            // Flattened attributes are also kept apart from the text of their element.
+            separate_json_attributes: settings.should_separate_json_attributes()
+                || settings.should_flatten_json_attributes(),
+            flatten_json_attributes: settings.should_flatten_json_attributes(),
+            normalize_timestamps: settings.should_normalize_timestamps(),
+            strip_namespaces: settings.should_strip_namespaces(),
+            binary_byte_limit: settings.get_binary_byte_limit(),
+            binary_encoding: settings.get_binary_encoding(),
+            empty_element_style: settings.get_empty_element_style(),
+            control_chars: settings.get_control_char_policy(),
+            compatibility: settings.get_json_compatibility(),
+            root_path: settings.get_json_root_path().map(<[String]>::to_vec),
+            skipped_depth: 0,
+            canonicalize: settings.should_canonicalize_json(),
+            ascii_case_insensitive: settings.should_ignore_ascii_case(),
+            strict: settings.should_use_strict_json(),
+            trim_whitespace: settings.should_trim_whitespace(),
+            symbolic_system_names: settings.should_add_symbolic_system_names(),
+            hex_keywords: settings.should_write_hex_keywords(),
+            numeric_attributes: settings.get_numeric_json_attributes().cloned(),
+            max_depth: settings.get_max_depth(),
+            max_output_bytes: settings.get_max_output_bytes(),
+            output_bytes: 0,
+            attribute_key: settings.get_attribute_key().to_owned(),
+            text_key: settings.get_text_key().to_owned(),
+            closed_elements: 0,
+            eof_reached: false,
         }
     }
 
-    /// Looks up the current path, will fill with empty objects if needed.
-    fn get_or_create_current_path(&mut self) -> &mut Value {
-        let mut v_temp = self.map.borrow_mut();
-
-        for key in self.stack.iter() {
-            // Current path does not exist yet, we need to create it.
-            if v_temp.get(key).is_none() {
-                // Can happen if we have
-                // <Event>
-                //    <System>
-                //       <...>
-                // since system has no attributes it has null and not an empty map.
-                if v_temp.is_null() {
-                    let mut map = Map::new();
-                    map.insert(key.clone(), Value::Object(Map::new()));
-
-                    *v_temp = Value::Object(map);
-                } else if !v_temp.is_object() {
-                    // This branch could only happen while `separate-json-attributes` was on,
-                    // and a very non-standard xml structure is going on (character nodes between XML nodes)
-                    //
-                    // Example:
-                    // ```
-                    //  <URLCacheFlushInfo></URLCacheFlushInfo>&amp;quot&amp;<URLCacheResponseInfo></URLCacheResponseInfo>
-                    // ```
-                    // We shift the characters in to be consistent with regular json parser.
-                    // The resulting JSON looks like:
-                    // ```
-                    // ...
-                    //  "URLCacheResponseInfo": "\"",
-                    //  "URLCacheResponseInfo_attributes": {
-                    //      ...
-                    //   }
-                    // ...
-                    // ```
-                    let mut map = Map::new();
-                    map.insert(key.clone(), v_temp.clone());
-
-                    *v_temp = Value::Object(map);
-                } else {
-                    let current_object = v_temp
-                        .as_object_mut()
-                        .expect("!v_temp.is_object was matched above.");
+    /// The value of an empty element, `None` if it should stay `null`.
+    fn empty_element_value(&self) -> Option<Value> {
+        match self.empty_element_style {
+            EmptyElementStyle::Null => None,
+            EmptyElementStyle::EmptyObject => Some(Value::Object(Map::new())),
+            EmptyElementStyle::EmptyString => Some(json!("")),
+        }
+    }
 
-                    current_object.insert(key.clone(), Value::Object(Map::new()));
-                }
+    /// Adds `len` bytes to the size of the current record, failing if it exceeds `max_output_bytes`.
+    fn count_output(&mut self, len: usize) -> SerializationResult<()> {
+        if let Some(max_output_bytes) = self.max_output_bytes {
+            self.output_bytes += len;
+            if self.output_bytes > max_output_bytes {
+                return Err(SerializationError::OutputTooLarge { max_output_bytes });
             }
+        }
+
+        Ok(())
+    }
+
+    /// Whether content of the current node is discarded, because it is not part of `root_path`.
+    fn is_outside_root(&self) -> bool {
+        self.skipped_depth > 0 || self.stack.len() < self.root_path.as_ref().map_or(0, Vec::len)
+    }
 
-            v_temp = v_temp.get_mut(key).expect("Loop above inserted this node.")
+    /// Whether `name` is the structural name `expected`, see `ascii_case_insensitive`.
+    fn is_structural_name(&self, name: &str, expected: &str) -> bool {
+        if self.ascii_case_insensitive {
+            name.eq_ignore_ascii_case(expected)
+        } else {
+            name == expected
         }
+    }
 
-        v_temp
+    /// Whether the current node is inside of `Event/UserData`.
+    fn is_in_user_data(&self) -> bool {
+        matches!(self.stack.get(1), Some(name) if self.is_structural_name(name, "UserData"))
     }
 
-    fn get_current_parent(&mut self) -> &mut Value {
-        // Make sure we are operating on created nodes.
-        self.get_or_create_current_path();
+    /// Looks up the current path, without creating it.
+    fn get_current_value(&mut self) -> Option<&mut Value> {
+        match self.stack.last() {
+            Some(key) => self
+                .frames
+                .last_mut()
+                .unwrap_or(&mut self.map)
+                .get_mut(key.as_ref()),
+            None => Some(&mut self.map),
+        }
+    }
+
+    /// Whether `value` is whitespace which does not follow other text of the current element.
+    fn is_whitespace_only(&self, value: &BinXmlValue) -> bool {
+        let text = match value {
+            BinXmlValue::StringType(s) => s.as_str(),
+            BinXmlValue::AnsiStringType(s) => s.as_ref(),
+            _ => return false,
+        };
+
+        !self.has_text.last().copied().unwrap_or(false) && text.trim().is_empty()
+    }
+
+    fn attribute_to_json(&self, attribute: &XmlAttribute) -> SerializationResult<Value> {
+        if self.normalize_timestamps && attribute.name.as_str() == "SystemTime" {
+            return normalize_timestamp(&attribute.value);
+        }
+
+        let value = BinXmlValue::encode_binary(
+            Cow::Borrowed(attribute.value.as_ref()),
+            self.binary_encoding,
+            self.binary_byte_limit,
+        );
+        let value: Value = self.control_chars.apply(value).into_owned().into();
+
+        match (&self.numeric_attributes, &value) {
+            (Some(names), Value::String(text)) if names.contains(attribute.name.as_str()) => {
+                Ok(parse_integer(text).unwrap_or(value))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Looks up the current path, will fill with empty objects if needed.
+    fn get_or_create_current_path(&mut self) -> SerializationResult<&mut Value> {
+        match self.stack.last() {
+            Some(key) => get_or_create_child(self.frames.last_mut().unwrap_or(&mut self.map), key),
+            None => Ok(&mut self.map),
+        }
+    }
+
+    fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(interned) = self.names.get(name) {
+            return Arc::clone(interned);
+        }
+
+        let interned: Arc<str> = Arc::from(name);
+        self.names.insert(Arc::clone(&interned));
+        interned
+    }
+
+    /// Opens a child of the current node, detaching the current node from its parent.
+    ///
+    /// Nodes with mixed content keep their text under the text key, next to their children:
+    /// ```text
+    /// <EventData>a<Foo>1</Foo>b</EventData> -> {"EventData": {"#text": ["a", "b"], "Foo": 1}}
+    /// ```
+    fn push_node(&mut self, name: &str) -> SerializationResult<()> {
+        let name = self.intern(name);
+
+        if matches!(
+            self.get_current_value(),
+            Some(Value::String(_) | Value::Number(_) | Value::Bool(_))
+        ) {
+            self.get_or_create_current_object()?;
+        }
+        // Text after this child is a new segment, and is not concatenated with the text before it.
+        if let Some(has_text) = self.has_text.last_mut() {
+            *has_text = false;
+        }
+
+        if let Some(key) = self.stack.last() {
+            let parent = self.frames.last_mut().unwrap_or(&mut self.map);
+            let current_value = get_or_create_child(parent, key)?.take();
+            self.frames.push(current_value);
+        }
+
+        self.stack.push(name);
+        self.has_text.push(false);
+
+        Ok(())
+    }
+
+    /// Closes the current node, putting its parent back into the grandparent.
+    fn pop_node(&mut self) -> SerializationResult<Option<Arc<str>>> {
+        let name = self.stack.pop();
+        self.has_text.pop();
+
+        if let Some(key) = self.stack.last() {
+            let current_value = self.frames.pop().ok_or_else(|| {
+                invariant_violation("every open node but the innermost one has a frame")
+            })?;
+            let parent = self
+                .frames
+                .last_mut()
+                .unwrap_or(&mut self.map)
+                .as_object_mut()
+                .ok_or_else(|| {
+                    invariant_violation("a node with an open child is always an object")
+                })?;
+
+            // The placeholder is still in place, so we can avoid allocating the key again.
+            match parent.get_mut(key.as_ref()) {
+                Some(placeholder) => *placeholder = current_value,
+                None => {
+                    parent.insert(key.to_string(), current_value);
+                }
+            }
+        }
+
+        Ok(name)
+    }
 
-        let mut v_temp = self.map.borrow_mut();
+    /// Like `get_or_create_current_path`, but makes sure the current node is an object,
+    /// moving any existing text of the node to the text key (`#text`).
+    fn get_or_create_current_object(&mut self) -> SerializationResult<&mut Map<String, Value>> {
+        let text_key = self.text_key.clone();
+        let current_value = self.get_or_create_current_path()?;
 
-        for key in self.stack.iter().take(self.stack.len() - 1) {
-            v_temp = v_temp
-                .get_mut(key)
-                .expect("Calling `get_or_create_current_path` ensures that the node was created")
+        match current_value {
+            Value::Null => *current_value = Value::Object(Map::new()),
+            Value::Object(_) => {}
+            Value::Array(_) => {
+                return Err(SerializationError::JsonStructureError {
+                    message: format!(
+                        "expected current value to be an Object or a scalar, found {:?}",
+                        current_value
+                    ),
+                })
+            }
+            _ => {
+                let text = current_value.take();
+                let mut object = Map::new();
+                object.insert(text_key, text);
+                *current_value = Value::Object(object);
+            }
         }
 
-        v_temp
+        current_value
+            .as_object_mut()
+            .ok_or_else(|| invariant_violation("current value was converted to an object above"))
+    }
+
+    fn get_current_parent(&mut self) -> SerializationResult<&mut Value> {
+        // Make sure we are operating on created nodes.
+        self.get_or_create_current_path()?;
+
+        Ok(self.frames.last_mut().unwrap_or(&mut self.map))
     }
 
     /// Like a regular node, but uses it's "Name" attribute.
+    ///
+    /// The node is built in a pending slot, and merged into its parent when closed,
+    /// so that repeated names accumulate into an array instead of overwriting each other:
+    /// ```text
+    /// <Data Name="Path">a</Data><Data Name="Path">b</Data> -> {"Path": ["a", "b"]}
+    /// <Data>a</Data><Data>b</Data>                         -> {"Data": ["a", "b"]}
+    /// ```
     fn insert_data_node(&mut self, element: &XmlElement) -> SerializationResult<()> {
         trace!("inserting data node {:?}", &element);
-        match element
+        let data_name = element
             .attributes
             .iter()
-            .find(|a| a.name.as_ref().as_str() == "Name")
-        {
-            Some(name) => {
-                let data_key: Cow<'_, str> = name.value.as_ref().as_cow_str();
+            .find(|attribute| self.is_structural_name(attribute.name.as_str(), "Name"))
+            .map(|attribute| attribute.value.as_cow_str().into_owned());
+
+        if self.strict && data_name.is_none() {
+            return Err(SerializationError::UnnamedDataNode);
+        }
+
+        self.push_node(PENDING_DATA_KEY)?;
+        self.data_nodes.push((self.stack.len(), data_name));
+
+        let container = self.get_current_parent()?.as_object_mut().ok_or_else(|| {
+            SerializationError::JsonStructureError {
+                message: "This is a bug - expected parent container of a `Data` node to be an object type."
+                    .to_string(),
+            }
+        })?;
+
+        container.insert(PENDING_DATA_KEY.to_owned(), Value::Null);
+
+        Ok(())
+    }
 
-                self.insert_node_without_attributes(element, &data_key)
+    /// Moves the value of the `Data` node which is being closed from its pending slot to its key.
+    fn close_data_node(&mut self) -> SerializationResult<()> {
+        let (_, data_name) = self
+            .data_nodes
+            .pop()
+            .ok_or_else(|| invariant_violation("only called when a data node is open"))?;
+        let empty_element_value = self.empty_element_value();
+
+        let container = self.get_current_parent()?.as_object_mut().ok_or_else(|| {
+            SerializationError::JsonStructureError {
+                message: "This is a bug - expected parent container of a `Data` node to be an object type."
+                    .to_string(),
             }
-            // Ignore this node
-            None => {
-                self.stack.push("Data".to_owned());
-                Ok(())
+        })?;
+
+        let mut value = container.remove(PENDING_DATA_KEY).unwrap_or(Value::Null);
+        if value.is_null() {
+            if let Some(empty) = empty_element_value {
+                value = empty;
             }
         }
+
+        match data_name {
+            Some(data_name) => match container.get_mut(&data_name) {
+                None => {
+                    container.insert(data_name, value);
+                }
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array(vec![first, value]);
+                }
+            },
+            // Unnamed data nodes are positional, so we always keep them in an array.
+            None => match container.get_mut(UNNAMED_DATA_KEY) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array(vec![first, value]);
+                }
+                None => {
+                    container.insert(UNNAMED_DATA_KEY.to_owned(), Value::Array(vec![value]));
+                }
+            },
+        }
+
+        self.pop_node()?;
+
+        Ok(())
     }
 
     fn insert_node_without_attributes(
@@ -125,9 +549,10 @@ impl JsonOutput {
         name: &str,
     ) -> SerializationResult<()> {
         trace!("insert_node_without_attributes");
-        self.stack.push(name.to_owned());
+        self.push_node(name)?;
+        let strict = self.strict;
 
-        let container = self.get_current_parent().as_object_mut().ok_or_else(|| {
+        let container = self.get_current_parent()?.as_object_mut().ok_or_else(|| {
             SerializationError::JsonStructureError {
                 message:
                     "This is a bug - expected parent container to exist, and to be an object type.\
@@ -166,6 +591,12 @@ impl JsonOutput {
                 }
             }
 
+            if strict {
+                return Err(SerializationError::DuplicateElement {
+                    name: name.to_owned(),
+                });
+            }
+
             let mut free_slot = 1;
 
             // If it is a concrete value, we look for another slot.
@@ -180,33 +611,74 @@ impl JsonOutput {
         Ok(())
     }
 
+    /// The key of `attribute`, suffixed with `_2`, `_3`... when an attribute with the same name
+    /// was already inserted, so that it does not overwrite it.
+    /// Empty names (which only come from corrupted records) are written as `_`.
+    fn attribute_name(
+        &self,
+        attributes: &Map<String, Value>,
+        element_name: &str,
+        attribute: &XmlAttribute,
+    ) -> SerializationResult<String> {
+        let name = attribute.name.as_str();
+
+        if name.is_empty() || attributes.contains_key(name) {
+            if self.strict {
+                return Err(SerializationError::DuplicateAttribute {
+                    element: element_name.to_owned(),
+                    name: name.to_owned(),
+                });
+            }
+
+            let name = if name.is_empty() { "_" } else { name };
+            if !attributes.contains_key(name) {
+                return Ok(name.to_owned());
+            }
+
+            let mut free_slot = 2;
+            while attributes.contains_key(&format!("{}_{}", name, free_slot)) {
+                free_slot += 1;
+            }
+
+            return Ok(format!("{}_{}", name, free_slot));
+        }
+
+        Ok(name.to_owned())
+    }
+
     fn insert_node_with_attributes(
         &mut self,
         element: &XmlElement,
         name: &str,
     ) -> SerializationResult<()> {
         trace!("insert_node_with_attributes");
-        self.stack.push(name.to_owned());
+        self.push_node(name)?;
 
         let mut attributes = Map::new();
 
         for attribute in element.attributes.iter() {
-            let value = attribute.value.clone().into_owned();
-            let value: Value = value.into();
+            if self.strip_namespaces && is_namespace_declaration(attribute.name.as_str()) {
+                continue;
+            }
+
+            let value = self.attribute_to_json(attribute)?;
 
             if !value.is_null() {
-                let name: &str = attribute.name.as_str();
-                attributes.insert(name.to_owned(), value);
+                let attribute_name = self.attribute_name(&attributes, name, attribute)?;
+                attributes.insert(attribute_name, value);
             }
         }
 
+        let attribute_key = self.attribute_key.clone();
+        let flatten_json_attributes = self.flatten_json_attributes;
+
         // If we have attributes, create a map as usual.
         if !attributes.is_empty() {
             if self.separate_json_attributes {
                 // If we are separating the attributes we want
                 // to insert the object for the attributes
                 // into the parent.
-                let value = self.get_current_parent().as_object_mut().ok_or_else(|| {
+                let value = self.get_current_parent()?.as_object_mut().ok_or_else(|| {
                     SerializationError::JsonStructureError {
                     message:
                         "This is a bug - expected current value to exist, and to be an object type.
@@ -215,16 +687,23 @@ impl JsonOutput {
                 }
                 })?;
 
-                value.insert(format!("{}_attributes", name), Value::Object(attributes));
-
                 // If the element's main value is empty, we want to remove it because we
                 // do not want the value to represent an empty object.
+                // This is done first, since removing a key moves the last key in its place.
                 if value[name] == Value::Object(Map::new()) {
                     value.remove(name);
                 }
+
+                if flatten_json_attributes {
+                    for (attribute, attribute_value) in attributes {
+                        value.insert(format!("{}_{}", name, attribute), attribute_value);
+                    }
+                } else {
+                    value.insert(format!("{}_attributes", name), Value::Object(attributes));
+                }
             } else {
                 let value = self
-                    .get_or_create_current_path()
+                    .get_or_create_current_path()?
                     .as_object_mut()
                     .ok_or_else(|| {
                         SerializationError::JsonStructureError {
@@ -235,13 +714,13 @@ impl JsonOutput {
                 }
                     })?;
 
-                value.insert("#attributes".to_owned(), Value::Object(attributes));
+                value.insert(attribute_key, Value::Object(attributes));
             }
         } else {
             // If the object does not have attributes, replace it with a null placeholder,
             // so it will be printed as a key-value pair
             let value =
-                self.get_current_parent()
+                self.get_current_parent()?
                     .as_object_mut()
                     .ok_or(SerializationError::JsonStructureError {
                     message:
@@ -256,191 +735,775 @@ impl JsonOutput {
         Ok(())
     }
 
-    pub fn into_value(self) -> SerializationResult<Value> {
+    pub fn into_value(mut self) -> SerializationResult<Value> {
+        self.finish_record()
+    }
+
+    /// Takes the value of the current record, leaving the output empty and ready for the next one.
+    ///
+    /// Fails with `IncompleteOutput` if the end of the stream was not visited (a truncated record),
+    /// and with `UnexpectedEof` if it was visited while elements were still open (a broken record).
+    pub fn finish_record(&mut self) -> SerializationResult<Value> {
+        if !self.eof_reached {
+            return Err(SerializationError::IncompleteOutput);
+        }
+
         if !self.stack.is_empty() {
-            return Err(SerializationError::JsonStructureError {
-                message: "Invalid stream, EOF reached before closing all attributes".to_string(),
+            return Err(SerializationError::UnexpectedEof {
+                open_elements: self.stack.len(),
             });
         }
 
-        Ok(self.map)
-    }
-}
-
-impl BinXmlOutput for JsonOutput {
-    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
-        trace!("visit_end_of_stream");
-        Ok(())
-    }
+        self.clear_record_state();
 
-    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
-        trace!("visit_open_start_element: {:?}", element.name);
-        let element_name = element.name.as_str();
+        let mut value = std::mem::replace(&mut self.map, Value::Object(Map::new()));
 
-        if element_name == "Data" {
-            return self.insert_data_node(element);
+        if self.canonicalize {
+            let ascii_case_insensitive = self.ascii_case_insensitive;
+            if let Some(event) = get_structural_child(&mut value, "Event", ascii_case_insensitive) {
+                if let Some(event_data) =
+                    get_structural_child(event, "EventData", ascii_case_insensitive)
+                {
+                    sort_keys(event_data);
+                }
+                if let Some(Value::Object(user_data)) =
+                    get_structural_child(event, "UserData", ascii_case_insensitive)
+                {
+                    user_data.values_mut().for_each(sort_keys);
+                }
+            }
         }
 
-        // <Task>12288</Task> -> {"Task": 12288}
-        if element.attributes.is_empty() {
-            return self.insert_node_without_attributes(element, element_name);
+        if self.symbolic_system_names || self.hex_keywords {
+            let ascii_case_insensitive = self.ascii_case_insensitive;
+            if let Some(Value::Object(system)) =
+                get_structural_child(&mut value, "Event", ascii_case_insensitive)
+                    .and_then(|event| get_structural_child(event, "System", ascii_case_insensitive))
+            {
+                if self.symbolic_system_names {
+                    add_symbolic_name(system, "Level", &self.text_key, level_name);
+                    add_symbolic_name(system, "Opcode", &self.text_key, opcode_name);
+                }
+                if self.hex_keywords {
+                    pad_keywords(system, &self.text_key);
+                }
+            }
         }
 
-        self.insert_node_with_attributes(element, element_name)
-    }
+        if let Some(root_path) = &self.root_path {
+            return Ok(root_path
+                .iter()
+                .try_fold(&mut value, |value, name| value.get_mut(name))
+                .map_or(Value::Null, Value::take));
+        }
 
-    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
-        let p = self.stack.pop();
-        trace!("visit_close_element: {:?}", p);
-        Ok(())
+        Ok(match self.compatibility {
+            JsonCompatibility::Native => value,
+            JsonCompatibility::WinEventLog => self.to_win_event_log(value),
+        })
     }
 
-    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
-        trace!("visit_chars {:?}", &self.stack);
-        // We need to clone this bool since the next statement will borrow self as mutable.
-        let separate_json_attributes = self.separate_json_attributes;
-        let current_value = self.get_or_create_current_path();
-
-        // A small optimization in case we already have an owned string.
-        fn value_to_json(value: Cow<BinXmlValue>) -> Value {
-            if let Cow::Owned(BinXmlValue::StringType(value)) = value {
-                json!(value)
-            } else {
-                value.into_owned().into()
-            }
+    /// Drops the current record, leaving the output empty and ready for the next one,
+    /// while keeping the memory it allocated (and the interned names).
+    ///
+    /// The output is reset either way, but fails with `IncompleteOutput` if it was called
+    /// mid-record (while elements were still open), to report that a record was discarded.
+    pub fn reset(&mut self) -> SerializationResult<()> {
+        let open_elements = self.stack.len() + self.skipped_depth;
+
+        self.stack.clear();
+        self.clear_record_state();
+        match &mut self.map {
+            Value::Object(map) => map.clear(),
+            map => *map = Value::Object(Map::new()),
         }
 
-        // If our parent is an element without any attributes,
-        // we simply swap the null with the string value.
-        // This is also true for the case when the attributes were inserted as our siblings.
-        match current_value {
-            // Regular, distinct node.
-            Value::Null => {
-                *current_value = value_to_json(value);
-            }
-            Value::Object(object) => {
-                if separate_json_attributes {
-                    if object.is_empty() {
-                        *current_value = value_to_json(value);
-                    } else {
-                        // TODO: Currently we discard some of the data in this case. What should we do?
-                    }
-                } else {
-                    // Otherwise,
-                    // Should look like:
-                    // ----------------
-                    //  "EventID": {
-                    //    "#attributes": {
-                    //      "Qualifiers": ""
-                    //    },
-                    //    "#text": "4902"
-                    //  },
-                    //
-                    // If multiple nodes with the same name exists, we convert the `#text` attribute into an array.
-                    const TEXT_KEY: &str = "#text";
-                    match object.get_mut(TEXT_KEY) {
-                        // Regular, distinct node.
-                        None | Some(Value::Null) => {
-                            object.insert(TEXT_KEY.to_owned(), value_to_json(value));
-                        }
-                        // The first time we encounter another node with the same name,
-                        // we convert the exiting value into an array with both values.
-                        Some(Value::String(perv_value)) => {
-                            let perv_value = perv_value.clone();
-                            object.remove(TEXT_KEY);
-                            object.insert(
-                                TEXT_KEY.to_owned(),
-                                json!([perv_value, value_to_json(value)]),
-                            );
-                        }
-                        // If we already have an array, we can just push into it.
-                        Some(Value::Array(arr)) => arr.push(value_to_json(value)),
-                        current_value => {
-                            return Err(SerializationError::JsonStructureError {
-                            message: format!(
-                                "expected current value to be a String or an Array, found {:?}, new value is {:?}",
-                                current_value, value
-                            ),
-                        });
-                        }
-                    }
-                }
-            }
-            // The first time we encounter another node with the same name,
-            // we convert the exiting value into an array with both values.
-            Value::String(current_string) => {
-                current_string.push_str(&value.as_cow_str());
-            }
-            // If we already have an array, we can just push into it.
-            Value::Array(arr) => arr.push(value_to_json(value)),
-            current_value => {
-                return Err(SerializationError::JsonStructureError {
-                    message: format!(
-                        "expected current value to be a String or an Array, found {:?}, new value is {:?}",
-                        current_value, value
-                    ),
-                });
-            }
+        if open_elements > 0 {
+            return Err(SerializationError::IncompleteOutput);
         }
 
         Ok(())
     }
 
-    fn visit_cdata_section(&mut self) -> SerializationResult<()> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_cdata_section", file!()),
-        })
+    /// Clears everything but the value and the stack of open nodes, between records.
+    fn clear_record_state(&mut self) {
+        self.frames.clear();
+        self.has_text.clear();
+        self.skipped_depth = 0;
+        self.data_nodes.clear();
+        self.closed_elements = 0;
+        self.output_bytes = 0;
+        self.eof_reached = false;
     }
 
-    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> Result<(), SerializationError> {
-        // We need to create a BytesText event to access quick-xml's unescape functionality (which is private).
-        // We also terminate the entity.
-        let entity_ref = "&".to_string() + entity.as_str() + ";";
-
-        let xml_event = BytesText::from_escaped(&entity_ref);
-        match xml_event.unescape() {
-            Ok(escaped) => {
-                let as_string = escaped.to_string();
+    /// Moves the fields of a record to the `JsonCompatibility::WinEventLog` layout.
+    fn to_win_event_log(&self, mut value: Value) -> Value {
+        let mut event = value
+            .get_mut("Event")
+            .map(Value::take)
+            .unwrap_or(Value::Null);
+        let system = event.get("System").unwrap_or(&Value::Null);
+
+        // The text of an element, which is stored under the text key if it also has attributes.
+        let text = |element: &str| match system.get(element) {
+            Some(Value::Object(object)) => object.get(&self.text_key).cloned(),
+            value => value.cloned(),
+        };
+        let attribute = |element: &str, name: &str| {
+            system
+                .get(element)
+                .and_then(|element| element.get(&self.attribute_key))
+                .or_else(|| system.get(format!("{}_attributes", element)))
+                .and_then(|attributes| attributes.get(name))
+                .or_else(|| system.get(format!("{}_{}", element, name)))
+                .cloned()
+        };
 
-                self.visit_characters(Cow::Owned(BinXmlValue::StringType(as_string)))?;
-                Ok(())
-            }
-            Err(_) => Err(JsonStructureError {
-                message: format!("Unterminated XML Entity {}", entity_ref),
-            }),
+        let mut process = Map::new();
+        insert_present(&mut process, "pid", attribute("Execution", "ProcessID"));
+        if let Some(thread_id) = attribute("Execution", "ThreadID").filter(is_present) {
+            process.insert("thread".to_owned(), json!({ "id": thread_id }));
         }
+
+        let mut user = Map::new();
+        insert_present(&mut user, "identifier", attribute("Security", "UserID"));
+
+        let mut record = Map::new();
+        insert_present(&mut record, "provider_name", attribute("Provider", "Name"));
+        insert_present(&mut record, "provider_guid", attribute("Provider", "Guid"));
+        insert_present(&mut record, "event_id", text("EventID"));
+        insert_present(&mut record, "version", text("Version"));
+        insert_present(&mut record, "level", text("Level"));
+        insert_present(&mut record, "task", text("Task"));
+        insert_present(&mut record, "opcode", text("Opcode"));
+        insert_present(&mut record, "keywords", text("Keywords"));
+        insert_present(
+            &mut record,
+            "time_created",
+            attribute("TimeCreated", "SystemTime"),
+        );
+        insert_present(&mut record, "record_id", text("EventRecordID"));
+        insert_present(
+            &mut record,
+            "activity_id",
+            attribute("Correlation", "ActivityID"),
+        );
+        insert_present(
+            &mut record,
+            "related_activity_id",
+            attribute("Correlation", "RelatedActivityID"),
+        );
+        insert_present(&mut record, "process", Some(Value::Object(process)));
+        insert_present(&mut record, "channel", text("Channel"));
+        insert_present(&mut record, "computer_name", text("Computer"));
+        insert_present(&mut record, "user", Some(Value::Object(user)));
+        insert_present(
+            &mut record,
+            "event_data",
+            event.get_mut("EventData").map(Value::take),
+        );
+        insert_present(
+            &mut record,
+            "user_data",
+            event.get_mut("UserData").map(Value::take),
+        );
+
+        Value::Object(record)
     }
+}
 
-    fn visit_character_reference(
-        &mut self,
-        _char_ref: Cow<'_, str>,
-    ) -> Result<(), SerializationError> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_character_reference", file!()),
-        })
+/// Whether a value carries any data, empty strings and objects do not.
+fn is_present(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Object(object) => !object.is_empty(),
+        _ => true,
     }
+}
 
-    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> Result<(), SerializationError> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_processing_instruction_data", file!()),
-        })
+fn insert_present(object: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if let Some(value) = value.filter(is_present) {
+        object.insert(key.to_owned(), value);
     }
+}
 
-    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
-        trace!("visit_start_of_stream");
-        Ok(())
+/// Sorts the keys of `value` lexicographically, if it is an object.
+fn sort_keys(value: &mut Value) {
+    if let Value::Object(object) = value {
+        let mut entries: Vec<(String, Value)> = std::mem::take(object).into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        *object = entries.into_iter().collect();
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::binxml::name::BinXmlName;
-    use crate::binxml::value_variant::BinXmlValue;
-    use crate::model::xml::{XmlAttribute, XmlElement};
-    use crate::{BinXmlOutput, JsonOutput, ParserSettings};
+/// The standard name of an event level, as defined in `winmeta.xml`.
+fn level_name(level: u64) -> Option<&'static str> {
+    Some(match level {
+        0 => "LogAlways",
+        1 => "Critical",
+        2 => "Error",
+        3 => "Warning",
+        4 => "Information",
+        5 => "Verbose",
+        _ => return None,
+    })
+}
+
+/// The standard name of an event opcode, as defined in `winmeta.xml`.
+fn opcode_name(opcode: u64) -> Option<&'static str> {
+    Some(match opcode {
+        0 => "Info",
+        1 => "Start",
+        2 => "Stop",
+        3 => "DCStart",
+        4 => "DCStop",
+        5 => "Extension",
+        6 => "Reply",
+        7 => "Resume",
+        8 => "Suspend",
+        9 => "Send",
+        240 => "Receive",
+        _ => return None,
+    })
+}
+
+/// Adds `{key}Name` next to `key`, if its value (or its text) has a standard name.
+fn add_symbolic_name(
+    system: &mut Map<String, Value>,
+    key: &str,
+    text_key: &str,
+    name_of: fn(u64) -> Option<&'static str>,
+) {
+    let value = match system.get(key) {
+        Some(Value::Object(object)) => object.get(text_key),
+        value => value,
+    };
+    let number = match value {
+        Some(Value::Number(number)) => number.as_u64(),
+        Some(Value::String(text)) => text.trim().parse().ok(),
+        _ => None,
+    };
+
+    if let Some(name) = number.and_then(name_of) {
+        system.insert(format!("{}Name", key), json!(name));
+    }
+}
+
+/// Looks up the child of an object by a structural name, see `ascii_case_insensitive`.
+fn get_structural_child<'a>(
+    value: &'a mut Value,
+    name: &str,
+    ascii_case_insensitive: bool,
+) -> Option<&'a mut Value> {
+    let object = value.as_object_mut()?;
+
+    if ascii_case_insensitive {
+        object
+            .iter_mut()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, child)| child)
+    } else {
+        object.get_mut(name)
+    }
+}
+
+/// Looks up a child of `v_temp`, filling it with an empty object if needed.
+fn get_or_create_child<'a>(v_temp: &'a mut Value, key: &str) -> SerializationResult<&'a mut Value> {
+    // Current path does not exist yet, we need to create it.
+    if v_temp.get(key).is_none() {
+        // Can happen if we have
+        // <Event>
+        //    <System>
+        //       <...>
+        // since system has no attributes it has null and not an empty map.
+        if v_temp.is_null() {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), Value::Object(Map::new()));
+
+            *v_temp = Value::Object(map);
+        } else if !v_temp.is_object() {
+            // This branch could only happen while `separate-json-attributes` was on,
+            // and a very non-standard xml structure is going on (character nodes between XML nodes)
+            //
+            // Example:
+            // ```
+            //  <URLCacheFlushInfo></URLCacheFlushInfo>&amp;quot&amp;<URLCacheResponseInfo></URLCacheResponseInfo>
+            // ```
+            // We shift the characters in to be consistent with regular json parser.
+            // The resulting JSON looks like:
+            // ```
+            // ...
+            //  "URLCacheResponseInfo": "\"",
+            //  "URLCacheResponseInfo_attributes": {
+            //      ...
+            //   }
+            // ...
+            // ```
+            let mut map = Map::new();
+            map.insert(key.to_owned(), v_temp.clone());
+
+            *v_temp = Value::Object(map);
+        } else if let Some(current_object) = v_temp.as_object_mut() {
+            current_object.insert(key.to_owned(), Value::Object(Map::new()));
+        }
+    }
+
+    v_temp
+        .get_mut(key)
+        .ok_or_else(|| invariant_violation("the child was inserted above if missing"))
+}
+
+/// An error for an internal invariant of `JsonOutput` which does not hold, instead of a panic.
+fn invariant_violation(invariant: &str) -> SerializationError {
+    SerializationError::JsonStructureError {
+        message: format!("This is a bug - expected that {}", invariant),
+    }
+}
+
+/// `xmlns` or `xmlns:prefix`.
+fn is_namespace_declaration(name: &str) -> bool {
+    name == "xmlns" || name.starts_with("xmlns:")
+}
+
+/// The name without its namespace prefix, `ns:EventData` -> `EventData`.
+fn local_name(name: &str) -> &str {
+    name.split_once(':').map_or(name, |(_, local)| local)
+}
+
+/// Appends text to an existing text value, turning it into a string if needed.
+fn append_text(current_value: &mut Value, value: &BinXmlValue) -> SerializationResult<()> {
+    match current_value {
+        Value::String(current_string) => current_string.push_str(&value.as_cow_str()),
+        Value::Number(_) | Value::Bool(_) => {
+            *current_value = json!(format!("{}{}", current_value, value.as_cow_str()));
+        }
+        current_value => {
+            return Err(SerializationError::JsonStructureError {
+                message: format!(
+                    "expected current value to be a String or a scalar, found {:?}, new value is {:?}",
+                    current_value, value
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites `System/Keywords` as `0x` followed by 16 hex digits.
+fn pad_keywords(system: &mut Map<String, Value>, text_key: &str) {
+    let value = match system.get_mut("Keywords") {
+        Some(Value::Object(object)) => object.get_mut(text_key),
+        value => value,
+    };
+    let value = match value {
+        Some(value) => value,
+        None => return,
+    };
+
+    let number = match value {
+        Value::String(text) => parse_integer(text),
+        Value::Number(_) => Some(value.clone()),
+        _ => None,
+    };
+    // Negative decimals are the two's complement of the bitmask.
+    let keywords = number.and_then(|n| n.as_u64().or_else(|| n.as_i64().map(|n| n as u64)));
+
+    if let Some(keywords) = keywords {
+        *value = json!(format!("0x{:016x}", keywords));
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hex integer, such as `Keywords="0x8020000000000000"`.
+///
+/// The `Number` is built from the integer itself, so 64-bit values are never rounded to an `f64`.
+fn parse_integer(text: &str) -> Option<Value> {
+    let text = text.trim();
+
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(Value::from),
+        None => text
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| text.parse::<u64>().map(Value::from))
+            .ok(),
+    }
+}
+
+/// Re-serializes a timestamp as RFC3339, in UTC and with microsecond precision.
+fn normalize_timestamp(value: &BinXmlValue) -> SerializationResult<Value> {
+    let timestamp = match value {
+        BinXmlValue::FileTimeType(timestamp) | BinXmlValue::SysTimeType(timestamp) => *timestamp,
+        BinXmlValue::NullType => return Ok(Value::Null),
+        value => {
+            let value = value.as_cow_str();
+            DateTime::parse_from_rfc3339(value.trim())
+                .map_err(|source| SerializationError::InvalidTimestamp {
+                    value: value.to_string(),
+                    source,
+                })?
+                .with_timezone(&Utc)
+        }
+    };
+
+    Ok(json!(timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)))
+}
+
+/// Expands one of the entities predefined by the XML specification.
+pub(crate) fn expand_entity_reference(entity: &BinXmlName) -> SerializationResult<&'static str> {
+    match entity.as_str() {
+        "amp" => Ok("&"),
+        "lt" => Ok("<"),
+        "gt" => Ok(">"),
+        "quot" => Ok("\""),
+        "apos" => Ok("'"),
+        name => Err(SerializationError::UnknownEntityReference {
+            name: name.to_owned(),
+        }),
+    }
+}
+
+/// Decodes a character reference, accepts both the decimal (`65`) and the hexadecimal (`x41`) forms.
+pub(crate) fn expand_character_reference(char_ref: &str) -> SerializationResult<char> {
+    let code_point = match char_ref.strip_prefix('x') {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => char_ref.parse::<u32>(),
+    };
+
+    code_point.ok().and_then(char::from_u32).ok_or_else(|| {
+        SerializationError::InvalidCharacterReference {
+            value: char_ref.to_string(),
+        }
+    })
+}
+
+impl BinXmlOutput for JsonOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        self.eof_reached = true;
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        trace!("visit_open_start_element: {:?}", element.name);
+        if self.skipped_depth > 0 {
+            self.skipped_depth += 1;
+            return Ok(());
+        }
+
+        if let Some(max_depth) = self.max_depth {
+            if self.stack.len() >= max_depth {
+                return Err(SerializationError::MaxDepthExceeded { max_depth });
+            }
+        }
+
+        if self.max_output_bytes.is_some() {
+            let len = element.name.as_str().len()
+                + element
+                    .attributes
+                    .iter()
+                    .map(|a| a.name.as_str().len() + a.value.as_cow_str().len())
+                    .sum::<usize>();
+            self.count_output(len)?;
+        }
+
+        let element_name = if self.strip_namespaces {
+            local_name(element.name.as_str())
+        } else {
+            element.name.as_str()
+        };
+
+        // Elements on the way to `root_path` are kept (without their attributes),
+        // and anything else above or beside it is skipped without being built.
+        if let Some(root_path) = &self.root_path {
+            match root_path.get(self.stack.len()) {
+                Some(name) if name != element_name => {
+                    self.skipped_depth = 1;
+                    return Ok(());
+                }
+                Some(_) if self.stack.len() + 1 < root_path.len() => {
+                    return self.insert_node_without_attributes(element, element_name);
+                }
+                _ => {}
+            }
+        }
+
+        // `UserData` children are defined by the provider, so their `Data` elements are regular nodes.
+        if self.is_structural_name(element_name, "Data") && !self.is_in_user_data() {
+            return self.insert_data_node(element);
+        }
+
+        // <Task>12288</Task> -> {"Task": 12288}
+        if element.attributes.is_empty() {
+            return self.insert_node_without_attributes(element, element_name);
+        }
+
+        self.insert_node_with_attributes(element, element_name)
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        if self.skipped_depth > 0 {
+            self.skipped_depth -= 1;
+            return Ok(());
+        }
+
+        if self.stack.is_empty() {
+            return Err(SerializationError::UnbalancedStack {
+                closed_elements: self.closed_elements,
+            });
+        }
+        self.closed_elements += 1;
+
+        if let Some((depth, _)) = self.data_nodes.last() {
+            if *depth == self.stack.len() {
+                trace!("visit_close_element: Data");
+                return self.close_data_node();
+            }
+        }
+
+        if let Some(empty) = self.empty_element_value() {
+            if let Some(value) = self.get_current_value().filter(|value| value.is_null()) {
+                *value = empty;
+            }
+        }
+
+        let p = self.pop_node()?;
+        trace!("visit_close_element: {:?}", p);
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        trace!("visit_chars {:?}", &self.stack);
+        if self.is_outside_root() {
+            return Ok(());
+        }
+        if self.trim_whitespace && self.is_whitespace_only(&value) {
+            return Ok(());
+        }
+        let value = BinXmlValue::encode_binary(value, self.binary_encoding, self.binary_byte_limit);
+        let value = self.control_chars.apply(value);
+        if self.max_output_bytes.is_some() {
+            self.count_output(value.as_cow_str().len())?;
+        }
+        // We need to clone this bool since the next statement will borrow self as mutable.
+        let separate_json_attributes = self.separate_json_attributes;
+        let text_key = self.text_key.clone();
+        let is_consecutive_text = match self.has_text.last_mut() {
+            Some(has_text) => std::mem::replace(has_text, true),
+            None => false,
+        };
+        let current_value = self.get_or_create_current_path()?;
+
+        // A small optimization in case we already have an owned string.
+        fn value_to_json(value: Cow<BinXmlValue>) -> Value {
+            if let Cow::Owned(BinXmlValue::StringType(value)) = value {
+                json!(value)
+            } else {
+                value.into_owned().into()
+            }
+        }
+
+        // If our parent is an element without any attributes,
+        // we simply swap the null with the string value.
+        // This is also true for the case when the attributes were inserted as our siblings.
+        match current_value {
+            // Regular, distinct node.
+            Value::Null => {
+                *current_value = value_to_json(value);
+            }
+            Value::Object(object) => {
+                if separate_json_attributes && object.is_empty() {
+                    *current_value = value_to_json(value);
+                } else {
+                    // Otherwise,
+                    // Should look like:
+                    // ----------------
+                    //  "EventID": {
+                    //    "#attributes": {
+                    //      "Qualifiers": ""
+                    //    },
+                    //    "#text": "4902"
+                    //  },
+                    //
+                    // If multiple nodes with the same name exists, we convert the `#text` attribute into an array.
+                    // The same goes for text which is separated by child elements (mixed content),
+                    // and for text after the children of a node when `separate_json_attributes` is on.
+                    // Consecutive text of the same node (ex. text split by an entity reference) is concatenated.
+                    match object.get_mut(&text_key) {
+                        // Regular, distinct node.
+                        None | Some(Value::Null) => {
+                            object.insert(text_key, value_to_json(value));
+                        }
+                        Some(current_text) if is_consecutive_text => {
+                            append_text(current_text, &value)?
+                        }
+                        // The first time we encounter another node with the same name,
+                        // we convert the exiting value into an array with both values.
+                        // This is done in place, since removing a key moves the last key in its place.
+                        Some(current_text @ Value::String(_)) => {
+                            let perv_value = current_text.take();
+                            *current_text = json!([perv_value, value_to_json(value)]);
+                        }
+                        // If we already have an array, we can just push into it.
+                        Some(Value::Array(arr)) => arr.push(value_to_json(value)),
+                        current_value => {
+                            return Err(SerializationError::JsonStructureError {
+                            message: format!(
+                                "expected current value to be a String or an Array, found {:?}, new value is {:?}",
+                                current_value, value
+                            ),
+                        });
+                        }
+                    }
+                }
+            }
+            // If we already have an array, we can just push into it.
+            Value::Array(arr) if !is_consecutive_text => arr.push(value_to_json(value)),
+            // The text of another node with the same name (ex. with `separate_json_attributes`).
+            Value::String(_) | Value::Number(_) | Value::Bool(_) if !is_consecutive_text => {
+                let prev_value = current_value.take();
+                *current_value = json!([prev_value, value_to_json(value)]);
+            }
+            current_value => append_text(current_value, &value)?,
+        }
+
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        trace!("visit_cdata_section {:?}", &self.stack);
+        const CDATA_KEY: &str = "#cdata";
+        if self.is_outside_root() {
+            return Ok(());
+        }
+        let cdata = self.control_chars.apply_str(cdata);
+        self.count_output(cdata.len())?;
+
+        let is_consecutive_text = match self.has_text.last_mut() {
+            Some(has_text) => std::mem::replace(has_text, true),
+            None => false,
+        };
+
+        // A node holding only CDATA is rendered like a regular text node.
+        match self.get_or_create_current_path()? {
+            current_value @ Value::Null => {
+                *current_value = json!(cdata);
+                return Ok(());
+            }
+            // Adjacent CDATA sections belong to the same text node.
+            Value::String(prev_cdata) if is_consecutive_text => {
+                prev_cdata.push_str(&cdata);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Otherwise CDATA is kept apart from the rest of the node, so that its boundaries survive:
+        // ----------------
+        //  "Data": {
+        //    "#attributes": {
+        //      "Name": "Payload"
+        //    },
+        //    "#cdata": "<inner/>"
+        //  },
+        let object = self.get_or_create_current_object()?;
+
+        match object.get_mut(CDATA_KEY) {
+            None | Some(Value::Null) => {
+                object.insert(CDATA_KEY.to_owned(), json!(cdata));
+            }
+            // Adjacent CDATA sections belong to the same text node.
+            Some(Value::String(prev_cdata)) => prev_cdata.push_str(&cdata),
+            current_value => {
+                return Err(SerializationError::JsonStructureError {
+                    message: format!(
+                        "expected current CDATA value to be a String, found {:?}, new value is {:?}",
+                        current_value, cdata
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> Result<(), SerializationError> {
+        let expanded = expand_entity_reference(entity)?;
+
+        self.visit_characters(Cow::Owned(BinXmlValue::StringType(expanded.to_owned())))
+    }
+
+    fn visit_character_reference(
+        &mut self,
+        char_ref: Cow<'_, str>,
+    ) -> Result<(), SerializationError> {
+        let c = expand_character_reference(&char_ref)?;
+
+        self.visit_characters(Cow::Owned(BinXmlValue::StringType(c.to_string())))
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> Result<(), SerializationError> {
+        trace!("visit_processing_instruction {:?}", &self.stack);
+        const PI_KEY: &str = "#processing_instructions";
+        if self.is_outside_root() {
+            return Ok(());
+        }
+
+        let object = self.get_or_create_current_object()?;
+        let pi = json!({"target": pi.name.as_str(), "data": pi.data});
+
+        match object.get_mut(PI_KEY) {
+            Some(Value::Array(instructions)) => instructions.push(pi),
+            _ => {
+                object.insert(PI_KEY.to_owned(), json!([pi]));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_comment(&mut self, comment: &str) -> SerializationResult<()> {
+        trace!("visit_comment {:?}", &self.stack);
+        const COMMENTS_KEY: &str = "#comments";
+        if self.is_outside_root() {
+            return Ok(());
+        }
+        self.count_output(comment.len())?;
+
+        let object = self.get_or_create_current_object()?;
+
+        match object.get_mut(COMMENTS_KEY) {
+            Some(Value::Array(comments)) => comments.push(json!(comment)),
+            _ => {
+                object.insert(COMMENTS_KEY.to_owned(), json!([comment]));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binxml::name::BinXmlName;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::err::SerializationError;
+    use crate::model::xml::test_helpers::{element, typed_element};
+    use crate::model::xml::{BinXmlPI, XmlAttribute, XmlElement};
+    use crate::{
+        BinXmlOutput, ControlCharPolicy, EmptyElementStyle, JsonCompatibility, JsonIndent,
+        JsonOutput, ParserSettings,
+    };
     use pretty_assertions::assert_eq;
     use quick_xml::events::{BytesStart, Event};
     use quick_xml::Reader;
+    use serde_json::json;
     use std::borrow::Cow;
 
     fn bytes_to_string(bytes: &[u8]) -> String {
@@ -448,10 +1511,7 @@ mod tests {
     }
 
     fn dummy_event() -> XmlElement<'static> {
-        XmlElement {
-            name: Cow::Owned(BinXmlName::from_str("Dummy")),
-            attributes: vec![],
-        }
+        element("Dummy", &[])
     }
 
     fn event_to_element(event: BytesStart) -> XmlElement {
@@ -507,10 +1567,24 @@ mod tests {
                             text.as_ref(),
                         ))))
                         .expect("Text element"),
-                    Event::Comment(_) => {}
-                    Event::CData(_) => unimplemented!(),
+                    Event::Comment(comment) => output
+                        .visit_comment(&bytes_to_string(comment.as_ref()))
+                        .expect("Comment"),
+                    Event::CData(cdata) => output
+                        .visit_cdata_section(Cow::Owned(bytes_to_string(cdata.as_ref())))
+                        .expect("CDATA element"),
                     Event::Decl(_) => {}
-                    Event::PI(_) => unimplemented!(),
+                    Event::PI(pi) => {
+                        let content = bytes_to_string(pi.as_ref());
+                        let (target, data) = content.split_once(' ').unwrap_or((&content, ""));
+
+                        output
+                            .visit_processing_instruction(&BinXmlPI {
+                                name: Cow::Owned(BinXmlName::from_str(target)),
+                                data: Cow::Borrowed(data),
+                            })
+                            .expect("PI element")
+                    }
                     Event::DocType(_) => {}
                     Event::Eof => {
                         output.visit_end_of_stream().expect("End of stream");
@@ -536,10 +1610,10 @@ mod tests {
         let s2 = r#"
 {
   "HTTPResponseHeadersInfo": {
-    "Header": "HTTP/1.1 200 OK",
     "Header_attributes": {
       "attribute1": "NoProxy"
-    }
+    },
+    "Header": "HTTP/1.1 200 OK"
   }
 }
 "#
@@ -554,4 +1628,1296 @@ mod tests {
 
         assert_eq!(xml_to_json(s1, &settings), s2)
     }
+
+    #[test]
+    fn test_flatten_json_attributes() {
+        let xml = r#"
+<Event>
+    <System>
+        <Provider Name="Microsoft-Windows-Security-Auditing" Guid="{54849625}"></Provider>
+        <EventID Qualifiers="0">4902</EventID>
+        <Level>0</Level>
+    </System>
+</Event>
+"#
+        .trim();
+        let expected = r#"
+{
+  "Event": {
+    "System": {
+      "Provider_Name": "Microsoft-Windows-Security-Auditing",
+      "Provider_Guid": "{54849625}",
+      "EventID_Qualifiers": "0",
+      "EventID": "4902",
+      "Level": "0"
+    }
+  }
+}
+"#
+        .trim();
+
+        for separate_json_attributes in [false, true] {
+            let settings = ParserSettings::new()
+                .separate_json_attributes(separate_json_attributes)
+                .flatten_json_attributes(true);
+            assert_eq!(xml_to_json(xml, &settings), expected)
+        }
+    }
+
+    #[test]
+    fn test_repeated_data_names_accumulate_into_array() {
+        let xml = r#"
+<Event>
+    <EventData>
+        <Data Name="Path">C:\first</Data>
+        <Data Name="User">bob</Data>
+        <Data Name="Path">C:\second</Data>
+        <Data Name="Path"></Data>
+    </EventData>
+</Event>
+"#
+        .trim();
+        let expected = r#"
+{
+  "Event": {
+    "EventData": {
+      "Path": [
+        "C:\\first",
+        "C:\\second",
+        null
+      ],
+      "User": "bob"
+    }
+  }
+}
+"#
+        .trim();
+
+        for separate_json_attributes in [false, true] {
+            let settings = ParserSettings::new().separate_json_attributes(separate_json_attributes);
+            assert_eq!(xml_to_json(xml, &settings), expected)
+        }
+    }
+
+    #[test]
+    fn test_unnamed_data_nodes_keep_document_order() {
+        let xml = r#"
+<Event>
+    <EventData>
+        <Data>first</Data>
+        <Data></Data>
+        <Data>third</Data>
+    </EventData>
+</Event>
+"#
+        .trim();
+        let expected = r#"
+{
+  "Event": {
+    "EventData": {
+      "Data": [
+        "first",
+        null,
+        "third"
+      ]
+    }
+  }
+}
+"#
+        .trim();
+
+        for separate_json_attributes in [false, true] {
+            let settings = ParserSettings::new().separate_json_attributes(separate_json_attributes);
+            assert_eq!(xml_to_json(xml, &settings), expected)
+        }
+    }
+
+    #[test]
+    fn test_attributes_keep_document_order() {
+        let xml = r#"
+<Event>
+    <Provider Name="Microsoft-Windows-Security-Auditing" Guid="{54849625-5478-4994-a5ba-3e3b0328c30d}" EventSourceName="Security"/>
+    <Execution ThreadID="460" ProcessID="456"/>
+</Event>
+"#
+        .trim();
+
+        for separate_json_attributes in [false, true] {
+            let settings = ParserSettings::new()
+                .indent(false)
+                .separate_json_attributes(separate_json_attributes);
+            let json = xml_to_json(xml, &settings);
+
+            let name = json.find("\"Name\"").unwrap();
+            let guid = json.find("\"Guid\"").unwrap();
+            let event_source_name = json.find("\"EventSourceName\"").unwrap();
+            let thread_id = json.find("\"ThreadID\"").unwrap();
+            let process_id = json.find("\"ProcessID\"").unwrap();
+
+            assert!(name < guid && guid < event_source_name, "{}", json);
+            assert!(thread_id < process_id, "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_empty_element_styles() {
+        let xml = r#"
+<Event>
+    <System>
+        <Correlation/>
+        <Keywords></Keywords>
+        <Provider Name="Security"/>
+    </System>
+    <EventData>
+        <Data Name="Empty"/>
+        <Data Name="Full">x</Data>
+    </EventData>
+</Event>
+"#
+        .trim();
+
+        let render = |style: EmptyElementStyle| -> serde_json::Value {
+            let settings = ParserSettings::new().empty_element_as(style);
+            serde_json::from_str(&xml_to_json(xml, &settings)).unwrap()
+        };
+
+        let expected = |empty: serde_json::Value| {
+            json!({
+                "Event": {
+                    "System": {
+                        "Correlation": empty.clone(),
+                        "Keywords": empty.clone(),
+                        "Provider": {"#attributes": {"Name": "Security"}}
+                    },
+                    "EventData": {
+                        "Empty": empty,
+                        "Full": "x"
+                    }
+                }
+            })
+        };
+
+        assert_eq!(render(EmptyElementStyle::Null), expected(json!(null)));
+        assert_eq!(render(EmptyElementStyle::EmptyObject), expected(json!({})));
+        assert_eq!(render(EmptyElementStyle::EmptyString), expected(json!("")));
+    }
+
+    #[test]
+    fn test_trim_whitespace() {
+        // `<Event>\n  <EventData>\n  </EventData>\n  <UserData>\n    <Data Name="User"> a </Data>\n  </UserData>\n</Event>`,
+        // with the indentation kept as text, like some records have.
+        let text = |s: &str| Cow::Owned(BinXmlValue::StringType(s.to_string()));
+        let event = element("Event", &[]);
+        let event_data = element("EventData", &[]);
+        let user_data = element("UserData", &[]);
+        let data = element("Data", &[("Name", "User")]);
+
+        let render = |settings: &ParserSettings| {
+            let mut output = JsonOutput::new(settings);
+            output.visit_start_of_stream().unwrap();
+            output.visit_open_start_element(&event).unwrap();
+            output.visit_characters(text("\n  ")).unwrap();
+            output.visit_open_start_element(&event_data).unwrap();
+            output.visit_characters(text("\n  ")).unwrap();
+            output.visit_close_element(&event_data).unwrap();
+            output.visit_characters(text("\n  ")).unwrap();
+            output.visit_open_start_element(&user_data).unwrap();
+            output.visit_characters(text("\n    ")).unwrap();
+            output.visit_open_start_element(&data).unwrap();
+            output.visit_characters(text(" a ")).unwrap();
+            output.visit_close_element(&data).unwrap();
+            output.visit_characters(text("\n  ")).unwrap();
+            output.visit_close_element(&user_data).unwrap();
+            output.visit_characters(text("\n")).unwrap();
+            output.visit_close_element(&event).unwrap();
+            output.visit_end_of_stream().unwrap();
+            output.into_value().unwrap()
+        };
+
+        // The indentation is kept by default.
+        let json = render(&ParserSettings::new());
+        assert_eq!(json["Event"]["EventData"], json!("\n  "));
+        assert_eq!(
+            json["Event"]["UserData"]["#text"],
+            json!(["\n    ", "\n  "])
+        );
+
+        assert_eq!(
+            render(&ParserSettings::new().trim_whitespace(true)),
+            json!({"Event": {"EventData": null, "UserData": {"Data": {"#attributes": {"Name": "User"}, "#text": " a "}}}})
+        );
+    }
+
+    #[test]
+    fn test_control_char_policy() {
+        let xml = "<Event><Message Text=\"a\u{1}b\">c\u{0}d\te</Message></Event>";
+
+        let render = |policy: ControlCharPolicy| -> serde_json::Value {
+            let settings = ParserSettings::new().control_char_policy(policy);
+            serde_json::from_str(&xml_to_json(xml, &settings)).unwrap()
+        };
+
+        let expected = |attribute: &str, text: &str| json!({"Event": {"Message": {"#attributes": {"Text": attribute}, "#text": text}}});
+
+        assert_eq!(
+            render(ControlCharPolicy::Escape),
+            expected("a\u{1}b", "c\u{0}d\te")
+        );
+        assert_eq!(render(ControlCharPolicy::Strip), expected("ab", "cde"));
+        assert_eq!(
+            render(ControlCharPolicy::Replace('?')),
+            expected("a?b", "c?d?e")
+        );
+        assert!(xml_to_json(xml, &ParserSettings::new()).contains(r"c\u0000d\te"));
+    }
+
+    #[test]
+    fn test_close_element_with_empty_stack_is_an_error() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        let event = element("Event", &[]);
+
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_close_element(&event).unwrap();
+
+        let err = output.visit_close_element(&event).unwrap_err();
+
+        assert!(matches!(
+            err,
+            SerializationError::UnbalancedStack { closed_elements: 2 }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Found a close element without a matching open element, after 2 close elements"
+        );
+    }
+
+    #[test]
+    fn test_broken_invariants_are_an_error() {
+        let event = element("Event", &[]);
+
+        // A node whose parent frame went missing.
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.frames.clear();
+
+        assert!(matches!(
+            output.visit_close_element(&event),
+            Err(SerializationError::JsonStructureError { .. })
+        ));
+
+        // A grandparent which was replaced by a value which can't hold children.
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.frames[0] = json!(["not", "an", "object"]);
+
+        assert!(matches!(
+            output.visit_close_element(&event),
+            Err(SerializationError::JsonStructureError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_node_names_are_interned() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        let event = element("Event", &[]);
+        let header = element("Header", &[]);
+
+        output.visit_open_start_element(&event).unwrap();
+        for _ in 0..3 {
+            output.visit_open_start_element(&header).unwrap();
+            output.visit_close_element(&header).unwrap();
+        }
+        output.visit_close_element(&event).unwrap();
+
+        assert_eq!(output.names.len(), 2);
+    }
+
+    #[test]
+    fn test_custom_attribute_and_text_keys() {
+        let xml = r#"
+<Event>
+    <EventID Qualifiers="16384">4111</EventID>
+    <RenderingInfo>Text<?render bold?></RenderingInfo>
+</Event>
+"#
+        .trim();
+
+        let settings = ParserSettings::new()
+            .attribute_key("@attributes")
+            .text_key("$text");
+        let json: serde_json::Value = serde_json::from_str(&xml_to_json(xml, &settings)).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "Event": {
+                    "EventID": {
+                        "@attributes": {"Qualifiers": "16384"},
+                        "$text": "4111"
+                    },
+                    "RenderingInfo": {
+                        "$text": "Text",
+                        "#processing_instructions": [{"target": "render", "data": "bold"}]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_open_elements_at_eof_is_an_error() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        let event = element("Event", &[]);
+
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert!(matches!(
+            output.into_value(),
+            Err(SerializationError::UnexpectedEof { open_elements: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_missing_eof_is_a_truncated_record() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        let event = element("Event", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+
+        assert!(matches!(
+            output.finish_record(),
+            Err(SerializationError::IncompleteOutput)
+        ));
+
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(output.finish_record().unwrap(), json!({ "Event": null }));
+    }
+
+    #[test]
+    fn test_symbolic_system_names() {
+        let xml = r#"<Event><System><Level>4</Level><Opcode Custom="1">1</Opcode><Task>12544</Task></System></Event>"#;
+
+        let json = xml_to_json(xml, &ParserSettings::new().symbolic_system_names(true));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            json!({"Event": {"System": {
+                "Level": "4",
+                "Opcode": {"#attributes": {"Custom": "1"}, "#text": "1"},
+                "Task": "12544",
+                "LevelName": "Information",
+                "OpcodeName": "Start"
+            }}})
+        );
+
+        let json = xml_to_json(
+            r#"<Event><System><Level>42</Level></System></Event>"#,
+            &ParserSettings::new().symbolic_system_names(true),
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            json!({"Event": {"System": {"Level": "42"}}})
+        );
+    }
+
+    #[test]
+    fn test_hex_keywords() {
+        let render = |xml: &str| -> serde_json::Value {
+            serde_json::from_str(&xml_to_json(xml, &ParserSettings::new().hex_keywords(true)))
+                .unwrap()
+        };
+
+        assert_eq!(
+            render(r#"<Event><System><Keywords>0x8020000000000000</Keywords></System></Event>"#),
+            json!({"Event": {"System": {"Keywords": "0x8020000000000000"}}})
+        );
+        assert_eq!(
+            render(r#"<Event><System><Keywords>0x10</Keywords></System></Event>"#),
+            json!({"Event": {"System": {"Keywords": "0x0000000000000010"}}})
+        );
+        // Decimal keywords, including negative ones, as some converters write them.
+        assert_eq!(
+            render(r#"<Event><System><Keywords>-9223372036854775808</Keywords></System></Event>"#),
+            json!({"Event": {"System": {"Keywords": "0x8000000000000000"}}})
+        );
+        assert_eq!(
+            render(r#"<Event><System><Keywords Custom="1">16</Keywords></System></Event>"#),
+            json!({"Event": {"System": {"Keywords": {"#attributes": {"Custom": "1"}, "#text": "0x0000000000000010"}}}})
+        );
+        // Only `System/Keywords` is rewritten, and only if it is a number.
+        assert_eq!(
+            render(
+                r#"<Event><System><Keywords>none</Keywords></System><EventData><Keywords>16</Keywords></EventData></Event>"#
+            ),
+            json!({"Event": {"System": {"Keywords": "none"}, "EventData": {"Keywords": "16"}}})
+        );
+    }
+
+    #[test]
+    fn test_json_indent() {
+        let value = json!({"Event": {"EventID": 4624}});
+
+        assert_eq!(
+            JsonIndent::default().to_string(&value).unwrap(),
+            "{\n  \"Event\": {\n    \"EventID\": 4624\n  }\n}"
+        );
+        assert_eq!(
+            JsonIndent::Spaces(4).to_string(&value).unwrap(),
+            "{\n    \"Event\": {\n        \"EventID\": 4624\n    }\n}"
+        );
+        assert_eq!(
+            JsonIndent::Tabs.to_string(&value).unwrap(),
+            "{\n\t\"Event\": {\n\t\t\"EventID\": 4624\n\t}\n}"
+        );
+        assert_eq!(
+            JsonIndent::Compact.to_string(&value).unwrap(),
+            "{\"Event\":{\"EventID\":4624}}"
+        );
+
+        let settings = ParserSettings::new().json_indent(JsonIndent::Tabs);
+        assert_eq!(settings.get_json_indent(), JsonIndent::Tabs);
+        assert_eq!(
+            settings.indent(false).get_json_indent(),
+            JsonIndent::Compact
+        );
+    }
+
+    #[test]
+    fn test_numeric_json_attributes() {
+        let xml = r#"<Event><System><EventID Qualifiers="16384">4624</EventID><Keywords Version="0x10" Name="7">a</Keywords><Execution ProcessID="abc" ThreadID="-5"/></System></Event>"#;
+
+        let json = xml_to_json(xml, &ParserSettings::new().numeric_json_attributes(true));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            json!({"Event": {"System": {
+                "EventID": {"#attributes": {"Qualifiers": 16384}, "#text": "4624"},
+                "Keywords": {"#attributes": {"Version": 16, "Name": "7"}, "#text": "a"},
+                "Execution": {"#attributes": {"ProcessID": "abc", "ThreadID": -5}}
+            }}})
+        );
+
+        // 64-bit values are kept exactly, and are not rounded to the precision of an `f64`.
+        let json = xml_to_json(
+            r#"<Event><Keywords Max="0xFFFFFFFFFFFFFFFF" Min="-9223372036854775808" Odd="9007199254740993"/></Event>"#,
+            &ParserSettings::new().numeric_json_attribute_names(&["Max", "Min", "Odd"]),
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()["Event"]["Keywords"]
+                ["#attributes"],
+            json!({"Max": u64::MAX, "Min": i64::MIN, "Odd": 9007199254740993_u64})
+        );
+        assert!(json.contains("18446744073709551615") && json.contains("9007199254740993"));
+
+        let json = xml_to_json(
+            xml,
+            &ParserSettings::new().numeric_json_attribute_names(&["Name"]),
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(
+            json["Event"]["System"]["Keywords"]["#attributes"],
+            json!({"Version": "0x10", "Name": 7})
+        );
+        assert_eq!(
+            json["Event"]["System"]["EventID"]["#attributes"]["Qualifiers"],
+            json!("16384")
+        );
+    }
+
+    #[test]
+    fn test_strict_json() {
+        let text = |s: &str| Cow::Owned(BinXmlValue::StringType(s.to_string()));
+        let event = element("Event", &[]);
+        let event_data = element("EventData", &[]);
+        let header = element("Header", &[]);
+        let unnamed_data = element("Data", &[]);
+        let settings = ParserSettings::new().strict_json(true);
+
+        let mut output = JsonOutput::new(&settings);
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event_data).unwrap();
+        assert!(matches!(
+            output.visit_open_start_element(&unnamed_data),
+            Err(SerializationError::UnnamedDataNode)
+        ));
+
+        let mut output = JsonOutput::new(&settings);
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&header).unwrap();
+        output.visit_characters(text("a")).unwrap();
+        output.visit_close_element(&header).unwrap();
+        assert!(matches!(
+            output.visit_open_start_element(&header),
+            Err(SerializationError::DuplicateElement { name }) if name == "Header"
+        ));
+
+        let duplicate_attributes = element(
+            "Provider",
+            &[("Name", "a"), ("Name", "b"), ("", "c"), ("Name", "d")],
+        );
+        let mut output = JsonOutput::new(&settings);
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        assert!(matches!(
+            output.visit_open_start_element(&duplicate_attributes),
+            Err(SerializationError::DuplicateAttribute { element, name })
+                if element == "Provider" && name == "Name"
+        ));
+
+        // Lenient by default, repeated attributes are renamed instead of being overwritten.
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output
+            .visit_open_start_element(&duplicate_attributes)
+            .unwrap();
+        output.visit_close_element(&duplicate_attributes).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+        assert_eq!(
+            output.into_value().unwrap(),
+            json!({"Event": {"Provider": {"#attributes": {"Name": "a", "Name_2": "b", "_": "c", "Name_3": "d"}}}})
+        );
+
+        let json = xml_to_json(
+            "<Event><Header>a</Header><Header>b</Header><Data>c</Data></Event>",
+            &ParserSettings::new(),
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            json!({"Event": {"Header": "b", "Header_1": "a", "Data": ["c"]}})
+        );
+    }
+
+    #[test]
+    fn test_reset_between_records() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        // Nothing was started, so there is nothing to discard.
+        output.reset().unwrap();
+
+        output.visit_start_of_stream().unwrap();
+        output
+            .visit_open_start_element(&element("Event", &[]))
+            .unwrap();
+        output
+            .visit_open_start_element(&element("System", &[]))
+            .unwrap();
+
+        assert!(matches!(
+            output.reset(),
+            Err(SerializationError::IncompleteOutput)
+        ));
+
+        output.visit_start_of_stream().unwrap();
+        output
+            .visit_open_start_element(&element("Other", &[]))
+            .unwrap();
+        output.visit_close_element(&element("Other", &[])).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(output.finish_record().unwrap(), json!({ "Other": null }));
+    }
+
+    #[test]
+    fn test_typed_values_keep_their_json_type() {
+        let render = |value: BinXmlValue<'static>| {
+            let event = typed_element("Event", &[("Attribute", value.clone())]);
+            let data = element("Value", &[]);
+
+            let mut output = JsonOutput::new(&ParserSettings::new());
+            output.visit_start_of_stream().unwrap();
+            output.visit_open_start_element(&event).unwrap();
+            output.visit_open_start_element(&data).unwrap();
+            output.visit_characters(Cow::Owned(value)).unwrap();
+            output.visit_close_element(&data).unwrap();
+            output.visit_close_element(&event).unwrap();
+            output.visit_end_of_stream().unwrap();
+
+            let json = output.into_value().unwrap();
+            (
+                json["Event"]["#attributes"]["Attribute"].clone(),
+                json["Event"]["Value"].clone(),
+            )
+        };
+
+        assert_eq!(render(BinXmlValue::Int8Type(-8)), (json!(-8), json!(-8)));
+        assert_eq!(
+            render(BinXmlValue::Int32Type(-32)),
+            (json!(-32), json!(-32))
+        );
+        assert_eq!(
+            render(BinXmlValue::Int64Type(i64::MIN)),
+            (json!(i64::MIN), json!(i64::MIN))
+        );
+        assert_eq!(
+            render(BinXmlValue::UInt16Type(4624)),
+            (json!(4624), json!(4624))
+        );
+        assert_eq!(
+            render(BinXmlValue::UInt64Type(u64::MAX)),
+            (json!(u64::MAX), json!(u64::MAX))
+        );
+        assert_eq!(
+            render(BinXmlValue::BoolType(true)),
+            (json!(true), json!(true))
+        );
+        assert_eq!(
+            render(BinXmlValue::BoolType(false)),
+            (json!(false), json!(false))
+        );
+        assert_eq!(
+            render(BinXmlValue::Real32Type(0.5)),
+            (json!(0.5), json!(0.5))
+        );
+        assert_eq!(
+            render(BinXmlValue::Real64Type(-1.25)),
+            (json!(-1.25), json!(-1.25))
+        );
+    }
+
+    #[test]
+    fn test_consecutive_characters_are_concatenated() {
+        let event = element("Event", &[]);
+        let event_id = typed_element("EventID", &[("Qualifiers", BinXmlValue::UInt16Type(16384))]);
+        let computer = element("Computer", &[]);
+        let text = |s: &str| Cow::Owned(BinXmlValue::StringType(s.to_string()));
+
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&computer).unwrap();
+        output.visit_characters(text("a")).unwrap();
+        output.visit_characters(text("b")).unwrap();
+        output.visit_close_element(&computer).unwrap();
+        output.visit_open_start_element(&event_id).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::UInt16Type(41)))
+            .unwrap();
+        output.visit_characters(text("11")).unwrap();
+        output.visit_characters(text("!")).unwrap();
+        output.visit_close_element(&event_id).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            output.into_value().unwrap(),
+            json!({
+                "Event": {
+                    "Computer": "ab",
+                    "EventID": {
+                        "#attributes": {"Qualifiers": 16384},
+                        "#text": "4111!"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_mixed_content() {
+        let settings = ParserSettings::new();
+        let text_then_child = xml_to_json(
+            "<Event><EventData>Text<Foo>1</Foo></EventData></Event>",
+            &settings,
+        );
+        let child_then_text = xml_to_json(
+            "<Event><EventData><Foo>1</Foo>Text</EventData></Event>",
+            &settings,
+        );
+
+        let expected = json!({"Event": {"EventData": {"#text": "Text", "Foo": "1"}}});
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&text_then_child).unwrap(),
+            expected
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&child_then_text).unwrap(),
+            expected
+        );
+
+        let separated = xml_to_json(
+            "<Event><EventData>a<Foo>1</Foo>b<Bar/></EventData></Event>",
+            &settings.clone().separate_json_attributes(true),
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&separated).unwrap(),
+            json!({"Event": {"EventData": {"#text": ["a", "b"], "Foo": "1", "Bar": null}}})
+        );
+    }
+
+    #[test]
+    fn test_mixed_content_keeps_key_order() {
+        let json = xml_to_json(
+            r#"<Event><EventData Source="test">a<Foo>1</Foo><Bar>2</Bar>b</EventData></Event>"#,
+            &ParserSettings::new(),
+        );
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let keys: Vec<&String> = json["Event"]["EventData"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        assert_eq!(keys, ["#attributes", "#text", "Foo", "Bar"]);
+        assert_eq!(json["Event"]["EventData"]["#text"], json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_strip_namespaces() {
+        let xml = r#"
+<Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+    <System>
+        <Provider Name="Security"/>
+    </System>
+    <ev:EventData xmlns:ev="urn:example" Source="test">
+        <ev:Data Name="User">bob</ev:Data>
+    </ev:EventData>
+</Event>
+"#
+        .trim();
+
+        let settings = ParserSettings::new().strip_namespaces(true);
+        let json: serde_json::Value = serde_json::from_str(&xml_to_json(xml, &settings)).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "Event": {
+                    "System": {
+                        "Provider": {"#attributes": {"Name": "Security"}}
+                    },
+                    "EventData": {
+                        "#attributes": {"Source": "test"},
+                        "User": "bob"
+                    }
+                }
+            })
+        );
+
+        // Namespaces are kept by default.
+        let json: serde_json::Value =
+            serde_json::from_str(&xml_to_json(xml, &ParserSettings::new())).unwrap();
+        assert_eq!(
+            json["Event"]["#attributes"]["xmlns"],
+            "http://schemas.microsoft.com/win/2004/08/events/event"
+        );
+        assert!(json["Event"]["ev:EventData"].is_object());
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let xml = "<Event><System><Provider><Name>x</Name></Provider></System></Event>";
+
+        let settings = ParserSettings::new().max_depth(4);
+        assert!(xml_to_json(xml, &settings).contains("\"Name\": \"x\""));
+
+        let mut output = JsonOutput::new(&ParserSettings::new().max_depth(3));
+        output
+            .visit_open_start_element(&element("Event", &[]))
+            .unwrap();
+        output
+            .visit_open_start_element(&element("System", &[]))
+            .unwrap();
+        output
+            .visit_open_start_element(&element("Provider", &[]))
+            .unwrap();
+
+        assert!(matches!(
+            output.visit_open_start_element(&element("Name", &[])),
+            Err(SerializationError::MaxDepthExceeded { max_depth: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_repeated_nodes_with_attributes_collect_text() {
+        let xml = r#"
+<Event>
+    <Binary Length="1">a</Binary>
+    <Binary Length="2">bc</Binary>
+</Event>
+"#
+        .trim();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&xml_to_json(xml, &ParserSettings::new())).unwrap();
+
+        assert_eq!(json["Event"]["Binary"]["#text"], json!(["a", "bc"]));
+    }
+
+    #[test]
+    fn test_repeated_sibling_text_nodes_are_not_concatenated() {
+        let xml = r#"
+<Event>
+    <Binary Length="1">a</Binary>
+    <Binary Length="2">bc</Binary>
+    <Binary Length="1">d</Binary>
+</Event>
+"#
+        .trim();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&xml_to_json(xml, &ParserSettings::new())).unwrap();
+        assert_eq!(json["Event"]["Binary"]["#text"], json!(["a", "bc", "d"]));
+
+        let settings = ParserSettings::new().separate_json_attributes(true);
+        let json: serde_json::Value = serde_json::from_str(&xml_to_json(xml, &settings)).unwrap();
+        assert_eq!(json["Event"]["Binary"], json!(["a", "bc", "d"]));
+    }
+
+    #[test]
+    fn test_deeply_nested_elements() {
+        let depth = 2000;
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        let node_element = element("Node", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        for _ in 0..depth {
+            output.visit_open_start_element(&node_element).unwrap();
+        }
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::StringType("leaf".to_string())))
+            .unwrap();
+        for _ in 0..depth {
+            output.visit_close_element(&node_element).unwrap();
+        }
+        output.visit_end_of_stream().unwrap();
+
+        let value = output.into_value().unwrap();
+        let mut node = &value;
+        for _ in 0..depth {
+            node = &node["Node"];
+        }
+
+        assert_eq!(node, &json!("leaf"));
+    }
+
+    #[test]
+    fn test_cdata_to_json() {
+        let xml = r#"
+<Event>
+    <EventData>
+        <Data><![CDATA[<inner/>]]></Data>
+        <Data Name="Payload"><![CDATA[a & b]]></Data>
+    </EventData>
+</Event>
+"#
+        .trim();
+        let expected = r##"
+{
+  "Event": {
+    "EventData": {
+      "Data": [
+        "<inner/>"
+      ],
+      "Payload": "a & b"
+    }
+  }
+}
+"##
+        .trim();
+
+        assert_eq!(xml_to_json(xml, &ParserSettings::new()), expected)
+    }
+
+    #[test]
+    fn test_cdata_with_attributes_to_json() {
+        let xml = r#"<Script Language="PowerShell"><![CDATA[if ($a -lt $b) {}]]></Script>"#;
+        let expected = r##"
+{
+  "Script": {
+    "#attributes": {
+      "Language": "PowerShell"
+    },
+    "#cdata": "if ($a -lt $b) {}"
+  }
+}
+"##
+        .trim();
+
+        assert_eq!(xml_to_json(xml, &ParserSettings::new()), expected)
+    }
+
+    #[test]
+    fn test_entity_and_character_references_are_expanded() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+        let element = element("Message", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&element).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::StringType("a".to_string())))
+            .unwrap();
+        output
+            .visit_entity_reference(&BinXmlName::from_str("amp"))
+            .unwrap();
+        output
+            .visit_character_reference(Cow::Borrowed("66"))
+            .unwrap();
+        output
+            .visit_character_reference(Cow::Borrowed("x1F600"))
+            .unwrap();
+        output.visit_close_element(&element).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            output.into_value().unwrap(),
+            serde_json::json!({"Message": "a&B\u{1F600}"})
+        );
+    }
+
+    #[test]
+    fn test_normalize_timestamps() {
+        let xml = r#"
+<Event>
+    <System>
+        <TimeCreated SystemTime="2021-03-01T14:34:56.789+02:00"/>
+        <Data Name="Other" SystemTime="2021-03-01T12:34:56Z">x</Data>
+    </System>
+</Event>
+"#
+        .trim();
+        let expected = r##"
+{
+  "Event": {
+    "System": {
+      "TimeCreated": {
+        "#attributes": {
+          "SystemTime": "2021-03-01T12:34:56.789000Z"
+        }
+      },
+      "Other": "x"
+    }
+  }
+}
+"##
+        .trim();
+
+        let settings = ParserSettings::new().normalize_timestamps(true);
+        assert_eq!(xml_to_json(xml, &settings), expected);
+
+        // Timestamps are passed through as-is by default.
+        assert!(xml_to_json(xml, &ParserSettings::new()).contains("2021-03-01T14:34:56.789+02:00"));
+    }
+
+    #[test]
+    fn test_malformed_timestamp_is_an_error() {
+        let mut output = JsonOutput::new(&ParserSettings::new().normalize_timestamps(true));
+        let time_created = element("TimeCreated", &[("SystemTime", "yesterday")]);
+
+        let err = output.visit_open_start_element(&time_created).unwrap_err();
+
+        assert_eq!(err.to_string(), "Failed to parse timestamp `yesterday`");
+    }
+
+    #[test]
+    fn test_invalid_character_reference_is_an_error() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+
+        // A lone surrogate is not a unicode scalar.
+        let err = output
+            .visit_character_reference(Cow::Borrowed("55296"))
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Character reference `&#55296;` does not refer to a valid unicode scalar"
+        );
+    }
+
+    #[test]
+    fn test_unknown_entity_reference_is_an_error() {
+        let mut output = JsonOutput::new(&ParserSettings::new());
+
+        let err = output
+            .visit_entity_reference(&BinXmlName::from_str("nbsp"))
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Unknown XML entity reference `&nbsp;`");
+    }
+
+    #[test]
+    fn test_processing_instructions_to_json() {
+        let xml = r#"
+<Event>
+    <?xml-stylesheet href="event.xsl"?>
+    <RenderingInfo>Text<?render bold?></RenderingInfo>
+</Event>
+"#
+        .trim();
+        let expected = r##"
+{
+  "Event": {
+    "#processing_instructions": [
+      {
+        "target": "xml-stylesheet",
+        "data": "href=\"event.xsl\""
+      }
+    ],
+    "RenderingInfo": {
+      "#text": "Text",
+      "#processing_instructions": [
+        {
+          "target": "render",
+          "data": "bold"
+        }
+      ]
+    }
+  }
+}
+"##
+        .trim();
+
+        assert_eq!(xml_to_json(xml, &ParserSettings::new()), expected)
+    }
+
+    #[test]
+    fn test_comments_to_json() {
+        let xml = r#"
+<Event>
+    <!-- rendered -->
+    <RenderingInfo>Text<!-- first --><!-- second --></RenderingInfo>
+</Event>
+"#
+        .trim();
+        let expected = r##"
+{
+  "Event": {
+    "#comments": [
+      " rendered "
+    ],
+    "RenderingInfo": {
+      "#text": "Text",
+      "#comments": [
+        " first ",
+        " second "
+      ]
+    }
+  }
+}
+"##
+        .trim();
+
+        assert_eq!(xml_to_json(xml, &ParserSettings::new()), expected)
+    }
+
+    #[test]
+    fn test_win_event_log_compatibility() {
+        let xml = r#"
+<Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+    <System>
+        <Provider Name="Microsoft-Windows-Security-Auditing" Guid="{54849625-5478-4994-A5BA-3E3B0328C30D}"/>
+        <EventID Qualifiers="">4624</EventID>
+        <Level>0</Level>
+        <Keywords>0x8020000000000000</Keywords>
+        <TimeCreated SystemTime="2016-07-08T18:12:51.681640Z"/>
+        <EventRecordID>1</EventRecordID>
+        <Correlation/>
+        <Execution ProcessID="456" ThreadID="460"/>
+        <Channel>Security</Channel>
+        <Computer>WIN-L6PC55MPB98</Computer>
+        <Security/>
+    </System>
+    <EventData>
+        <Data Name="TargetUserName">bob</Data>
+    </EventData>
+</Event>
+"#
+        .trim();
+
+        for separate_json_attributes in [false, true] {
+            let settings = ParserSettings::new()
+                .json_compatibility(JsonCompatibility::WinEventLog)
+                .separate_json_attributes(separate_json_attributes);
+            let json: serde_json::Value =
+                serde_json::from_str(&xml_to_json(xml, &settings)).unwrap();
+
+            assert_eq!(
+                json,
+                json!({
+                    "provider_name": "Microsoft-Windows-Security-Auditing",
+                    "provider_guid": "{54849625-5478-4994-A5BA-3E3B0328C30D}",
+                    "event_id": "4624",
+                    "level": "0",
+                    "keywords": "0x8020000000000000",
+                    "time_created": "2016-07-08T18:12:51.681640Z",
+                    "record_id": "1",
+                    "process": {"pid": "456", "thread": {"id": "460"}},
+                    "channel": "Security",
+                    "computer_name": "WIN-L6PC55MPB98",
+                    "event_data": {"TargetUserName": "bob"}
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_root_path() {
+        let xml = r#"
+<Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+    <System>
+        <EventID>4624</EventID>
+    </System>
+    <EventData Source="test">
+        <Data Name="TargetUserName">bob</Data>
+        <Data Name="LogonType">2</Data>
+    </EventData>
+    <RenderingInfo Culture="en-US">
+        <Message>An account was successfully logged on.</Message>
+    </RenderingInfo>
+</Event>
+"#
+        .trim();
+
+        let settings = ParserSettings::new().json_root_path("Event/EventData");
+        let json: serde_json::Value = serde_json::from_str(&xml_to_json(xml, &settings)).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "#attributes": {"Source": "test"},
+                "TargetUserName": "bob",
+                "LogonType": "2"
+            })
+        );
+
+        // Records without the subtree are `null`.
+        let settings = ParserSettings::new().json_root_path("Event/UserData");
+        assert_eq!(xml_to_json(xml, &settings), "null");
+
+        // An empty path renders the whole record.
+        let settings = ParserSettings::new().json_root_path("");
+        assert_eq!(
+            xml_to_json(xml, &settings),
+            xml_to_json(xml, &ParserSettings::new())
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_json() {
+        let xml = |reversed: bool| {
+            let mut event_data = [
+                r#"<Data Name="TargetUserName">bob</Data>"#,
+                r#"<Data Name="LogonType">2</Data>"#,
+            ];
+            let mut user_data = [
+                "<TargetUserName>bob</TargetUserName>",
+                "<LogonType>2</LogonType>",
+            ];
+            if reversed {
+                event_data.reverse();
+                user_data.reverse();
+            }
+
+            format!(
+                "<Event><System><EventID>4624</EventID><Level>0</Level></System>\
+                 <EventData>{}</EventData><UserData><EventXML>{}</EventXML></UserData></Event>",
+                event_data.concat(),
+                user_data.concat()
+            )
+        };
+
+        let settings = ParserSettings::new().canonicalize_json(true);
+        let json = xml_to_json(&xml(false), &settings);
+
+        assert_eq!(json, xml_to_json(&xml(true), &settings));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            json!({
+                "Event": {
+                    "System": {"EventID": "4624", "Level": "0"},
+                    "EventData": {"LogonType": "2", "TargetUserName": "bob"},
+                    "UserData": {"EventXML": {"LogonType": "2", "TargetUserName": "bob"}}
+                }
+            })
+        );
+
+        // Document order is preserved by default.
+        assert_ne!(
+            xml_to_json(&xml(false), &ParserSettings::new()),
+            xml_to_json(&xml(true), &ParserSettings::new())
+        );
+    }
+
+    #[test]
+    fn test_user_data_keeps_native_element_names() {
+        let xml = r#"
+<Event>
+    <UserData>
+        <EventXML xmlns="urn:provider">
+            <Param>first</Param>
+            <Data>second</Data>
+        </EventXML>
+    </UserData>
+</Event>
+"#
+        .trim();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&xml_to_json(xml, &ParserSettings::new())).unwrap();
+
+        assert_eq!(
+            json["Event"]["UserData"],
+            json!({
+                "EventXML": {
+                    "#attributes": {"xmlns": "urn:provider"},
+                    "Param": "first",
+                    "Data": "second"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_ascii_case_insensitive() {
+        let xml = r#"
+<Event>
+    <eventData>
+        <data name="User">bob</data>
+        <DATA Name="Logon">2</DATA>
+    </eventData>
+    <userdata>
+        <EventXML>
+            <Data>kept</Data>
+        </EventXML>
+    </userdata>
+</Event>
+"#
+        .trim();
+
+        let json = |settings: &ParserSettings| -> serde_json::Value {
+            serde_json::from_str(&xml_to_json(xml, settings)).unwrap()
+        };
+
+        let settings = ParserSettings::new()
+            .ascii_case_insensitive(true)
+            .canonicalize_json(true);
+        assert_eq!(
+            json(&settings),
+            json!({
+                "Event": {
+                    "eventData": {"Logon": "2", "User": "bob"},
+                    "userdata": {"EventXML": {"Data": "kept"}}
+                }
+            })
+        );
+
+        // Oddly-cased names are regular nodes by default.
+        assert_eq!(
+            json(&ParserSettings::new())["Event"]["eventData"],
+            json!({
+                "data": {"#attributes": {"name": "User"}, "#text": "bob"},
+                "DATA": {"#attributes": {"Name": "Logon"}, "#text": "2"}
+            })
+        );
+    }
 }
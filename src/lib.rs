@@ -11,11 +11,38 @@ mod macros;
 #[macro_use]
 extern crate bitflags;
 
-pub use evtx_chunk::{EvtxChunk, EvtxChunkData, EvtxChunkHeader, IterChunkRecords};
-pub use evtx_parser::{EvtxParser, IntoIterChunks, IterChunks, ParserSettings};
-pub use evtx_record::{EvtxRecord, EvtxRecordHeader, SerializedEvtxRecord};
-pub use json_output::JsonOutput;
-pub use xml_output::{BinXmlOutput, XmlOutput};
+pub use binxml::value_variant::{BinaryEncoding, DefaultFormatter, ValueFormatter};
+pub use columnar_output::{ColumnBatch, ColumnType, ColumnValues, ColumnarOutput};
+pub use csv_output::CsvOutput;
+pub use event_data_output::EventDataOutput;
+pub use evtx_chunk::{ChecksumStatus, EvtxChunk, EvtxChunkData, EvtxChunkHeader, IterChunkRecords};
+pub use evtx_file_header::{EvtxFileHeader, HeaderFlags};
+pub use evtx_parser::{
+    ErrorPolicy, EvtxParser, IntoIterChunks, IterChunks, MessageResolver, ParserSettings,
+    RecordCallback, RecordSummary, ValueTransform,
+};
+pub use evtx_record::{
+    render_record_to_json, render_record_to_json_into, render_record_to_xml,
+    render_record_to_xml_into, EvtxRecord, EvtxRecordHeader, RecordContext, RecordPlaceholder,
+    SerializedEvtxRecord,
+};
+pub use evtx_stream_parser::EvtxStreamParser;
+pub use evtx_writer::EvtxWriter;
+pub use flat_json_output::FlatJsonOutput;
+pub use hashing_output::HashingOutput;
+pub use json_array_output::JsonArrayOutput;
+pub use json_output::{
+    ControlCharPolicy, EmptyElementStyle, JsonCompatibility, JsonIndent, JsonOutput,
+};
+pub use null_output::NullOutput;
+pub use record_writer::RecordWriter;
+pub use system_fields::SystemFields;
+pub use template_cache::{TemplateDescriptor, TemplateElement};
+pub use tee_output::TeeOutput;
+pub use token_trace_output::TokenTraceOutput;
+pub use tree_output::{TreeElement, TreeNode, TreeOutput};
+pub use utils::OnInvalidUtf16;
+pub use xml_output::{BinXmlOutput, OnUnhandled, XmlOutput, XmlOutputOptions, XmlOutputStyle};
 
 pub mod binxml;
 pub mod err;
@@ -25,11 +52,26 @@ mod evtx_chunk;
 mod evtx_file_header;
 mod evtx_parser;
 mod evtx_record;
+mod evtx_stream_parser;
+mod evtx_writer;
+mod record_writer;
 mod string_cache;
 mod template_cache;
 mod utils;
 
+mod columnar_output;
+mod csv_output;
+mod event_data_output;
+mod field_selector;
+mod flat_json_output;
+mod hashing_output;
+mod json_array_output;
 mod json_output;
+mod null_output;
+mod system_fields;
+mod tee_output;
+mod token_trace_output;
+mod tree_output;
 mod xml_output;
 
 pub type ChunkOffset = u32;
@@ -147,31 +147,19 @@ macro_rules! try_read {
         Guid::from_reader($cursor).map_err(|e| capture_context!($cursor, e, "guid", $name))
     };
 
-    ($cursor: ident, len_prefixed_utf_16_str) => {{
-        try_read!($cursor, len_prefixed_utf_16_str, "<Unknown>")
-    }};
-
-    ($cursor: ident, len_prefixed_utf_16_str, $name: expr) => {
-        read_len_prefixed_utf16_string($cursor, false)
+    ($cursor: ident, len_prefixed_utf_16_str, $name: expr, $on_invalid_utf16: expr) => {
+        read_len_prefixed_utf16_string($cursor, false, $on_invalid_utf16)
             .map_err(|e| capture_context!($cursor, e, "len_prefixed_utf_16_str", $name))
     };
 
-    ($cursor: ident, len_prefixed_utf_16_str_nul_terminated) => {{
-        try_read!($cursor, len_prefixed_utf_16_str_nul_terminated, "<Unknown>")
-    }};
-
-    ($cursor: ident, len_prefixed_utf_16_str_nul_terminated, $name: expr) => {
-        read_len_prefixed_utf16_string($cursor, true).map_err(|e| {
+    ($cursor: ident, len_prefixed_utf_16_str_nul_terminated, $name: expr, $on_invalid_utf16: expr) => {
+        read_len_prefixed_utf16_string($cursor, true, $on_invalid_utf16).map_err(|e| {
             capture_context!($cursor, e, "len_prefixed_utf_16_str_nul_terminated", $name)
         })
     };
 
-    ($cursor: ident, null_terminated_utf_16_str) => {{
-        try_read!($cursor, null_terminated_utf_16_str, "<Unknown>")
-    }};
-
-    ($cursor: ident, null_terminated_utf_16_str, $name: expr) => {
-        read_null_terminated_utf16_string($cursor)
+    ($cursor: ident, null_terminated_utf_16_str, $name: expr, $on_invalid_utf16: expr) => {
+        read_null_terminated_utf16_string($cursor, $on_invalid_utf16)
             .map_err(|e| capture_context!($cursor, e, "null_terminated_utf_16_str", $name))
     };
 
@@ -207,7 +195,16 @@ macro_rules! try_read {
 }
 
 macro_rules! try_read_sized_array {
-    ($cursor: ident, $unit: ident, $size: ident) => {{
+    ($cursor: ident, $unit: ident, $size: ident) => {
+        try_read_sized_array!(@read $cursor, $size, try_read!($cursor, $unit))
+    };
+
+    // Units which need extra arguments, such as strings (which need `OnInvalidUtf16`).
+    ($cursor: ident, $unit: ident, $size: ident, $($arg: expr),+) => {
+        try_read_sized_array!(@read $cursor, $size, try_read!($cursor, $unit, "<Unknown>", $($arg),+))
+    };
+
+    (@read $cursor: ident, $size: ident, $read: expr) => {{
         let mut array = vec![];
         let start_pos = $cursor.position();
 
@@ -216,7 +213,7 @@ macro_rules! try_read_sized_array {
                 break;
             }
 
-            let val = try_read!($cursor, $unit)?;
+            let val = $read?;
             array.push(val);
         }
 
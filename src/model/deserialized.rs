@@ -16,8 +16,8 @@ pub enum BinXMLDeserializedTokens<'a> {
     CloseEmptyElement,
     CloseElement,
     Value(BinXmlValue<'a>),
-    CDATASection,
-    CharRef,
+    CDATASection(String),
+    CharRef(u16),
     EntityRef(BinXmlEntityReference),
     PITarget(BinXMLProcessingInstructionTarget),
     PIData(String),
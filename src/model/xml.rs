@@ -3,7 +3,9 @@ use crate::binxml::value_variant::BinXmlValue;
 
 use crate::err::EvtxError;
 use log::error;
+use quick_xml::events::BytesStart;
 use std::borrow::Cow;
+use std::fmt;
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
 pub enum XmlModel<'a> {
@@ -12,6 +14,8 @@ pub enum XmlModel<'a> {
     PI(BinXmlPI<'a>),
     EntityRef(Cow<'a, BinXmlName>),
     Value(Cow<'a, BinXmlValue<'a>>),
+    CData(Cow<'a, str>),
+    CharRef(u16),
     EndOfStream,
     StartOfStream,
 }
@@ -121,8 +125,140 @@ pub struct XmlElement<'a> {
     pub attributes: Vec<XmlAttribute<'a>>,
 }
 
+impl<'a> XmlElement<'a> {
+    /// The value of the attribute called `name`, if the element has one.
+    pub fn get_attribute(&self, name: &str) -> Option<&BinXmlValue<'a>> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name.as_str() == name)
+            .map(|attribute| attribute.value.as_ref())
+    }
+
+    /// The value of the attribute called `name` as text, if the element has one.
+    ///
+    /// String values are borrowed, other values are formatted as they are in XML.
+    pub fn attribute_str(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.get_attribute(name).map(BinXmlValue::as_cow_str)
+    }
+}
+
+/// Renders the start tag of the element, (ex. `<Provider Name="Microsoft-Windows-Kernel-General">`).
+/// Attribute values are escaped and written as they appear in text, without any formatting settings.
+impl<'a> fmt::Display for XmlElement<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut start = BytesStart::from(self.name.as_ref());
+        for attribute in self.attributes.iter() {
+            start.push_attribute((
+                attribute.name.as_str(),
+                attribute.value.as_cow_str().as_ref(),
+            ));
+        }
+
+        write!(f, "<{}>", String::from_utf8_lossy(&start))
+    }
+}
+
 #[derive(Debug, PartialOrd, PartialEq, Eq, Clone)]
 pub struct BinXmlPI<'a> {
     pub name: Cow<'a, BinXmlName>,
     pub data: Cow<'a, str>,
 }
+
+#[cfg(test)]
+pub(crate) mod test_helpers {
+    use super::{XmlAttribute, XmlElement};
+    use crate::binxml::name::BinXmlName;
+    use crate::binxml::value_variant::BinXmlValue;
+    use std::borrow::Cow;
+
+    /// An element with string attributes, to drive outputs in tests.
+    pub(crate) fn element(name: &str, attributes: &[(&str, &str)]) -> XmlElement<'static> {
+        let attributes: Vec<_> = attributes
+            .iter()
+            .map(|(name, value)| (*name, BinXmlValue::StringType(value.to_string())))
+            .collect();
+
+        typed_element(name, &attributes)
+    }
+
+    /// Like `element`, for attributes which are not strings.
+    pub(crate) fn typed_element<'a>(
+        name: &str,
+        attributes: &[(&str, BinXmlValue<'a>)],
+    ) -> XmlElement<'a> {
+        XmlElement {
+            name: Cow::Owned(BinXmlName::from_str(name)),
+            attributes: attributes
+                .iter()
+                .map(|(name, value)| XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str(name)),
+                    value: Cow::Owned(value.clone()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{XmlAttribute, XmlElement};
+    use crate::binxml::name::BinXmlName;
+    use crate::binxml::value_variant::BinXmlValue;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_get_attribute() {
+        let element = XmlElement {
+            name: Cow::Owned(BinXmlName::from_str("EventID")),
+            attributes: vec![
+                XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str("Name")),
+                    value: Cow::Owned(BinXmlValue::StringType("TargetUserName".to_owned())),
+                },
+                XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str("Qualifiers")),
+                    value: Cow::Owned(BinXmlValue::UInt16Type(16384)),
+                },
+            ],
+        };
+
+        assert_eq!(
+            element.get_attribute("Qualifiers"),
+            Some(&BinXmlValue::UInt16Type(16384))
+        );
+        assert_eq!(element.attribute_str("Name").unwrap(), "TargetUserName");
+        assert_eq!(element.attribute_str("Qualifiers").unwrap(), "16384");
+
+        // Lookups are case sensitive, like XML.
+        assert_eq!(element.get_attribute("name"), None);
+        assert_eq!(element.attribute_str("Missing"), None);
+
+        let element = XmlElement {
+            name: Cow::Owned(BinXmlName::from_str("Data")),
+            attributes: vec![],
+        };
+        assert_eq!(element.get_attribute("Name"), None);
+    }
+
+    #[test]
+    fn test_element_displays_as_start_tag() {
+        let element = XmlElement {
+            name: Cow::Owned(BinXmlName::from_str("Data")),
+            attributes: vec![
+                XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str("Name")),
+                    value: Cow::Owned(BinXmlValue::StringType("a<b & \"c\"".to_owned())),
+                },
+                XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str("Count")),
+                    value: Cow::Owned(BinXmlValue::UInt32Type(3)),
+                },
+            ],
+        };
+
+        assert_eq!(
+            element.to_string(),
+            r#"<Data Name="a&lt;b &amp; &quot;c&quot;" Count="3">"#
+        );
+    }
+}
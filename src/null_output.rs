@@ -0,0 +1,94 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+use crate::ParserSettings;
+
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Discards everything it visits, useful for measuring the cost of parsing alone.
+///
+/// Wraps a writer only to mirror `XmlOutput`, nothing is ever written to it.
+pub struct NullOutput<W: Write> {
+    writer: W,
+    eof_reached: bool,
+}
+
+impl<W: Write> NullOutput<W> {
+    pub fn with_writer(target: W, _settings: &ParserSettings) -> Self {
+        NullOutput {
+            writer: target,
+            eof_reached: false,
+        }
+    }
+
+    /// Returns the writer, fails if the end of the stream was not reached yet.
+    pub fn into_writer(self) -> SerializationResult<W> {
+        if self.eof_reached {
+            Ok(self.writer)
+        } else {
+            Err(SerializationError::IncompleteOutput)
+        }
+    }
+}
+
+impl<W: Write> BinXmlOutput for NullOutput<W> {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        self.eof_reached = true;
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, _value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, _cdata: Cow<'_, str>) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, _entity: &BinXmlName) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, _char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        self.eof_reached = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NullOutput;
+    use crate::{BinXmlOutput, ParserSettings};
+
+    #[test]
+    fn test_into_writer_requires_eof() {
+        let mut output = NullOutput::with_writer(vec![], &ParserSettings::new());
+        output.visit_start_of_stream().unwrap();
+        assert!(output.into_writer().is_err());
+
+        let mut output = NullOutput::with_writer(vec![], &ParserSettings::new());
+        output.visit_start_of_stream().unwrap();
+        output.visit_end_of_stream().unwrap();
+        assert_eq!(output.into_writer().unwrap(), Vec::<u8>::new());
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::{self, Write};
+
+/// Writes many serialized records to a single writer, with a `prefix` before the first record,
+/// a `separator` between records and a `suffix` after the last one.
+///
+/// This turns the output of the single record backends into collections, for example
+/// a JSON array:
+///
+/// ```rust
+/// # use evtx::{EvtxParser, ParserSettings, RecordWriter};
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let mut parser = EvtxParser::from_path(fp)
+///     .unwrap()
+///     .with_configuration(ParserSettings::new().indent(false));
+/// let mut writer = RecordWriter::json_array(Vec::new());
+///
+/// for record in parser.records_json() {
+///     writer.write_record(record.unwrap().data.as_bytes()).unwrap();
+/// }
+///
+/// let records: serde_json::Value = serde_json::from_slice(&writer.into_inner().unwrap()).unwrap();
+/// assert!(records.is_array());
+/// ```
+///
+/// Records can also be streamed, by calling `start_record` and then writing the record to the
+/// `RecordWriter` itself.
+///
+/// Nothing is buffered, so the output can be compressed by wrapping any encoder,
+/// which is returned by `into_inner` to be finished, for example with `flate2`:
+///
+/// ```rust,ignore
+/// let file = std::fs::File::create("security.json.gz")?;
+/// let mut writer = RecordWriter::json_array(GzEncoder::new(file, Compression::default()));
+///
+/// for record in parser.records_json() {
+///     writer.write_record(record?.data.as_bytes())?;
+/// }
+///
+/// writer.into_inner()?.finish()?;
+/// ```
+pub struct RecordWriter<W: Write> {
+    inner: W,
+    prefix: Vec<u8>,
+    separator: Vec<u8>,
+    suffix: Vec<u8>,
+    records_written: usize,
+}
+
+impl<W: Write> RecordWriter<W> {
+    /// A writer which separates records with `\n`, without a prefix or a suffix.
+    pub fn new(inner: W) -> Self {
+        RecordWriter {
+            inner,
+            prefix: vec![],
+            separator: b"\n".to_vec(),
+            suffix: vec![],
+            records_written: 0,
+        }
+    }
+
+    /// Writes records as the elements of a JSON array, one per line.
+    pub fn json_array(inner: W) -> Self {
+        RecordWriter::new(inner)
+            .prefix("[\n")
+            .separator(",\n")
+            .suffix("\n]\n")
+    }
+
+    /// Writes records as newline-delimited JSON (NDJSON), each record ends with a `\n`.
+    /// Records must be written without indentation.
+    pub fn ndjson(inner: W) -> Self {
+        RecordWriter::new(inner).separator("\n").suffix("\n")
+    }
+
+    /// Written before the first record.
+    pub fn prefix(mut self, prefix: impl Into<Vec<u8>>) -> Self {
+        self.prefix = prefix.into();
+
+        self
+    }
+
+    /// Written between consecutive records.
+    pub fn separator(mut self, separator: impl Into<Vec<u8>>) -> Self {
+        self.separator = separator.into();
+
+        self
+    }
+
+    /// Written after the last record.
+    ///
+    /// If there are no records, the prefix is directly followed by the suffix, so that
+    /// collections are still closed. Nothing is written at all if there is no prefix either.
+    pub fn suffix(mut self, suffix: impl Into<Vec<u8>>) -> Self {
+        self.suffix = suffix.into();
+
+        self
+    }
+
+    /// Writes the prefix or the separator, anything written afterwards is part of a new record.
+    pub fn start_record(&mut self) -> io::Result<()> {
+        if self.records_written == 0 {
+            self.inner.write_all(&self.prefix)?;
+        } else {
+            self.inner.write_all(&self.separator)?;
+        }
+        self.records_written += 1;
+
+        Ok(())
+    }
+
+    /// Writes a whole record.
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        self.start_record()?;
+        self.inner.write_all(record)
+    }
+
+    /// The number of records started so far.
+    pub fn records_written(&self) -> usize {
+        self.records_written
+    }
+
+    /// Writes the suffix, and returns the underlying writer after flushing it.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        if self.records_written == 0 && !self.prefix.is_empty() {
+            self.inner.write_all(&self.prefix)?;
+            self.inner.write_all(&self.suffix)?;
+        } else if self.records_written > 0 {
+            self.inner.write_all(&self.suffix)?;
+        }
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for RecordWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordWriter;
+    use std::io::Write;
+
+    fn write_records(mut writer: RecordWriter<Vec<u8>>, records: &[&str]) -> String {
+        for record in records {
+            writer.write_record(record.as_bytes()).unwrap();
+        }
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_json_array() {
+        let json = write_records(
+            RecordWriter::json_array(vec![]),
+            &["{\"a\":1}", "{\"b\":2}"],
+        );
+        assert_eq!(json, "[\n{\"a\":1},\n{\"b\":2}\n]\n");
+        assert!(serde_json::from_str::<serde_json::Value>(&json)
+            .unwrap()
+            .is_array());
+
+        let empty = write_records(RecordWriter::json_array(vec![]), &[]);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&empty).unwrap(),
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let json = write_records(RecordWriter::ndjson(vec![]), &["{\"a\":1}", "{\"b\":2}"]);
+        assert_eq!(json, "{\"a\":1}\n{\"b\":2}\n");
+        assert_eq!(write_records(RecordWriter::ndjson(vec![]), &[]), "");
+    }
+
+    #[test]
+    fn test_streamed_records() {
+        let mut writer = RecordWriter::new(vec![])
+            .prefix("<Events>")
+            .separator("")
+            .suffix("</Events>");
+
+        for id in 1..=2 {
+            writer.start_record().unwrap();
+            write!(writer, "<Event>{}</Event>", id).unwrap();
+        }
+
+        assert_eq!(writer.records_written(), 2);
+        assert_eq!(
+            writer.into_inner().unwrap(),
+            b"<Events><Event>1</Event><Event>2</Event></Events>"
+        );
+    }
+}
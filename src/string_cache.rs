@@ -1,5 +1,6 @@
 use crate::binxml::name::{BinXmlName, BinXmlNameLink};
 use crate::err::DeserializationResult;
+use crate::utils::OnInvalidUtf16;
 use crate::ChunkOffset;
 
 use log::trace;
@@ -11,7 +12,11 @@ use std::io::{Cursor, Seek, SeekFrom};
 pub struct StringCache(HashMap<ChunkOffset, BinXmlName>);
 
 impl StringCache {
-    pub fn populate(data: &[u8], offsets: &[ChunkOffset]) -> DeserializationResult<Self> {
+    pub fn populate(
+        data: &[u8],
+        offsets: &[ChunkOffset],
+        on_invalid_utf16: OnInvalidUtf16,
+    ) -> DeserializationResult<Self> {
         let mut cache = HashMap::new();
         let mut cursor = Cursor::new(data);
         let cursor_ref = cursor.borrow_mut();
@@ -22,7 +27,7 @@ impl StringCache {
             loop {
                 let string_position = cursor_ref.position() as ChunkOffset;
                 let link = BinXmlNameLink::from_stream(cursor_ref)?;
-                let name = BinXmlName::from_stream(cursor_ref)?;
+                let name = BinXmlName::from_stream(cursor_ref, on_invalid_utf16)?;
 
                 cache.insert(string_position, name);
 
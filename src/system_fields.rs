@@ -0,0 +1,245 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// The well known fields of a record's `System` element.
+///
+/// Built from the JSON representation of a record, in either the default
+/// or the `separate_json_attributes` layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemFields {
+    pub event_id: u32,
+    pub provider: String,
+    pub computer: String,
+    pub time_created: DateTime<Utc>,
+    pub provider_guid: Option<String>,
+    pub channel: Option<String>,
+    pub event_record_id: Option<u64>,
+    pub version: Option<u8>,
+    pub level: Option<u8>,
+    pub task: Option<u16>,
+    pub opcode: Option<u8>,
+    pub keywords: Option<String>,
+    pub process_id: Option<u32>,
+    pub thread_id: Option<u32>,
+    pub user_id: Option<String>,
+}
+
+impl SystemFields {
+    /// Reads the system fields of a record rendered by `JsonOutput`.
+    pub fn from_json_value(value: &Value) -> SerializationResult<Self> {
+        let system = value
+            .get("Event")
+            .and_then(|event| event.get("System"))
+            .filter(|system| system.is_object())
+            .ok_or_else(|| missing("System"))?;
+
+        let time_created = match attribute(system, "TimeCreated", "SystemTime") {
+            Some(value) => {
+                let s = value
+                    .as_str()
+                    .ok_or_else(|| invalid("TimeCreated", value))?;
+                DateTime::parse_from_rfc3339(s)
+                    .map_err(|_| invalid("TimeCreated", value))?
+                    .with_timezone(&Utc)
+            }
+            None => return Err(missing("TimeCreated")),
+        };
+
+        Ok(SystemFields {
+            event_id: number(system, "EventID")?.ok_or_else(|| missing("EventID"))?,
+            provider: attribute_string(system, "Provider", "Name")?
+                .ok_or_else(|| missing("Provider"))?,
+            computer: string(system, "Computer")?.ok_or_else(|| missing("Computer"))?,
+            time_created,
+            provider_guid: attribute_string(system, "Provider", "Guid")?,
+            channel: string(system, "Channel")?,
+            event_record_id: number(system, "EventRecordID")?,
+            version: number(system, "Version")?,
+            level: number(system, "Level")?,
+            task: number(system, "Task")?,
+            opcode: number(system, "Opcode")?,
+            keywords: string(system, "Keywords")?,
+            process_id: attribute_number(system, "Execution", "ProcessID")?,
+            thread_id: attribute_number(system, "Execution", "ThreadID")?,
+            user_id: attribute_string(system, "Security", "UserID")?,
+        })
+    }
+}
+
+fn missing(field: &str) -> SerializationError {
+    SerializationError::MissingSystemField {
+        field: field.to_owned(),
+    }
+}
+
+fn invalid(field: &str, value: &Value) -> SerializationError {
+    SerializationError::InvalidSystemField {
+        field: field.to_owned(),
+        value: value.to_string(),
+    }
+}
+
+/// The text of an element, which is stored under `#text` when the element also has attributes.
+fn text<'a>(system: &'a Value, element: &str) -> Option<&'a Value> {
+    match system.get(element)? {
+        Value::Object(object) => object.get("#text"),
+        value => Some(value),
+    }
+    .filter(|value| !value.is_null())
+}
+
+fn attribute<'a>(system: &'a Value, element: &str, name: &str) -> Option<&'a Value> {
+    system
+        .get(element)
+        .and_then(|element| element.get("#attributes"))
+        .or_else(|| system.get(format!("{}_attributes", element)))
+        .and_then(|attributes| attributes.get(name))
+        .filter(|value| !value.is_null())
+}
+
+fn to_string(field: &str, value: Option<&Value>) -> SerializationResult<Option<String>> {
+    match value {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(value @ Value::Number(_)) => Ok(Some(value.to_string())),
+        Some(value) => Err(invalid(field, value)),
+    }
+}
+
+fn to_number<T: TryFrom<u64>>(
+    field: &str,
+    value: Option<&Value>,
+) -> SerializationResult<Option<T>> {
+    let value = match value {
+        None => return Ok(None),
+        Some(value) => value,
+    };
+
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        .and_then(|n| T::try_from(n).ok())
+        .map(Some)
+        .ok_or_else(|| invalid(field, value))
+}
+
+fn string(system: &Value, element: &str) -> SerializationResult<Option<String>> {
+    to_string(element, text(system, element))
+}
+
+fn number<T: TryFrom<u64>>(system: &Value, element: &str) -> SerializationResult<Option<T>> {
+    to_number(element, text(system, element))
+}
+
+fn attribute_string(
+    system: &Value,
+    element: &str,
+    name: &str,
+) -> SerializationResult<Option<String>> {
+    let field = format!("{}/@{}", element, name);
+    to_string(&field, attribute(system, element, name))
+}
+
+fn attribute_number<T: TryFrom<u64>>(
+    system: &Value,
+    element: &str,
+    name: &str,
+) -> SerializationResult<Option<T>> {
+    let field = format!("{}/@{}", element, name);
+    to_number(&field, attribute(system, element, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SystemFields;
+    use crate::err::SerializationError;
+    use chrono::{DateTime, NaiveDate, Utc};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_system_fields_from_json() {
+        let value = json!({
+            "Event": {
+                "System": {
+                    "Channel": "Security",
+                    "Computer": "37L4247F27-25",
+                    "Correlation": null,
+                    "EventID": {"#attributes": {"Qualifiers": 16384}, "#text": 4608},
+                    "EventRecordID": 1,
+                    "Execution": {"#attributes": {"ProcessID": 456, "ThreadID": 460}},
+                    "Keywords": "0x8020000000000000",
+                    "Level": 0,
+                    "Opcode": 0,
+                    "Provider": {"#attributes": {"Name": "Microsoft-Windows-Security-Auditing"}},
+                    "Security": null,
+                    "Task": 12288,
+                    "TimeCreated": {"#attributes": {"SystemTime": "2016-07-08T18:12:51.681640Z"}},
+                    "Version": 0
+                }
+            }
+        });
+
+        assert_eq!(
+            SystemFields::from_json_value(&value).unwrap(),
+            SystemFields {
+                event_id: 4608,
+                provider: "Microsoft-Windows-Security-Auditing".to_string(),
+                computer: "37L4247F27-25".to_string(),
+                time_created: DateTime::<Utc>::from_utc(
+                    NaiveDate::from_ymd_opt(2016, 7, 8)
+                        .unwrap()
+                        .and_hms_micro_opt(18, 12, 51, 681640)
+                        .unwrap(),
+                    Utc,
+                ),
+                provider_guid: None,
+                channel: Some("Security".to_string()),
+                event_record_id: Some(1),
+                version: Some(0),
+                level: Some(0),
+                task: Some(12288),
+                opcode: Some(0),
+                keywords: Some("0x8020000000000000".to_string()),
+                process_id: Some(456),
+                thread_id: Some(460),
+                user_id: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_fields_from_separated_json() {
+        let value = json!({
+            "Event": {
+                "System": {
+                    "Computer": "WIN-L6PC55MPB98",
+                    "EventID": 1,
+                    "Provider_attributes": {"Name": "Microsoft-Windows-Sysmon"},
+                    "Security_attributes": {"UserID": "S-1-5-18"},
+                    "TimeCreated_attributes": {"SystemTime": "2017-05-19T02:02:36.203125Z"}
+                }
+            }
+        });
+
+        let fields = SystemFields::from_json_value(&value).unwrap();
+
+        assert_eq!(fields.event_id, 1);
+        assert_eq!(fields.provider, "Microsoft-Windows-Sysmon");
+        assert_eq!(fields.user_id.as_deref(), Some("S-1-5-18"));
+        assert_eq!(fields.channel, None);
+        assert_eq!(fields.process_id, None);
+    }
+
+    #[test]
+    fn test_system_fields_missing_required_field() {
+        let value = json!({"Event": {"System": {"EventID": 1}}});
+
+        assert!(matches!(
+            SystemFields::from_json_value(&value),
+            Err(SerializationError::MissingSystemField { field }) if field == "TimeCreated"
+        ));
+    }
+}
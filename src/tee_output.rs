@@ -0,0 +1,143 @@
+use crate::err::SerializationResult;
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+
+use std::borrow::Cow;
+
+/// Forwards everything it visits to two outputs, so that a record can be rendered in two formats
+/// while its tokens are only parsed once.
+///
+/// ```rust
+/// # use evtx::{EvtxParser, JsonOutput, ParserSettings, TeeOutput, XmlOutput};
+/// # use std::sync::Arc;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let settings = ParserSettings::new();
+/// let mut parser = EvtxParser::from_path(fp).unwrap();
+///
+/// for chunk in parser.chunks() {
+///     let mut chunk = chunk.unwrap();
+///     for record in chunk.parse(Arc::new(settings.clone())).unwrap().iter() {
+///         let mut output = TeeOutput::new(
+///             XmlOutput::with_writer(Vec::new(), &settings),
+///             JsonOutput::new(&settings),
+///         );
+///         record.unwrap().into_output(&mut output).unwrap();
+///
+///         let (xml, json) = output.into_inner();
+///         let (xml, json) = (xml.into_writer().unwrap(), json.into_value().unwrap());
+///         assert!(!xml.is_empty() && json.is_object());
+///     }
+/// }
+/// ```
+///
+/// Visits stop at the first error of either output.
+pub struct TeeOutput<A: BinXmlOutput, B: BinXmlOutput> {
+    first: A,
+    second: B,
+}
+
+impl<A: BinXmlOutput, B: BinXmlOutput> TeeOutput<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        TeeOutput { first, second }
+    }
+
+    /// Returns both outputs, each one is then finished on its own
+    /// (for example with `XmlOutput::into_writer` or `JsonOutput::into_value`).
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: BinXmlOutput, B: BinXmlOutput> BinXmlOutput for TeeOutput<A, B> {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        self.first.visit_end_of_stream()?;
+        self.second.visit_end_of_stream()
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.first.visit_open_start_element(element)?;
+        self.second.visit_open_start_element(element)
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.first.visit_close_element(element)?;
+        self.second.visit_close_element(element)
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.first.visit_characters(Cow::Borrowed(value.as_ref()))?;
+        self.second.visit_characters(value)
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.first
+            .visit_cdata_section(Cow::Borrowed(cdata.as_ref()))?;
+        self.second.visit_cdata_section(cdata)
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.first.visit_entity_reference(entity)?;
+        self.second.visit_entity_reference(entity)
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.first
+            .visit_character_reference(Cow::Borrowed(char_ref.as_ref()))?;
+        self.second.visit_character_reference(char_ref)
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.first.visit_processing_instruction(pi)?;
+        self.second.visit_processing_instruction(pi)
+    }
+
+    /// Each output handles comments with its own `unhandled_policy`.
+    fn visit_comment(&mut self, comment: &str) -> SerializationResult<()> {
+        self.first.visit_comment(comment)?;
+        self.second.visit_comment(comment)
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        self.first.visit_start_of_stream()?;
+        self.second.visit_start_of_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TeeOutput;
+    use crate::{EvtxParser, HashingOutput, JsonOutput, ParserSettings, XmlOutput};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_renders_both_outputs_in_one_pass() {
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let settings = ParserSettings::new();
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected_xml = parser.records().next().unwrap().unwrap().data;
+        let expected_json = parser.records_json_value().next().unwrap().unwrap().data;
+
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(Arc::new(settings.clone())).unwrap();
+        let record = chunk.iter().next().unwrap().unwrap();
+
+        let mut output = TeeOutput::new(
+            XmlOutput::with_writer(Vec::new(), &settings),
+            TeeOutput::new(JsonOutput::new(&settings), HashingOutput::new()),
+        );
+        record.into_output(&mut output).unwrap();
+
+        let (xml, rest) = output.into_inner();
+        let (json, hash) = rest.into_inner();
+        assert_eq!(
+            String::from_utf8(xml.into_writer().unwrap()).unwrap(),
+            expected_xml
+        );
+        assert_eq!(json.into_value().unwrap(), expected_json);
+        assert_ne!(hash.into_writer(), [0; 32]);
+    }
+}
@@ -1,18 +1,148 @@
-use crate::binxml::tokens::read_template_definition;
-use crate::err::DeserializationResult;
+use crate::binxml::assemble::expand_string_ref;
+use crate::binxml::name::{BinXmlName, BinXmlNameRef};
+use crate::binxml::tokens::{read_template_definition, read_template_definition_header};
+use crate::binxml::value_variant::BinXmlValue;
+use crate::err::{DeserializationResult, Result};
+use crate::utils::OnInvalidUtf16;
 
-use crate::model::deserialized::BinXMLTemplateDefinition;
-use crate::ChunkOffset;
+use crate::model::deserialized::{
+    BinXMLDeserializedTokens, BinXMLTemplateDefinition, TemplateSubstitutionDescriptor,
+};
+use crate::{ChunkOffset, EvtxChunk};
 pub use byteorder::{LittleEndian, ReadBytesExt};
 
 use encoding::EncodingRef;
 use log::trace;
 use std::borrow::BorrowMut;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::io::{Cursor, Seek, SeekFrom};
+use std::sync::RwLock;
+use winstructs::guid::Guid;
 
 pub type CachedTemplate<'chunk> = BinXMLTemplateDefinition<'chunk>;
 
+/// The size of a template definition header, which comes before its `data_size` bytes.
+const TEMPLATE_DEFINITION_HEADER_SIZE: usize = 24;
+
+/// The most templates kept by a `SharedTemplateCache`, so that it can't grow without bound.
+const MAX_SHARED_TEMPLATES: usize = 4096;
+
+/// Parsed templates shared between the chunks of a file, see `ParserSettings::template_cache`.
+///
+/// Templates refer to their element names by chunk offset, so a template can only be reused
+/// by another chunk if it is defined at the same offset with the same bytes, and all of it's
+/// names are defined inline (inside of those bytes). This is the common case for logs which
+/// repeat the same events, where every chunk begins with the same templates.
+#[derive(Debug, Default)]
+pub struct SharedTemplateCache(RwLock<HashMap<u64, SharedTemplate>>);
+
+#[derive(Debug)]
+struct SharedTemplate {
+    /// The bytes of the definition, compared on lookup so that hash collisions are harmless.
+    bytes: Vec<u8>,
+    tokens: Vec<BinXMLDeserializedTokens<'static>>,
+}
+
+impl SharedTemplateCache {
+    pub fn new() -> Self {
+        SharedTemplateCache::default()
+    }
+
+    /// The key of a template defined at `offset`, by the `bytes` following its next template offset.
+    fn key(offset: ChunkOffset, bytes: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        offset.hash(&mut hasher);
+        bytes.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    fn get(&self, key: u64, bytes: &[u8]) -> Option<Vec<BinXMLDeserializedTokens<'static>>> {
+        let templates = self.0.read().ok()?;
+
+        templates
+            .get(&key)
+            .filter(|template| template.bytes == bytes)
+            .map(|template| template.tokens.clone())
+    }
+
+    fn insert(&self, key: u64, bytes: &[u8], tokens: Vec<BinXMLDeserializedTokens<'static>>) {
+        if let Ok(mut templates) = self.0.write() {
+            if templates.len() < MAX_SHARED_TEMPLATES {
+                let bytes = bytes.to_vec();
+                templates.insert(key, SharedTemplate { bytes, tokens });
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().map_or(0, |templates| templates.len())
+    }
+}
+
+/// Copies the tokens of a template which was defined in `start..end`, if they don't borrow
+/// from the chunk and all names they refer to are defined inside of the template.
+fn to_shared_tokens(
+    tokens: &[BinXMLDeserializedTokens],
+    start: ChunkOffset,
+    end: usize,
+) -> Option<Vec<BinXMLDeserializedTokens<'static>>> {
+    let is_inline = |name: &BinXmlNameRef| name.offset >= start && (name.offset as usize) < end;
+
+    tokens
+        .iter()
+        .map(|token| match token {
+            BinXMLDeserializedTokens::FragmentHeader(header) => {
+                Some(BinXMLDeserializedTokens::FragmentHeader(header.clone()))
+            }
+            BinXMLDeserializedTokens::OpenStartElement(element) if is_inline(&element.name) => {
+                Some(BinXMLDeserializedTokens::OpenStartElement(element.clone()))
+            }
+            BinXMLDeserializedTokens::AttributeList => {
+                Some(BinXMLDeserializedTokens::AttributeList)
+            }
+            BinXMLDeserializedTokens::Attribute(attribute) if is_inline(&attribute.name) => {
+                Some(BinXMLDeserializedTokens::Attribute(attribute.clone()))
+            }
+            BinXMLDeserializedTokens::CloseStartElement => {
+                Some(BinXMLDeserializedTokens::CloseStartElement)
+            }
+            BinXMLDeserializedTokens::CloseEmptyElement => {
+                Some(BinXMLDeserializedTokens::CloseEmptyElement)
+            }
+            BinXMLDeserializedTokens::CloseElement => Some(BinXMLDeserializedTokens::CloseElement),
+            BinXMLDeserializedTokens::Value(BinXmlValue::StringType(value)) => Some(
+                BinXMLDeserializedTokens::Value(BinXmlValue::StringType(value.clone())),
+            ),
+            BinXMLDeserializedTokens::CDATASection(data) => {
+                Some(BinXMLDeserializedTokens::CDATASection(data.clone()))
+            }
+            BinXMLDeserializedTokens::CharRef(value) => {
+                Some(BinXMLDeserializedTokens::CharRef(*value))
+            }
+            BinXMLDeserializedTokens::EntityRef(entity) if is_inline(&entity.name) => {
+                Some(BinXMLDeserializedTokens::EntityRef(entity.clone()))
+            }
+            BinXMLDeserializedTokens::PITarget(target) if is_inline(&target.name) => {
+                Some(BinXMLDeserializedTokens::PITarget(target.clone()))
+            }
+            BinXMLDeserializedTokens::PIData(data) => {
+                Some(BinXMLDeserializedTokens::PIData(data.clone()))
+            }
+            BinXMLDeserializedTokens::Substitution(substitution) => {
+                Some(BinXMLDeserializedTokens::Substitution(substitution.clone()))
+            }
+            BinXMLDeserializedTokens::EndOfStream => Some(BinXMLDeserializedTokens::EndOfStream),
+            BinXMLDeserializedTokens::StartOfStream => {
+                Some(BinXMLDeserializedTokens::StartOfStream)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 #[derive(Debug, Default)]
 pub struct TemplateCache<'chunk>(HashMap<ChunkOffset, CachedTemplate<'chunk>>);
 
@@ -21,10 +151,13 @@ impl<'chunk> TemplateCache<'chunk> {
         TemplateCache(HashMap::new())
     }
 
+    /// Parses the templates of a chunk, reusing the ones parsed by other chunks from `shared`.
     pub fn populate(
         data: &'chunk [u8],
         offsets: &[ChunkOffset],
         ansi_codec: EncodingRef,
+        on_invalid_utf16: OnInvalidUtf16,
+        shared: Option<&SharedTemplateCache>,
     ) -> DeserializationResult<Self> {
         let mut cache = HashMap::new();
         let mut cursor = Cursor::new(data);
@@ -35,7 +168,43 @@ impl<'chunk> TemplateCache<'chunk> {
 
             loop {
                 let table_offset = cursor_ref.position() as ChunkOffset;
-                let definition = read_template_definition(cursor_ref, None, ansi_codec)?;
+
+                let definition = match shared {
+                    Some(shared) => {
+                        let header = read_template_definition_header(cursor_ref)?;
+                        let end = (table_offset as usize
+                            + TEMPLATE_DEFINITION_HEADER_SIZE
+                            + header.data_size as usize)
+                            .min(data.len());
+                        // The next template offset (the first 4 bytes) is not part of the template.
+                        let bytes = &data[table_offset as usize + 4..end];
+                        let key = SharedTemplateCache::key(table_offset, bytes);
+
+                        match shared.get(key, bytes) {
+                            Some(tokens) => BinXMLTemplateDefinition { header, tokens },
+                            None => {
+                                try_seek!(cursor_ref, table_offset, "template")?;
+                                let definition = read_template_definition(
+                                    cursor_ref,
+                                    None,
+                                    ansi_codec,
+                                    on_invalid_utf16,
+                                )?;
+
+                                if let Some(tokens) =
+                                    to_shared_tokens(&definition.tokens, table_offset, end)
+                                {
+                                    shared.insert(key, bytes, tokens);
+                                }
+
+                                definition
+                            }
+                        }
+                    }
+                    None => {
+                        read_template_definition(cursor_ref, None, ansi_codec, on_invalid_utf16)?
+                    }
+                };
                 let next_template_offset = definition.header.next_template_offset;
 
                 cache.insert(table_offset, definition);
@@ -57,7 +226,83 @@ impl<'chunk> TemplateCache<'chunk> {
         self.0.get(&offset)
     }
 
+    /// The templates and their offsets, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkOffset, &CachedTemplate<'chunk>)> {
+        self.0.iter().map(|(offset, template)| (*offset, template))
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
 }
+
+/// An element of a template, see `TemplateDescriptor::elements`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateElement {
+    /// How deeply the element is nested, `0` for the root element of the template.
+    pub depth: usize,
+    pub name: BinXmlName,
+    pub attributes: Vec<BinXmlName>,
+}
+
+/// The structure of a template defined in a chunk, see `EvtxChunk::templates`.
+#[derive(Debug, Clone)]
+pub struct TemplateDescriptor<'a> {
+    /// The chunk offset of the definition, which is how template instances refer to it.
+    pub offset: ChunkOffset,
+    pub guid: Guid,
+    /// The elements of the template, in document order.
+    pub elements: Vec<TemplateElement>,
+    /// The substitution slots, in the order in which they appear in the template.
+    pub substitutions: Vec<TemplateSubstitutionDescriptor>,
+    /// The raw tokens of the template.
+    pub tokens: &'a [BinXMLDeserializedTokens<'a>],
+}
+
+impl<'a> TemplateDescriptor<'a> {
+    /// Describes the template defined at `offset`, resolving element names from the chunk.
+    pub(crate) fn new(
+        offset: ChunkOffset,
+        template: &'a CachedTemplate<'a>,
+        chunk: &'a EvtxChunk<'a>,
+    ) -> Result<Self> {
+        let mut elements: Vec<TemplateElement> = vec![];
+        let mut substitutions = vec![];
+        let mut depth = 0;
+
+        for token in template.tokens.iter() {
+            match token {
+                BinXMLDeserializedTokens::OpenStartElement(element) => {
+                    elements.push(TemplateElement {
+                        depth,
+                        name: expand_string_ref(&element.name, chunk)?.into_owned(),
+                        attributes: vec![],
+                    });
+                    depth += 1;
+                }
+                BinXMLDeserializedTokens::Attribute(attribute) => {
+                    if let Some(element) = elements.last_mut() {
+                        let name = expand_string_ref(&attribute.name, chunk)?.into_owned();
+                        element.attributes.push(name);
+                    }
+                }
+                BinXMLDeserializedTokens::CloseEmptyElement
+                | BinXMLDeserializedTokens::CloseElement => {
+                    depth = depth.saturating_sub(1);
+                }
+                BinXMLDeserializedTokens::Substitution(substitution) => {
+                    substitutions.push(substitution.clone());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(TemplateDescriptor {
+            offset,
+            guid: template.header.guid.clone(),
+            elements,
+            substitutions,
+            tokens: &template.tokens,
+        })
+    }
+}
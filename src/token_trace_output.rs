@@ -0,0 +1,173 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+use crate::ParserSettings;
+
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Writes a human readable listing of the visited tokens, one per line, for debugging records.
+///
+/// Lines are indented by the depth of the element they belong to, and values are listed with
+/// their BinXml type:
+///
+/// ```text
+/// StartOfStream
+/// OpenStartElement Event
+///   Attribute xmlns = "http://schemas.microsoft.com/win/2004/08/events/event" (StringType)
+///   OpenStartElement System
+///     OpenStartElement EventID
+///       Characters "4624" (UInt16Type)
+///     CloseElement EventID
+/// ...
+/// EndOfStream
+/// ```
+///
+/// Templates are expanded before tokens are visited, so substitutions are listed as the values
+/// which were substituted, see `EvtxRecord::substitution_values` for the raw substitution array.
+pub struct TokenTraceOutput<W: Write> {
+    writer: W,
+    depth: usize,
+    eof_reached: bool,
+}
+
+impl<W: Write> TokenTraceOutput<W> {
+    pub fn with_writer(target: W, _settings: &ParserSettings) -> Self {
+        TokenTraceOutput {
+            writer: target,
+            depth: 0,
+            eof_reached: false,
+        }
+    }
+
+    /// Returns the writer, fails if the end of the stream was not reached yet.
+    pub fn into_writer(self) -> SerializationResult<W> {
+        if self.eof_reached {
+            Ok(self.writer)
+        } else {
+            Err(SerializationError::IncompleteOutput)
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> SerializationResult<()> {
+        writeln!(
+            self.writer,
+            "{:indent$}{}",
+            "",
+            line,
+            indent = self.depth * 2
+        )
+        .map_err(quick_xml::Error::Io)?;
+
+        Ok(())
+    }
+}
+
+fn describe_value(value: &BinXmlValue) -> String {
+    format!("{:?} ({:?})", value.as_cow_str(), value.value_type())
+}
+
+impl<W: Write> BinXmlOutput for TokenTraceOutput<W> {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        self.write_line("EndOfStream")?;
+        self.eof_reached = true;
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.write_line(&format!("OpenStartElement {}", element.name.as_str()))?;
+        self.depth += 1;
+
+        for attribute in element.attributes.iter() {
+            self.write_line(&format!(
+                "Attribute {} = {}",
+                attribute.name.as_str(),
+                describe_value(&attribute.value)
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.depth = self.depth.saturating_sub(1);
+        self.write_line(&format!("CloseElement {}", element.name.as_str()))
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.write_line(&format!("Characters {}", describe_value(&value)))
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.write_line(&format!("CDataSection {:?}", cdata))
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.write_line(&format!("EntityReference {}", entity.as_str()))
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.write_line(&format!("CharacterReference {}", char_ref))
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.write_line(&format!(
+            "ProcessingInstruction {} {:?}",
+            pi.name.as_str(),
+            pi.data
+        ))
+    }
+
+    fn visit_comment(&mut self, comment: &str) -> SerializationResult<()> {
+        self.write_line(&format!("Comment {:?}", comment))
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        self.depth = 0;
+        self.eof_reached = false;
+        self.write_line("StartOfStream")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenTraceOutput;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::model::xml::test_helpers::{element, typed_element};
+    use crate::{BinXmlOutput, ParserSettings};
+    use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_lists_tokens_by_depth() {
+        let event = element("Event", &[]);
+        let event_id = typed_element("EventID", &[("Qualifiers", BinXmlValue::NullType)]);
+
+        let mut output = TokenTraceOutput::with_writer(vec![], &ParserSettings::new());
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event_id).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::UInt16Type(4624)))
+            .unwrap();
+        output.visit_close_element(&event_id).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        let expected = [
+            "StartOfStream",
+            "OpenStartElement Event",
+            "  OpenStartElement EventID",
+            "    Attribute Qualifiers = \"\" (NullType)",
+            "    Characters \"4624\" (UInt16Type)",
+            "  CloseElement EventID",
+            "CloseElement Event",
+            "EndOfStream",
+        ];
+        let trace = String::from_utf8(output.into_writer().unwrap()).unwrap();
+        assert_eq!(trace.lines().collect::<Vec<_>>(), expected);
+    }
+}
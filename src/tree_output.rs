@@ -0,0 +1,291 @@
+use crate::err::{SerializationError, SerializationResult};
+
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::json_output::{expand_character_reference, expand_entity_reference};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+
+use log::trace;
+use std::borrow::Cow;
+
+/// An element of a record collected by `TreeOutput`, with its attributes and children.
+///
+/// Values are kept as the text they have in XML, so the tree does not borrow from the chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<TreeNode>,
+}
+
+/// A child of a `TreeElement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeNode {
+    Element(TreeElement),
+    /// Adjacent text, CDATA sections, entity and character references are joined in a single node.
+    Text(String),
+    ProcessingInstruction {
+        name: String,
+        data: String,
+    },
+}
+
+impl TreeElement {
+    fn new(element: &XmlElement) -> Self {
+        TreeElement {
+            name: element.name.as_str().to_owned(),
+            attributes: element
+                .attributes
+                .iter()
+                .map(|attribute| {
+                    (
+                        attribute.name.as_str().to_owned(),
+                        attribute.value.as_cow_str().into_owned(),
+                    )
+                })
+                .collect(),
+            children: vec![],
+        }
+    }
+
+    /// The value of the attribute called `name`, if the element has one.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The child elements, in order.
+    pub fn elements(&self) -> impl Iterator<Item = &TreeElement> {
+        self.children.iter().filter_map(|child| match child {
+            TreeNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// The first child element called `name`.
+    pub fn element(&self, name: &str) -> Option<&TreeElement> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// The text directly held by the element, not including the text of its children.
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|child| match child {
+                TreeNode::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn push_text(&mut self, text: &str) {
+        match self.children.last_mut() {
+            Some(TreeNode::Text(previous)) => previous.push_str(text),
+            _ => self.children.push(TreeNode::Text(text.to_owned())),
+        }
+    }
+}
+
+/// Collects a record into a tree of `TreeElement`, to walk it with plain Rust code
+/// instead of implementing `BinXmlOutput`.
+///
+/// ```rust
+/// # use evtx::EvtxParser;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let mut parser = EvtxParser::from_path(fp).unwrap();
+///
+/// for record in parser.records_tree() {
+///     let event = record.unwrap().data;
+///     let system = event.element("System").unwrap();
+///
+///     assert!(system.element("EventID").unwrap().text().parse::<u32>().is_ok());
+///     assert!(system.element("Provider").unwrap().attribute("Name").is_some());
+/// }
+/// ```
+///
+/// Text outside of the root element is ignored.
+#[derive(Debug, Default)]
+pub struct TreeOutput {
+    stack: Vec<TreeElement>,
+    root: Option<TreeElement>,
+    closed_elements: usize,
+    eof_reached: bool,
+}
+
+impl TreeOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the root element, fails if the end of the stream was not reached yet.
+    pub fn into_root(self) -> SerializationResult<TreeElement> {
+        match self.root {
+            Some(root) if self.eof_reached => Ok(root),
+            _ => Err(SerializationError::IncompleteOutput),
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(element) = self.stack.last_mut() {
+            element.push_text(text);
+        }
+    }
+}
+
+impl BinXmlOutput for TreeOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_end_of_stream");
+        self.eof_reached = true;
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        trace!("visit_open_start_element: {:?}", element.name);
+        self.stack.push(TreeElement::new(element));
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        let element = self
+            .stack
+            .pop()
+            .ok_or(SerializationError::UnbalancedStack {
+                closed_elements: self.closed_elements,
+            })?;
+        self.closed_elements += 1;
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(TreeNode::Element(element)),
+            None => self.root = Some(element),
+        }
+
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
+        self.push_text(&value.as_cow_str());
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.push_text(&cdata);
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.push_text(expand_entity_reference(entity)?);
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        let c = expand_character_reference(&char_ref)?;
+        self.push_text(c.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        if let Some(element) = self.stack.last_mut() {
+            element.children.push(TreeNode::ProcessingInstruction {
+                name: pi.name.as_str().to_owned(),
+                data: pi.data.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        trace!("visit_start_of_stream");
+        *self = TreeOutput::new();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TreeElement, TreeNode, TreeOutput};
+    use crate::binxml::name::BinXmlName;
+    use crate::binxml::value_variant::BinXmlValue;
+    use crate::model::xml::test_helpers::element;
+    use crate::BinXmlOutput;
+    use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_collects_elements_into_a_tree() {
+        let event = element("Event", &[]);
+        let event_data = element("EventData", &[]);
+        let user = element("Data", &[("Name", "TargetUserName")]);
+        let logon_type = element("Data", &[("Name", "LogonType")]);
+
+        let mut output = TreeOutput::new();
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event_data).unwrap();
+        output.visit_open_start_element(&user).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::StringType("a".to_string())))
+            .unwrap();
+        output
+            .visit_entity_reference(&BinXmlName::from_str("amp"))
+            .unwrap();
+        output.visit_cdata_section(Cow::Borrowed("b")).unwrap();
+        output.visit_close_element(&user).unwrap();
+        output.visit_open_start_element(&logon_type).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::UInt32Type(3)))
+            .unwrap();
+        output.visit_close_element(&logon_type).unwrap();
+        output.visit_close_element(&event_data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        let data = |name: &str, text: &str| {
+            TreeNode::Element(TreeElement {
+                name: "Data".to_owned(),
+                attributes: vec![("Name".to_owned(), name.to_owned())],
+                children: vec![TreeNode::Text(text.to_owned())],
+            })
+        };
+        let root = output.into_root().unwrap();
+        assert_eq!(
+            root,
+            TreeElement {
+                name: "Event".to_owned(),
+                attributes: vec![],
+                children: vec![TreeNode::Element(TreeElement {
+                    name: "EventData".to_owned(),
+                    attributes: vec![],
+                    children: vec![data("TargetUserName", "a&b"), data("LogonType", "3")],
+                })],
+            }
+        );
+
+        let fields: Vec<_> = root
+            .element("EventData")
+            .unwrap()
+            .elements()
+            .map(|data| (data.attribute("Name").unwrap(), data.text()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("TargetUserName", "a&b".to_owned()),
+                ("LogonType", "3".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_into_root_requires_eof() {
+        let event = element("Event", &[]);
+
+        let mut output = TreeOutput::new();
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_close_element(&event).unwrap();
+        assert!(output.into_root().is_err());
+    }
+}
@@ -7,10 +7,22 @@ use byteorder::{LittleEndian, ReadBytesExt};
 
 use encoding::{decode, DecoderTrap, EncodingRef};
 use log::trace;
-use std::char::decode_utf16;
+use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
 use std::error::Error as StdErr;
 use std::io::{self, Error, ErrorKind};
 
+/// What to do with invalid UTF-16 (unpaired surrogates) in names and string values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnInvalidUtf16 {
+    /// Fail the token (and so the record, or the chunk for names in the string table).
+    #[default]
+    Error,
+    /// Substitute each invalid code unit with `U+FFFD`.
+    Replace,
+    /// Drop invalid code units and keep the rest of the string.
+    Lossy,
+}
+
 #[derive(Debug, Error)]
 pub enum FailedToReadString {
     #[error("An I/O error has occurred")]
@@ -20,6 +32,7 @@ pub enum FailedToReadString {
 pub fn read_len_prefixed_utf16_string<T: ReadSeek>(
     stream: &mut T,
     is_null_terminated: bool,
+    on_invalid_utf16: OnInvalidUtf16,
 ) -> Result<Option<String>, FailedToReadString> {
     let expected_number_of_characters = stream.read_u16::<LittleEndian>()?;
     let needed_bytes = u64::from(expected_number_of_characters * 2);
@@ -35,7 +48,7 @@ pub fn read_len_prefixed_utf16_string<T: ReadSeek>(
         len = expected_number_of_characters
     );
 
-    let s = read_utf16_by_size(stream, needed_bytes)?;
+    let s = read_utf16_by_size(stream, needed_bytes, on_invalid_utf16)?;
 
     if is_null_terminated {
         stream.read_u16::<LittleEndian>()?;
@@ -50,10 +63,14 @@ pub fn read_len_prefixed_utf16_string<T: ReadSeek>(
 
 /// Reads a utf16 string from the given stream.
 /// size is the actual byte representation of the string (not the number of characters).
-pub fn read_utf16_by_size<T: ReadSeek>(stream: &mut T, size: u64) -> io::Result<Option<String>> {
+pub fn read_utf16_by_size<T: ReadSeek>(
+    stream: &mut T,
+    size: u64,
+    on_invalid_utf16: OnInvalidUtf16,
+) -> io::Result<Option<String>> {
     match size {
         0 => Ok(None),
-        _ => read_utf16_string(stream, Some(size as usize / 2)).map(|mut s| {
+        _ => read_utf16_string(stream, Some(size as usize / 2), on_invalid_utf16).map(|mut s| {
             // Strip nul terminator if needed
             if let Some('\0') = s.chars().last() {
                 s.pop();
@@ -96,14 +113,22 @@ pub fn read_ansi_encoded_string<T: ReadSeek>(
     }
 }
 
-pub fn read_null_terminated_utf16_string<T: ReadSeek>(stream: &mut T) -> io::Result<String> {
-    read_utf16_string(stream, None)
+pub fn read_null_terminated_utf16_string<T: ReadSeek>(
+    stream: &mut T,
+    on_invalid_utf16: OnInvalidUtf16,
+) -> io::Result<String> {
+    read_utf16_string(stream, None, on_invalid_utf16)
 }
 
 /// Reads a utf16 string from the given stream.
 /// If `len` is given, exactly `len` u16 values are read from the stream.
 /// If `len` is None, the string is assumed to be null terminated and the stream will be read to the first null (0).
-fn read_utf16_string<T: ReadSeek>(stream: &mut T, len: Option<usize>) -> io::Result<String> {
+/// Invalid UTF-16 is handled according to `on_invalid_utf16`.
+fn read_utf16_string<T: ReadSeek>(
+    stream: &mut T,
+    len: Option<usize>,
+    on_invalid_utf16: OnInvalidUtf16,
+) -> io::Result<String> {
     let mut buffer = match len {
         Some(len) => Vec::with_capacity(len),
         None => Vec::new(),
@@ -128,7 +153,13 @@ fn read_utf16_string<T: ReadSeek>(stream: &mut T, len: Option<usize>) -> io::Res
     }
 
     // We need to stop if we see a NUL byte, even if asked for more bytes.
-    decode_utf16(buffer.into_iter().take_while(|&byte| byte != 0x00))
-        .map(|r| r.map_err(|_e| Error::from(ErrorKind::InvalidData)))
-        .collect()
+    let chars = decode_utf16(buffer.into_iter().take_while(|&byte| byte != 0x00));
+
+    match on_invalid_utf16 {
+        OnInvalidUtf16::Error => chars
+            .map(|r| r.map_err(|_e| Error::from(ErrorKind::InvalidData)))
+            .collect(),
+        OnInvalidUtf16::Replace => Ok(chars.map(|r| r.unwrap_or(REPLACEMENT_CHARACTER)).collect()),
+        OnInvalidUtf16::Lossy => Ok(chars.filter_map(|r| r.ok()).collect()),
+    }
 }
@@ -0,0 +1,49 @@
+use crate::err::SerializationError;
+use std::io::{self, Write};
+
+/// A writer which fails once more than `limit` bytes were written to it, since it was last reset.
+pub struct LimitedWriter<W: Write> {
+    inner: W,
+    limit: Option<usize>,
+    written: usize,
+}
+
+impl<W: Write> LimitedWriter<W> {
+    pub fn new(inner: W, limit: Option<usize>) -> Self {
+        LimitedWriter {
+            inner,
+            limit,
+            written: 0,
+        }
+    }
+
+    /// Starts counting from zero again, for example at the start of a new record.
+    pub fn reset(&mut self) {
+        self.written = 0;
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(limit) = self.limit {
+            if self.written + buf.len() > limit {
+                return Err(io::Error::other(SerializationError::OutputTooLarge {
+                    max_output_bytes: limit,
+                }));
+            }
+        }
+
+        let written = self.inner.write(buf)?;
+        self.written += written;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -1,10 +1,12 @@
 mod binxml_utils;
 pub(super) mod hexdump;
+mod limited_writer;
 mod time;
 
 pub use self::binxml_utils::{
     read_ansi_encoded_string, read_len_prefixed_utf16_string, read_null_terminated_utf16_string,
-    read_utf16_by_size,
+    read_utf16_by_size, OnInvalidUtf16,
 };
 pub use self::hexdump::{dump_stream, hexdump};
+pub use self::limited_writer::LimitedWriter;
 pub use self::time::read_systemtime;
@@ -1,18 +1,47 @@
-use crate::binxml::value_variant::BinXmlValue;
+use crate::binxml::value_variant::{BinXmlValue, BinaryEncoding};
 use crate::err::{SerializationError, SerializationResult};
 use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::utils::LimitedWriter;
 use crate::ParserSettings;
 
 use log::trace;
 use std::io::Write;
 
+use quick_xml::escape::escape;
 use quick_xml::events::attributes::Attribute;
-use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::events::{BytesCData, BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::name::QName;
 use quick_xml::Writer;
 
 use crate::binxml::name::BinXmlName;
 use std::borrow::Cow;
 
+/// What an output does with tokens which it does not render, such as processing instructions
+/// in `CsvOutput`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnUnhandled {
+    /// Panics, to catch unexpected tokens while testing.
+    Panic,
+    /// Silently drops the token.
+    #[default]
+    Skip,
+    /// Fails the record with `SerializationError::Unimplemented`.
+    Error,
+}
+
+impl OnUnhandled {
+    /// Applies the policy to an unhandled `token`, which is described in errors.
+    pub fn handle(self, token: &str) -> SerializationResult<()> {
+        match self {
+            OnUnhandled::Panic => panic!("{} is not handled by this output", token),
+            OnUnhandled::Skip => Ok(()),
+            OnUnhandled::Error => Err(SerializationError::Unimplemented {
+                message: format!("{} is not handled by this output", token),
+            }),
+        }
+    }
+}
+
 pub trait BinXmlOutput {
     /// Called once when EOF is reached.
     fn visit_end_of_stream(&mut self) -> SerializationResult<()>;
@@ -31,8 +60,9 @@ pub trait BinXmlOutput {
     ///                                                     ~~~~~~~~~~~~~~~
     fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()>;
 
-    /// Unimplemented
-    fn visit_cdata_section(&mut self) -> SerializationResult<()>;
+    /// Called with the contents of a CDATA section, (ex. <Data><![CDATA[<inner/>]]></Data>)
+    ///                                                                   ~~~~~~~~
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()>;
 
     /// Emit the character "&" and the text.
     fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()>;
@@ -40,30 +70,372 @@ pub trait BinXmlOutput {
     /// Emit the characters "&" and "#" and the decimal string representation of the value.
     fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()>;
 
-    /// Unimplemented
+    /// Called on a processing instruction, (ex. <?xml-stylesheet href="event.xsl"?>)
     fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()>;
 
+    /// Called with the text of a comment, (ex. <!-- rendered by wevtutil -->)
+    ///                                             ~~~~~~~~~~~~~~~~~~~~
+    /// BinXml has no comment token, so records never contain comments, but an output can also
+    /// be driven by other XML sources. Comments are unhandled by default.
+    fn visit_comment(&mut self, _comment: &str) -> SerializationResult<()> {
+        self.unhandled_policy().handle("comment")
+    }
+
+    /// What to do with tokens which the output does not render, `Skip` by default.
+    fn unhandled_policy(&self) -> OnUnhandled {
+        OnUnhandled::Skip
+    }
+
     /// Called once on beginning of parsing.
     fn visit_start_of_stream(&mut self) -> SerializationResult<()>;
 }
 
+/// Entities which are predefined by the XML specification.
+pub(crate) const PREDEFINED_ENTITIES: [&str; 5] = ["amp", "lt", "gt", "quot", "apos"];
+
+/// The overall layout of records written by `XmlOutput`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XmlOutputStyle {
+    /// Records are indented according to `indent_char` and `indent_size`.
+    #[default]
+    Pretty,
+    /// Every record is written on a single line, terminated by `\n`, for line-oriented tools.
+    /// Line breaks inside of text and attribute values are written as `&#10;` and `&#13;`,
+    /// except inside of CDATA sections which cannot contain references.
+    SingleLine,
+}
+
+/// Formatting options for `XmlOutput`.
+///
+/// Lines are always separated with `\n`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlOutputOptions {
+    /// The character used for indentation.
+    indent_char: u8,
+    /// How many `indent_char` are used per level, `0` disables indentation (and line breaks).
+    indent_size: usize,
+    /// If true, every record starts with an `<?xml ...?>` declaration.
+    emit_declaration: bool,
+    /// If true, the `xmlns` attribute of the root element (`<Event>`) is written.
+    emit_root_namespace: bool,
+    /// If true, elements with many attributes are written with one attribute per line.
+    attributes_on_separate_lines: bool,
+    /// Elements with more than this many attributes have them written on separate lines.
+    attributes_per_line_threshold: usize,
+    /// If true, elements without content are written as `<Foo/>` instead of `<Foo></Foo>`.
+    self_closing_empty_elements: bool,
+    /// Binary values longer than this many bytes are truncated, unlimited by default.
+    binary_byte_limit: Option<usize>,
+    /// How binary values are written, hex by default.
+    binary_encoding: BinaryEncoding,
+    /// Pretty (indented) by default.
+    style: XmlOutputStyle,
+    /// The most bytes written for a single record, unlimited by default.
+    max_output_bytes: Option<usize>,
+    /// The name of an element wrapping all of the records, if set.
+    root_element: Option<String>,
+}
+
+impl Default for XmlOutputOptions {
+    fn default() -> Self {
+        XmlOutputOptions {
+            indent_char: b' ',
+            indent_size: 2,
+            emit_declaration: true,
+            emit_root_namespace: true,
+            attributes_on_separate_lines: false,
+            attributes_per_line_threshold: 2,
+            self_closing_empty_elements: false,
+            binary_byte_limit: None,
+            binary_encoding: BinaryEncoding::default(),
+            style: XmlOutputStyle::default(),
+            max_output_bytes: None,
+            root_element: None,
+        }
+    }
+}
+
+impl From<&ParserSettings> for XmlOutputOptions {
+    fn from(settings: &ParserSettings) -> Self {
+        let mut options =
+            XmlOutputOptions::default().emit_declaration(settings.should_emit_xml_declaration());
+        options.binary_byte_limit = settings.get_binary_byte_limit();
+        options.binary_encoding = settings.get_binary_encoding();
+        options.max_output_bytes = settings.get_max_output_bytes();
+
+        if settings.should_indent() {
+            options
+        } else {
+            options.indent_size(0)
+        }
+    }
+}
+
+impl XmlOutputOptions {
+    pub fn new() -> Self {
+        XmlOutputOptions::default()
+    }
+
+    pub fn indent_char(mut self, indent_char: u8) -> Self {
+        self.indent_char = indent_char;
+
+        self
+    }
+
+    /// Sets the indentation width, `0` will write each record on a single line.
+    pub fn indent_size(mut self, indent_size: usize) -> Self {
+        self.indent_size = indent_size;
+
+        self
+    }
+
+    pub fn emit_declaration(mut self, emit_declaration: bool) -> Self {
+        self.emit_declaration = emit_declaration;
+
+        self
+    }
+
+    /// Drops the `xmlns` attribute of the root element, to embed records in another document
+    /// which declares the namespace itself. Other attributes are written as usual.
+    pub fn emit_root_namespace(mut self, emit_root_namespace: bool) -> Self {
+        self.emit_root_namespace = emit_root_namespace;
+
+        self
+    }
+
+    /// Writes each attribute on its own line, indented one level deeper than its element,
+    /// for elements with more than `attributes_per_line_threshold` attributes.
+    pub fn attributes_on_separate_lines(mut self, attributes_on_separate_lines: bool) -> Self {
+        self.attributes_on_separate_lines = attributes_on_separate_lines;
+
+        self
+    }
+
+    /// Sets how many attributes an element may have before they are wrapped, `2` by default.
+    pub fn attributes_per_line_threshold(mut self, threshold: usize) -> Self {
+        self.attributes_per_line_threshold = threshold;
+
+        self
+    }
+
+    /// Writes elements without any content as `<Foo/>`, instead of `<Foo></Foo>`.
+    ///
+    /// Both forms are equivalent XML, so parsing the output yields the same document either way,
+    /// but the output is no longer byte-for-byte comparable with output written without it.
+    /// Elements with an empty text value are still written as `<Foo></Foo>`.
+    pub fn self_closing_empty_elements(mut self, self_closing_empty_elements: bool) -> Self {
+        self.self_closing_empty_elements = self_closing_empty_elements;
+
+        self
+    }
+
+    /// Writes only the first `limit` bytes of binary values, followed by `...`.
+    pub fn binary_byte_limit(mut self, limit: usize) -> Self {
+        self.binary_byte_limit = Some(limit);
+
+        self
+    }
+
+    /// Writes binary values as `encoding`, instead of hex.
+    pub fn binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+
+        self
+    }
+
+    /// Sets the layout of records, `SingleLine` ignores the indentation and attribute wrapping settings.
+    pub fn style(mut self, style: XmlOutputStyle) -> Self {
+        self.style = style;
+
+        self
+    }
+
+    /// Fails records which are larger than `max_output_bytes` once written.
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_output_bytes);
+
+        self
+    }
+
+    /// Wraps all of the records in a `<root_element>` element, which is opened before the first
+    /// record and closed by `XmlOutput::into_writer`, so that many records form a single document.
+    /// The declaration (if any) is only written once, before the root element.
+    pub fn root_element(mut self, root_element: impl Into<String>) -> Self {
+        self.root_element = Some(root_element.into());
+
+        self
+    }
+
+    pub fn get_indent_char(&self) -> u8 {
+        self.indent_char
+    }
+
+    pub fn get_indent_size(&self) -> usize {
+        self.indent_size
+    }
+
+    pub fn should_emit_declaration(&self) -> bool {
+        self.emit_declaration
+    }
+
+    pub fn should_emit_root_namespace(&self) -> bool {
+        self.emit_root_namespace
+    }
+
+    pub fn should_write_attributes_on_separate_lines(&self) -> bool {
+        self.attributes_on_separate_lines
+    }
+
+    pub fn get_attributes_per_line_threshold(&self) -> usize {
+        self.attributes_per_line_threshold
+    }
+
+    pub fn should_write_self_closing_empty_elements(&self) -> bool {
+        self.self_closing_empty_elements
+    }
+
+    pub fn get_binary_byte_limit(&self) -> Option<usize> {
+        self.binary_byte_limit
+    }
+
+    pub fn get_binary_encoding(&self) -> BinaryEncoding {
+        self.binary_encoding
+    }
+
+    pub fn get_style(&self) -> XmlOutputStyle {
+        self.style
+    }
+
+    pub fn get_max_output_bytes(&self) -> Option<usize> {
+        self.max_output_bytes
+    }
+
+    pub fn get_root_element(&self) -> Option<&str> {
+        self.root_element.as_deref()
+    }
+}
+
 pub struct XmlOutput<W: Write> {
-    writer: Writer<W>,
+    writer: Writer<LimitedWriter<W>>,
+    options: XmlOutputOptions,
+    /// Depth of the next start element, used to indent wrapped attributes.
+    depth: usize,
+    /// A start element which is held back until we know if the element is empty,
+    /// only used with `self_closing_empty_elements`.
+    pending_start: Option<BytesStart<'static>>,
+    /// Whether the `root_element` was opened.
+    root_opened: bool,
 }
 
 impl<W: Write> XmlOutput<W> {
+    /// Writes records as configured by the parser settings, see `XmlOutputOptions::from`.
     pub fn with_writer(target: W, settings: &ParserSettings) -> Self {
-        let writer = if settings.should_indent() {
-            Writer::new_with_indent(target, b' ', 2)
+        Self::with_config(target, XmlOutputOptions::from(settings))
+    }
+
+    /// Writes records with fully built `options`, which are used as is.
+    pub fn with_config(target: W, options: XmlOutputOptions) -> Self {
+        let target = LimitedWriter::new(target, options.max_output_bytes);
+        let writer = if options.style == XmlOutputStyle::Pretty && options.indent_size > 0 {
+            Writer::new_with_indent(target, options.indent_char, options.indent_size)
         } else {
             Writer::new(target)
         };
 
-        XmlOutput { writer }
+        XmlOutput {
+            writer,
+            options,
+            depth: 0,
+            pending_start: None,
+            root_opened: false,
+        }
+    }
+
+    fn write_declaration(&mut self) -> SerializationResult<()> {
+        let event = BytesDecl::new("1.0", Some("utf-8"), None);
+        self.writer.write_event(Event::Decl(event))?;
+
+        Ok(())
+    }
+
+    /// Writes the declaration and opens the `root_element`, unless it is already open.
+    fn open_root_element(&mut self) -> SerializationResult<()> {
+        if self.root_opened {
+            return Ok(());
+        }
+
+        if let Some(root_element) = self.options.root_element.clone() {
+            if self.options.emit_declaration {
+                self.write_declaration()?;
+            }
+            self.writer
+                .write_event(Event::Start(BytesStart::new(root_element.as_str())))?;
+            self.root_opened = true;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the held back start element, once the element turned out to have content.
+    fn flush_pending_start(&mut self) -> SerializationResult<()> {
+        if let Some(start) = self.pending_start.take() {
+            self.writer.write_event(Event::Start(start))?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds a start element with every attribute on its own line:
+    /// ```text
+    /// <Provider
+    ///   Name="Microsoft-Windows-Security-Auditing"
+    ///   Guid="{54849625-5478-4994-a5ba-3e3b0328c30d}">
+    /// ```
+    fn start_with_attributes_on_separate_lines<'a>(
+        &self,
+        name: &str,
+        attributes: &[(&str, Cow<'a, str>)],
+    ) -> BytesStart<'a> {
+        let indent = (self.options.indent_char as char)
+            .to_string()
+            .repeat((self.depth + 1) * self.options.indent_size);
+
+        let mut content = name.to_owned();
+        for (name, value) in attributes {
+            content.push('\n');
+            content.push_str(&indent);
+            content.push_str(name);
+            content.push_str("=\"");
+            content.push_str(&self.escape(value));
+            content.push('"');
+        }
+
+        BytesStart::from_content(content, name.len())
+    }
+
+    /// Escapes a text or attribute value, including line breaks when writing single lines.
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        let escaped = escape(value);
+
+        if self.options.style == XmlOutputStyle::SingleLine && escaped.contains(['\n', '\r']) {
+            Cow::Owned(escaped.replace('\n', "&#10;").replace('\r', "&#13;"))
+        } else {
+            escaped
+        }
     }
 
-    pub fn into_writer(self) -> W {
-        self.writer.into_inner()
+    /// Returns the underlying writer, which was written to directly without buffering.
+    ///
+    /// Closes the `root_element` first, if set.
+    pub fn into_writer(mut self) -> SerializationResult<W> {
+        if let Some(root_element) = self.options.root_element.clone() {
+            self.writer.inner().reset();
+            self.open_root_element()?;
+            self.writer
+                .write_event(Event::End(BytesEnd::new(root_element.as_str())))?;
+        }
+
+        Ok(self.writer.into_inner().into_inner())
     }
 }
 
@@ -71,56 +443,134 @@ impl<W: Write> XmlOutput<W> {
 impl<W: Write> BinXmlOutput for XmlOutput<W> {
     fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
         trace!("visit_end_of_stream");
+        self.flush_pending_start()?;
         self.writer.write_event(Event::Eof)?;
 
+        if self.options.style == XmlOutputStyle::SingleLine {
+            self.writer
+                .inner()
+                .write_all(b"\n")
+                .map_err(quick_xml::Error::Io)?;
+        }
+
         Ok(())
     }
 
     fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
         trace!("visit_open_start_element: {:?}", element);
+        self.flush_pending_start()?;
 
-        let mut event_builder = BytesStart::new(element.name.as_ref().as_str());
+        let binary_byte_limit = self.options.binary_byte_limit;
+        let binary_encoding = self.options.binary_encoding;
+        let skip_namespace = self.depth == 0 && !self.options.emit_root_namespace;
+        let attributes: Vec<(&str, Cow<'_, str>)> = element
+            .attributes
+            .iter()
+            .filter(|attr| !(skip_namespace && attr.name.as_str() == "xmlns"))
+            .map(|attr| {
+                let value = match BinXmlValue::encode_binary(
+                    Cow::Borrowed(attr.value.as_ref()),
+                    binary_encoding,
+                    binary_byte_limit,
+                ) {
+                    Cow::Borrowed(value) => value.as_cow_str(),
+                    Cow::Owned(value) => Cow::Owned(value.as_cow_str().into_owned()),
+                };
+                (attr.name.as_str(), value)
+            })
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
 
-        for attr in element.attributes.iter() {
-            let value_cow: Cow<'_, str> = attr.value.as_ref().as_cow_str();
-
-            if value_cow.len() > 0 {
-                let name_as_str = attr.name.as_str();
-                let attr = Attribute::from((name_as_str, value_cow.as_ref()));
-                event_builder.push_attribute(attr);
+        let name = element.name.as_ref().as_str();
+        let event_builder = if self.options.style == XmlOutputStyle::Pretty
+            && self.options.attributes_on_separate_lines
+            && attributes.len() > self.options.attributes_per_line_threshold
+        {
+            self.start_with_attributes_on_separate_lines(name, &attributes)
+        } else {
+            let mut event_builder = BytesStart::new(name);
+            for (name, value) in attributes.iter() {
+                event_builder.push_attribute(Attribute {
+                    key: QName(name.as_bytes()),
+                    value: match self.escape(value) {
+                        Cow::Borrowed(value) => Cow::Borrowed(value.as_bytes()),
+                        Cow::Owned(value) => Cow::Owned(value.into_bytes()),
+                    },
+                });
             }
-        }
+            event_builder
+        };
 
-        self.writer.write_event(Event::Start(event_builder))?;
+        if self.options.self_closing_empty_elements {
+            self.pending_start = Some(event_builder.into_owned());
+        } else {
+            self.writer.write_event(Event::Start(event_builder))?;
+        }
+        self.depth += 1;
 
         Ok(())
     }
 
     fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
         trace!("visit_close_element");
-        let event = BytesEnd::new(element.name.as_ref().as_str());
-
-        self.writer.write_event(Event::End(event))?;
+        match self.pending_start.take() {
+            Some(start) => self.writer.write_event(Event::Empty(start))?,
+            None => {
+                let event = BytesEnd::new(element.name.as_ref().as_str());
+                self.writer.write_event(Event::End(event))?
+            }
+        }
+        self.depth = self.depth.saturating_sub(1);
 
         Ok(())
     }
 
     fn visit_characters(&mut self, value: Cow<BinXmlValue>) -> SerializationResult<()> {
         trace!("visit_chars");
+        self.flush_pending_start()?;
+        let value = BinXmlValue::encode_binary(
+            value,
+            self.options.binary_encoding,
+            self.options.binary_byte_limit,
+        );
         let cow: Cow<str> = value.as_cow_str();
-        let event = BytesText::new(&cow);
+        let event = BytesText::from_escaped(self.escape(&cow));
         self.writer.write_event(Event::Text(event))?;
 
         Ok(())
     }
 
-    fn visit_cdata_section(&mut self) -> SerializationResult<()> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_cdata_section", file!()),
-        })
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        trace!("visit_cdata_section");
+        self.flush_pending_start()?;
+        // `]]>` cannot appear inside a CDATA section, so it is split across two sections.
+        let sections: Vec<&str> = cdata.split("]]>").collect();
+        let last = sections.len() - 1;
+
+        for (i, section) in sections.into_iter().enumerate() {
+            let content = match (i == 0, i == last) {
+                (true, true) => Cow::Borrowed(section),
+                (true, false) => Cow::Owned(format!("{}]]", section)),
+                (false, true) => Cow::Owned(format!(">{}", section)),
+                (false, false) => Cow::Owned(format!(">{}]]", section)),
+            };
+
+            self.writer
+                .write_event(Event::CData(BytesCData::new(content)))?;
+        }
+
+        Ok(())
     }
 
     fn visit_entity_reference(&mut self, entity: &BinXmlName) -> Result<(), SerializationError> {
+        // Only the predefined entities can be referenced, since records do not carry a DTD.
+        if !PREDEFINED_ENTITIES.contains(&entity.as_str()) {
+            return Err(SerializationError::UnknownEntityReference {
+                name: entity.as_str().to_owned(),
+            });
+        }
+
+        self.flush_pending_start()?;
         let xml_ref = "&".to_string() + entity.as_str() + ";";
         // xml_ref is already escaped
         let event = Event::Text(BytesText::from_escaped(&xml_ref));
@@ -131,18 +581,44 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
 
     fn visit_character_reference(
         &mut self,
-        _char_ref: Cow<'_, str>,
+        char_ref: Cow<'_, str>,
     ) -> Result<(), SerializationError> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_character_reference", file!()),
-        })
+        self.flush_pending_start()?;
+        let xml_ref = "&#".to_string() + char_ref.as_ref() + ";";
+        let event = Event::Text(BytesText::from_escaped(&xml_ref));
+        self.writer.write_event(event)?;
+
+        Ok(())
     }
 
     fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
         // PITARGET - Emit the text "<?", the text (as specified by the Name rule in 2.2.12), and then the space character " ".
         // Emit the text (as specified by the NullTerminatedUnicodeString rule in 2.2.12), and then the text "?>".
-        let concat = pi.name.as_str().to_owned() + pi.data.as_ref(); // only `String` supports concatenation.
-        let event = Event::PI(BytesText::new(concat.as_str()));
+        self.flush_pending_start()?;
+        let mut content = pi.name.as_str().to_owned();
+        if !pi.data.is_empty() {
+            content.push(' ');
+            content.push_str(pi.data.as_ref());
+        }
+
+        let event = Event::PI(BytesText::from_escaped(content));
+        self.writer.write_event(event)?;
+
+        Ok(())
+    }
+
+    fn visit_comment(&mut self, comment: &str) -> SerializationResult<()> {
+        self.flush_pending_start()?;
+        // `--` cannot appear inside a comment, and it cannot end with `-`.
+        let mut content = comment.to_owned();
+        while content.contains("--") {
+            content = content.replace("--", "- -");
+        }
+        if content.ends_with('-') {
+            content.push(' ');
+        }
+
+        let event = Event::Comment(BytesText::from_escaped(content));
         self.writer.write_event(event)?;
 
         Ok(())
@@ -150,10 +626,425 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
 
     fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
         trace!("visit_start_of_stream");
-        let event = BytesDecl::new("1.0", Some("utf-8"), None);
+        self.depth = 0;
+        self.pending_start = None;
+        self.writer.inner().reset();
 
-        self.writer.write_event(Event::Decl(event))?;
+        if self.options.root_element.is_some() {
+            return self.open_root_element();
+        }
 
-        Ok(())
+        if !self.options.emit_declaration {
+            return Ok(());
+        }
+
+        self.write_declaration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BinXmlOutput, XmlOutput, XmlOutputOptions, XmlOutputStyle};
+    use crate::binxml::name::BinXmlName;
+    use crate::binxml::value_variant::{BinXmlValue, BinaryEncoding};
+    use crate::err::SerializationError;
+    use crate::model::xml::test_helpers::{element, typed_element};
+    use crate::model::xml::BinXmlPI;
+    use crate::ParserSettings;
+    use std::borrow::Cow;
+
+    fn render_cdata(cdata: &str) -> String {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(Vec::new(), &settings);
+        let data = element("Data", &[]);
+
+        output.visit_open_start_element(&data).unwrap();
+        output.visit_cdata_section(Cow::Borrowed(cdata)).unwrap();
+        output.visit_close_element(&data).unwrap();
+
+        String::from_utf8(output.into_writer().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_cdata_section() {
+        assert_eq!(
+            render_cdata("<inner/> & more"),
+            "<Data><![CDATA[<inner/> & more]]></Data>"
+        );
+    }
+
+    #[test]
+    fn test_empty_cdata_section() {
+        assert_eq!(render_cdata(""), "<Data><![CDATA[]]></Data>");
+    }
+
+    #[test]
+    fn test_cdata_section_containing_terminator() {
+        assert_eq!(
+            render_cdata("a]]>b"),
+            "<Data><![CDATA[a]]]]><![CDATA[>b]]></Data>"
+        );
+    }
+
+    #[test]
+    fn test_entity_reference() {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(Vec::new(), &settings);
+
+        output
+            .visit_entity_reference(&BinXmlName::from_str("quot"))
+            .unwrap();
+        output
+            .visit_entity_reference(&BinXmlName::from_str("lt"))
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "&quot;&lt;"
+        );
+    }
+
+    #[test]
+    fn test_unknown_entity_reference_is_an_error() {
+        let settings = ParserSettings::new();
+        let mut output = XmlOutput::with_writer(Vec::new(), &settings);
+
+        let err = output
+            .visit_entity_reference(&BinXmlName::from_str("nbsp"))
+            .unwrap_err();
+
+        assert!(
+            matches!(err, SerializationError::UnknownEntityReference { ref name } if name == "nbsp")
+        );
+        assert_eq!(err.to_string(), "Unknown XML entity reference `&nbsp;`");
+    }
+
+    #[test]
+    fn test_character_reference() {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(Vec::new(), &settings);
+
+        output
+            .visit_character_reference(Cow::Borrowed("60"))
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "&#60;"
+        );
+    }
+
+    #[test]
+    fn test_processing_instruction() {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(Vec::new(), &settings);
+
+        output
+            .visit_processing_instruction(&BinXmlPI {
+                name: Cow::Owned(BinXmlName::from_str("xml-stylesheet")),
+                data: Cow::Borrowed(r#"href="event.xsl""#),
+            })
+            .unwrap();
+        output
+            .visit_processing_instruction(&BinXmlPI {
+                name: Cow::Owned(BinXmlName::from_str("empty")),
+                data: Cow::Borrowed(""),
+            })
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            r#"<?xml-stylesheet href="event.xsl"?><?empty?>"#
+        );
+    }
+
+    #[test]
+    fn test_comment() {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(Vec::new(), &settings);
+
+        output.visit_comment(" rendered ").unwrap();
+        output.visit_comment("a -- b ---").unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<!-- rendered --><!--a - - b - - - -->"
+        );
+    }
+
+    fn render_record(options: XmlOutputOptions) -> String {
+        let mut output = XmlOutput::with_config(Vec::new(), options);
+        let event = element("Event", &[]);
+        let event_id = typed_element("EventID", &[("Qualifiers", BinXmlValue::UInt16Type(0))]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&event_id).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::UInt16Type(4624)))
+            .unwrap();
+        output.visit_close_element(&event_id).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        String::from_utf8(output.into_writer().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_default_options() {
+        assert_eq!(
+            render_record(XmlOutputOptions::default()),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Event>\n  <EventID Qualifiers=\"0\">4624</EventID>\n</Event>"
+        );
+    }
+
+    #[test]
+    fn test_custom_indentation() {
+        assert_eq!(
+            render_record(XmlOutputOptions::new().indent_char(b'\t').indent_size(1)),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Event>\n\t<EventID Qualifiers=\"0\">4624</EventID>\n</Event>"
+        );
+    }
+
+    #[test]
+    fn test_without_indentation_and_declaration() {
+        assert_eq!(
+            render_record(
+                XmlOutputOptions::new()
+                    .indent_size(0)
+                    .emit_declaration(false)
+            ),
+            "<Event><EventID Qualifiers=\"0\">4624</EventID></Event>"
+        );
+    }
+
+    #[test]
+    fn test_attributes_on_separate_lines() {
+        let mut output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::new()
+                .emit_declaration(false)
+                .attributes_on_separate_lines(true)
+                .attributes_per_line_threshold(1),
+        );
+        let event = element("Event", &[("xmlns", "urn:event")]);
+        let provider = element(
+            "Provider",
+            &[
+                ("Name", "Security"),
+                ("Guid", "{54849625}"),
+                ("Empty", ""),
+                ("Quoted", "a \"b\" & c"),
+            ],
+        );
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&provider).unwrap();
+        output.visit_close_element(&provider).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<Event xmlns=\"urn:event\">\n  \
+             <Provider\n    \
+             Name=\"Security\"\n    \
+             Guid=\"{54849625}\"\n    \
+             Quoted=\"a &quot;b&quot; &amp; c\">\n  \
+             </Provider>\n\
+             </Event>"
+        );
+    }
+
+    #[test]
+    fn test_root_element() {
+        let mut output =
+            XmlOutput::with_config(Vec::new(), XmlOutputOptions::new().root_element("Events"));
+        let event = element("Event", &[]);
+
+        for id in [1, 2] {
+            output.visit_start_of_stream().unwrap();
+            output.visit_open_start_element(&event).unwrap();
+            output
+                .visit_characters(Cow::Owned(BinXmlValue::UInt16Type(id)))
+                .unwrap();
+            output.visit_close_element(&event).unwrap();
+            output.visit_end_of_stream().unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Events>\n  <Event>1</Event>\n  <Event>2</Event>\n</Events>"
+        );
+
+        // Without any record, the root element is still written.
+        let output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::new()
+                .emit_declaration(false)
+                .root_element("Events"),
+        );
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<Events>\n</Events>"
+        );
+    }
+
+    #[test]
+    fn test_without_root_namespace() {
+        let mut output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::new()
+                .indent_size(0)
+                .emit_declaration(false)
+                .emit_root_namespace(false),
+        );
+        let event = element("Event", &[("xmlns", "urn:event"), ("Id", "1")]);
+        let inner = element("Inner", &[("xmlns", "urn:inner")]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&inner).unwrap();
+        output.visit_close_element(&inner).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<Event Id=\"1\"><Inner xmlns=\"urn:inner\"></Inner></Event>"
+        );
+    }
+
+    #[test]
+    fn test_self_closing_empty_elements() {
+        let mut output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::new()
+                .emit_declaration(false)
+                .self_closing_empty_elements(true),
+        );
+        let event = element("Event", &[]);
+        let provider = element("Provider", &[("Name", "Security")]);
+        let correlation = element("Correlation", &[]);
+        let computer = element("Computer", &[]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&provider).unwrap();
+        output.visit_close_element(&provider).unwrap();
+        output.visit_open_start_element(&correlation).unwrap();
+        output.visit_close_element(&correlation).unwrap();
+        output.visit_open_start_element(&computer).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::StringType("WIN".to_string())))
+            .unwrap();
+        output.visit_close_element(&computer).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<Event>\n  \
+             <Provider Name=\"Security\"/>\n  \
+             <Correlation/>\n  \
+             <Computer>WIN</Computer>\n\
+             </Event>"
+        );
+    }
+
+    #[test]
+    fn test_options_from_settings() {
+        assert_eq!(
+            XmlOutputOptions::from(&ParserSettings::new().indent(false)),
+            XmlOutputOptions::new().indent_size(0)
+        );
+        assert_eq!(
+            XmlOutputOptions::from(&ParserSettings::new().xml_declaration(false)),
+            XmlOutputOptions::new().emit_declaration(false)
+        );
+    }
+
+    #[test]
+    fn test_binary_byte_limit() {
+        let mut output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::from(&ParserSettings::new().binary_byte_limit(2))
+                .emit_declaration(false),
+        );
+        let bytes = [0xDE, 0xAD, 0xBE, 0xEF];
+        let data = typed_element("Binary", &[("Short", BinXmlValue::BinaryType(&bytes[..2]))]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::BinaryType(&bytes)))
+            .unwrap();
+        output.visit_close_element(&data).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<Binary Short=\"DEAD\">DEAD...</Binary>"
+        );
+    }
+
+    #[test]
+    fn test_binary_encoding() {
+        let mut output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::from(
+                &ParserSettings::new()
+                    .binary_encoding(BinaryEncoding::Base64)
+                    .binary_byte_limit(3),
+            )
+            .emit_declaration(false),
+        );
+        let bytes = [0xDE, 0xAD, 0xBE, 0xEF];
+        let data = typed_element("Binary", &[("Short", BinXmlValue::BinaryType(&bytes[..2]))]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::BinaryType(&bytes)))
+            .unwrap();
+        output.visit_close_element(&data).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<Binary Short=\"3q0=\">3q2+...</Binary>"
+        );
+    }
+
+    #[test]
+    fn test_single_line_style() {
+        let mut output = XmlOutput::with_config(
+            Vec::new(),
+            XmlOutputOptions::new()
+                .style(XmlOutputStyle::SingleLine)
+                .attributes_on_separate_lines(true)
+                .attributes_per_line_threshold(0),
+        );
+        let event = element("Event", &[]);
+        let data = element("Data", &[("Name", "a\r\nb")]);
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        output
+            .visit_characters(Cow::Owned(BinXmlValue::StringType(
+                "line 1\n<line 2>".to_string(),
+            )))
+            .unwrap();
+        output.visit_close_element(&data).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer().unwrap()).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <Event><Data Name=\"a&#13;&#10;b\">line 1&#10;&lt;line 2&gt;</Data></Event>\n"
+        );
     }
 }
@@ -91,3 +91,7 @@ pub fn sample_with_no_crc32() -> PathBuf {
 pub fn sample_with_invalid_flags_in_header() -> PathBuf {
     samples_dir().join("post-Security.evtx")
 }
+
+pub fn sample_with_unnamed_data_elements() -> PathBuf {
+    samples_dir().join("MSExchange_Management_wec.evtx")
+}
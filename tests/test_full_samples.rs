@@ -1,9 +1,11 @@
 mod fixtures;
 
-use evtx::{EvtxParser, ParserSettings};
+use evtx::{ErrorPolicy, EvtxParser, ParserSettings};
 use fixtures::*;
 use log::Level;
 use std::path::Path;
+use std::sync::Arc;
+use std::thread;
 
 /// Tests an .evtx file, asserting the number of parsed records matches `count`.
 fn test_full_sample(path: impl AsRef<Path>, ok_count: usize, err_count: usize) {
@@ -137,6 +139,58 @@ fn test_dirty_sample_binxml_with_incomplete_token() {
     test_full_sample(sample_binxml_with_incomplete_sid(), 6, 1)
 }
 
+#[test]
+fn test_dirty_sample_with_error_policies() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_binxml_with_incomplete_sid()).unwrap();
+
+    let records: Vec<_> = parser.records().collect();
+    let first_err = records.iter().position(|r| r.is_err()).unwrap();
+
+    parser = parser.with_configuration(ParserSettings::new().on_error(ErrorPolicy::Skip));
+    let skipped: Vec<_> = parser.records().collect();
+    assert_eq!(skipped.len(), 6);
+    assert!(skipped.iter().all(|r| r.is_ok()));
+
+    parser = parser.with_configuration(ParserSettings::new().on_error(ErrorPolicy::FailFast));
+    let failed_fast: Vec<_> = parser.records().collect();
+    assert_eq!(failed_fast.len(), first_err + 1);
+    assert!(failed_fast.last().unwrap().is_err());
+}
+
+#[test]
+fn test_dirty_sample_with_error_placeholders() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_checksum_2())
+        .unwrap()
+        .with_configuration(ParserSettings::new().on_error(ErrorPolicy::Placeholder));
+
+    let records: Vec<_> = parser
+        .records_json_value()
+        .map(|r| r.expect("placeholders replace errors"))
+        .collect();
+    assert_eq!(records.len(), 1776);
+
+    let placeholders: Vec<_> = records
+        .iter()
+        .filter(|r| r.data.get("Error").is_some())
+        .collect();
+    assert_eq!(placeholders.len(), 2);
+
+    // A record whose tokens failed to parse is located, an invalid record header is not.
+    let placeholder = &placeholders[0].data;
+    assert_eq!(placeholder["EventRecordID"], 463011);
+    assert_eq!(placeholder["RecordOffset"], 102040);
+    assert!(placeholders[1].data["EventRecordID"].is_null());
+    assert!(placeholder["Error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to parse record number"));
+
+    let xml: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+    assert_eq!(xml.iter().filter(|r| r.starts_with("<Error")).count(), 2);
+}
+
 #[test]
 fn test_dirty_sample_binxml_with_incomplete_template() {
     // Contains an unparsable record
@@ -180,3 +234,42 @@ fn test_sample_with_no_crc32() {
 fn test_sample_with_invalid_flags_in_header() {
     test_full_sample(sample_with_invalid_flags_in_header(), 126, 0)
 }
+
+#[test]
+fn test_chunks_parsed_on_threads_of_our_own() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(regular_sample()).unwrap();
+    let expected: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+
+    let settings = Arc::new(ParserSettings::new());
+    let chunks: Vec<_> = parser.chunks().map(|chunk| chunk.unwrap()).collect();
+
+    let records: Vec<String> = thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|mut chunk_data| {
+                let settings = Arc::clone(&settings);
+                scope.spawn(move || {
+                    assert!(chunk_data.validate_checksum());
+                    assert!(
+                        chunk_data.header.first_event_record_number
+                            <= chunk_data.header.last_event_record_number
+                    );
+
+                    let mut chunk = chunk_data.parse(settings).unwrap();
+                    chunk
+                        .records()
+                        .map(|r| r.unwrap().data)
+                        .collect::<Vec<String>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    assert_eq!(records, expected);
+}
@@ -1,8 +1,17 @@
 mod fixtures;
 use fixtures::*;
 
-use evtx::{EvtxParser, ParserSettings};
+use chrono::{TimeZone, Utc};
+use evtx::binxml::value_variant::BinXmlValue;
+use evtx::err::EvtxError;
+use evtx::{
+    render_record_to_json, render_record_to_json_into, render_record_to_xml,
+    render_record_to_xml_into, EvtxParser, EvtxRecordHeader, EvtxWriter, HashingOutput,
+    ParserSettings,
+};
 use serde_json::Value;
+use std::io::Cursor;
+use std::sync::Arc;
 
 #[test]
 fn test_event_xml_sample() {
@@ -21,6 +30,26 @@ fn test_event_xml_sample() {
     insta::assert_display_snapshot!(first_record.data);
 }
 
+#[test]
+fn test_event_xml_sample_without_declaration() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().num_threads(1).xml_declaration(false));
+
+    let records: Vec<String> = parser
+        .records()
+        .take(2)
+        .map(|record| record.expect("record to parse correctly").data)
+        .collect();
+
+    for record in records {
+        assert!(record.starts_with("<Event "), "{}", record);
+        assert!(!record.contains("<?xml"));
+    }
+}
+
 #[test]
 fn test_event_json_sample() {
     ensure_env_logger_initialized();
@@ -39,6 +68,69 @@ fn test_event_json_sample() {
     insta::assert_json_snapshot!(&value);
 }
 
+#[test]
+fn test_event_flat_json_sample() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().num_threads(1));
+
+    let mut chunk = parser
+        .chunks()
+        .next()
+        .expect("to have chunks")
+        .expect("chunk to be read");
+    let mut chunk = chunk
+        .parse(Arc::new(ParserSettings::new()))
+        .expect("chunk to parse");
+
+    let first_record = chunk
+        .iter()
+        .next()
+        .expect("to have records")
+        .expect("record to be read")
+        .into_flat_json_value()
+        .expect("record to parse correctly");
+
+    insta::assert_json_snapshot!(&first_record.data);
+}
+
+#[test]
+fn test_records_as_json_lines() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let mut chunk = parser
+        .chunks()
+        .next()
+        .expect("to have chunks")
+        .expect("chunk to be read");
+    let mut chunk = chunk
+        .parse(Arc::new(ParserSettings::new().indent(true)))
+        .expect("chunk to parse");
+
+    let mut output = Vec::new();
+    for record in chunk.iter().take(10) {
+        record
+            .expect("record to be read")
+            .write_json_line(&mut output)
+            .expect("record to be written");
+    }
+
+    let output = String::from_utf8(output).expect("output to be UTF-8");
+    assert!(output.ends_with('\n'));
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 10);
+
+    for line in lines {
+        let value: Value = serde_json::from_str(line).expect("each line to be a JSON document");
+        assert!(value["Event"].is_object());
+    }
+}
+
 #[test]
 fn test_event_json_sample_with_event_data() {
     ensure_env_logger_initialized();
@@ -128,6 +220,35 @@ fn test_event_xml_sample_with_user_data() {
     insta::assert_display_snapshot!(first_record.data);
 }
 
+#[test]
+fn test_event_json_sample_with_user_data() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!(
+        "../samples/E_Windows_system32_winevt_logs_Microsoft-Windows-CAPI2%4Operational.evtx"
+    );
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().num_threads(1));
+
+    let first_record = parser
+        .records_json_value()
+        .next()
+        .expect("to have records")
+        .expect("record to parse correctly");
+
+    let user_data = &first_record.data["Event"]["UserData"]["WinVerifyTrustStart"];
+    assert_eq!(
+        user_data["EventAuxInfo"]["#attributes"]["ProcessName"],
+        "Setup.exe"
+    );
+    assert_eq!(
+        user_data["CorrelationAuxInfo"]["#attributes"]["SeqNumber"],
+        "1"
+    );
+
+    insta::assert_json_snapshot!(&first_record.data);
+}
+
 #[test]
 fn test_event_xml_sample_with_entity_ref() {
     ensure_env_logger_initialized();
@@ -166,6 +287,27 @@ fn test_event_xml_sample_with_entity_ref_2() {
     insta::assert_display_snapshot!(record.data);
 }
 
+#[test]
+fn test_event_xml_sample_with_entity_ref_between_elements() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!(
+        "../samples/E_Windows_system32_winevt_logs_Microsoft-Windows-CAPI2%4Operational.evtx"
+    );
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().num_threads(1).indent(false));
+
+    let record = parser
+        .records()
+        .find(|record| matches!(record, Ok(r) if r.event_record_id == 25))
+        .expect("record to exist")
+        .expect("record to parse correctly");
+
+    assert!(record
+        .data
+        .contains("</URLCacheFlushInfo>&quot;&quot;<URLCacheResponseInfo"));
+}
+
 #[test]
 fn test_event_json_with_size_t() {
     ensure_env_logger_initialized();
@@ -297,3 +439,511 @@ fn test_event_json_multiple_empty_data_nodes_not_ignored() {
     let value: Value = serde_json::from_str(&record.data).expect("to parse correctly");
     insta::assert_json_snapshot!(&value);
 }
+
+#[test]
+fn test_event_json_unnamed_data_elements_are_positional() {
+    ensure_env_logger_initialized();
+
+    for separate_json_attributes in [false, true] {
+        let settings = ParserSettings::new()
+            .num_threads(1)
+            .separate_json_attributes(separate_json_attributes);
+
+        let xml_records: Vec<_> = EvtxParser::from_path(sample_with_unnamed_data_elements())
+            .unwrap()
+            .with_configuration(settings.clone())
+            .records()
+            .map(|record| record.expect("record to parse correctly"))
+            .collect();
+
+        let json_records: Vec<_> = EvtxParser::from_path(sample_with_unnamed_data_elements())
+            .unwrap()
+            .with_configuration(settings)
+            .records_json_value()
+            .map(|record| record.expect("record to parse correctly"))
+            .collect();
+
+        assert!(!json_records.is_empty());
+        assert_eq!(xml_records.len(), json_records.len());
+
+        for (xml_record, json_record) in xml_records.iter().zip(json_records.iter()) {
+            let data = json_record.data["Event"]["EventData"]["Data"]
+                .as_array()
+                .expect("unnamed data nodes to be collected into an array");
+
+            let data_nodes = xml_record.data.matches("<Data>").count()
+                + xml_record.data.matches("<Data/>").count();
+
+            assert_eq!(data.len(), data_nodes);
+        }
+
+        assert_eq!(
+            json_records[0].data["Event"]["EventData"]["Data"][0],
+            "Set-Mailbox"
+        );
+        assert_eq!(
+            json_records[0].data["Event"]["EventData"]["Data"][7],
+            Value::Null
+        );
+    }
+}
+
+#[test]
+fn test_event_system_fields() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(regular_sample()).unwrap();
+    let mut chunk = parser
+        .chunks()
+        .next()
+        .expect("to have chunks")
+        .expect("chunk to be read correctly");
+
+    // Custom JSON keys should not affect reading the system fields.
+    let custom_keys = ParserSettings::new().attribute_key("@").text_key("$");
+    let mut first_fields = vec![];
+
+    for settings in [ParserSettings::new(), custom_keys] {
+        let mut chunk = chunk
+            .parse(Arc::new(settings))
+            .expect("chunk to parse correctly");
+
+        first_fields.push(
+            chunk
+                .iter()
+                .next()
+                .expect("to have records")
+                .expect("record to parse correctly")
+                .into_system_fields()
+                .expect("system fields to be read"),
+        );
+    }
+
+    assert_eq!(first_fields[0], first_fields[1]);
+    let fields = &first_fields[0];
+
+    assert_eq!(fields.event_id, 4608);
+    assert_eq!(fields.event_record_id, Some(1));
+    assert_eq!(fields.provider, "Microsoft-Windows-Security-Auditing");
+    assert_eq!(fields.computer, "37L4247F27-25");
+    assert_eq!(fields.channel.as_deref(), Some("Security"));
+    assert_eq!(fields.process_id, Some(456));
+    assert_eq!(
+        fields.time_created.to_rfc3339(),
+        "2016-07-08T18:12:51.681640+00:00"
+    );
+}
+
+#[test]
+fn test_system_fields_are_read_without_rendering() {
+    ensure_env_logger_initialized();
+
+    for sample in [regular_sample(), sample_with_irregular_values()] {
+        let mut parser = EvtxParser::from_path(sample).unwrap();
+        let settings = Arc::new(ParserSettings::new());
+        let mut records = 0;
+
+        for chunk in parser.chunks() {
+            let mut chunk = chunk.unwrap();
+            let mut chunk = chunk.parse(Arc::clone(&settings)).unwrap();
+
+            for record in chunk.iter() {
+                let record = record.unwrap();
+                let scanned = record.system_fields();
+                let rendered = record.into_system_fields();
+
+                records += usize::from(rendered.is_ok());
+                assert_eq!(scanned.ok(), rendered.ok());
+            }
+        }
+
+        assert!(records > 0);
+    }
+}
+
+#[test]
+fn test_event_content_hash() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(regular_sample()).unwrap();
+    let mut chunk = parser.chunks().next().unwrap().unwrap();
+
+    let mut hash_records = |settings: ParserSettings| -> Vec<[u8; 32]> {
+        let mut chunk = chunk.parse(Arc::new(settings)).unwrap();
+        chunk
+            .iter()
+            .take(3)
+            .map(|record| {
+                let mut output = HashingOutput::new();
+                record.unwrap().into_output(&mut output).unwrap();
+                output.into_writer()
+            })
+            .collect()
+    };
+
+    let indented = hash_records(ParserSettings::new().indent(true));
+    let compact = hash_records(ParserSettings::new().indent(false));
+
+    assert_eq!(indented, compact);
+    assert_ne!(indented[0], indented[1]);
+}
+
+#[test]
+fn test_render_record_to_string() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(regular_sample()).unwrap();
+    let mut chunk = parser.chunks().next().unwrap().unwrap();
+    let settings = Arc::new(ParserSettings::new().num_threads(1));
+
+    let expected_xml = parser.records().next().unwrap().unwrap().data;
+    let expected_json = parser.records_json().next().unwrap().unwrap().data;
+
+    let mut chunk = chunk.parse(settings).unwrap();
+    let record = chunk.iter().next().unwrap().unwrap();
+
+    assert_eq!(render_record_to_xml(record.clone()).unwrap(), expected_xml);
+    assert_eq!(render_record_to_json(record).unwrap(), expected_json);
+}
+
+#[test]
+fn test_render_records_into_a_reused_buffer() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(regular_sample()).unwrap();
+    let mut chunk = parser.chunks().next().unwrap().unwrap();
+    let settings = Arc::new(ParserSettings::new().num_threads(1));
+
+    let expected_xml: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+    let expected_json: Vec<_> = parser.records_json().map(|r| r.unwrap().data).collect();
+
+    let mut chunk = chunk.parse(settings).unwrap();
+    let mut buf = Vec::new();
+
+    for (i, record) in chunk.iter().enumerate() {
+        let record = record.unwrap();
+
+        render_record_to_xml_into(record.clone(), &mut buf).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), expected_xml[i]);
+
+        render_record_to_json_into(record, &mut buf).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), expected_json[i]);
+    }
+}
+
+#[test]
+fn test_substitution_values_are_in_slot_order() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+    let mut chunk = parser.chunks().next().unwrap().unwrap();
+    let mut chunk = chunk.parse(Arc::new(ParserSettings::new())).unwrap();
+
+    let record = chunk.iter().nth(1).unwrap().unwrap();
+    let values = record.substitution_values();
+
+    assert_eq!(values[3], BinXmlValue::UInt16Type(4624));
+
+    // `EventData` is a nested fragment, with its own template.
+    let event_data_values: Vec<String> = values
+        .iter()
+        .find_map(|value| value.substitution_values())
+        .unwrap()
+        .iter()
+        .map(|value| value.as_cow_str().into_owned())
+        .collect();
+
+    let event_data = record.into_event_data_json_value().unwrap().data;
+    assert_eq!(event_data_values[5], "SYSTEM");
+    assert_eq!(event_data_values[6], "NT AUTHORITY");
+    assert_eq!(
+        event_data_values.len(),
+        event_data.as_object().unwrap().len()
+    );
+}
+
+#[test]
+fn test_evtx_writer_round_trip() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let settings = ParserSettings::new().num_threads(1);
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(settings.clone());
+
+    let records: Vec<_> = parser.records().map(|record| record.unwrap()).collect();
+
+    let mut writer = EvtxWriter::new(Cursor::new(Vec::new())).unwrap();
+    for record in &records {
+        let record_id = writer.write_xml_record(&record.data).unwrap();
+        assert_eq!(record_id, record.event_record_id);
+    }
+    let reencoded = writer.finish().unwrap().into_inner();
+
+    let mut parser = EvtxParser::from_buffer(reencoded)
+        .unwrap()
+        .with_configuration(settings.validate_checksums(true));
+    let roundtrip: Vec<_> = parser.records().map(|record| record.unwrap()).collect();
+
+    assert!(parser.chunks().count() > 1);
+    assert_eq!(roundtrip.len(), records.len());
+    for (original, reencoded) in records.iter().zip(roundtrip.iter()) {
+        assert_eq!(original.event_record_id, reencoded.event_record_id);
+        assert_eq!(original.timestamp, reencoded.timestamp);
+        assert_eq!(original.data, reencoded.data);
+    }
+}
+
+#[test]
+fn test_recover_records_from_slack_space() {
+    ensure_env_logger_initialized();
+    let mut writer = EvtxWriter::new(Cursor::new(Vec::new())).unwrap();
+    for record_id in 1..=3 {
+        writer
+            .write_xml_record(&format!(
+                "<Event><System><EventRecordID>{}</EventRecordID></System></Event>",
+                record_id
+            ))
+            .unwrap();
+    }
+    let mut file = writer.finish().unwrap().into_inner();
+
+    // Shrink the chunk to its first record, as if the others had been deleted.
+    let chunk = 4096;
+    let first_record_size =
+        u32::from_le_bytes(file[chunk + 0x204..chunk + 0x208].try_into().unwrap());
+    let free_space_offset = 0x200 + first_record_size;
+    file[chunk + 0x10..chunk + 0x18].copy_from_slice(&1_u64.to_le_bytes());
+    file[chunk + 0x20..chunk + 0x28].copy_from_slice(&1_u64.to_le_bytes());
+    file[chunk + 0x2C..chunk + 0x30].copy_from_slice(&0x200_u32.to_le_bytes());
+    file[chunk + 0x30..chunk + 0x34].copy_from_slice(&free_space_offset.to_le_bytes());
+
+    let mut parser = EvtxParser::from_buffer(file.clone()).unwrap();
+    let records: Vec<_> = parser.records().map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 1);
+    assert!(!records[0].recovered);
+
+    let mut parser = EvtxParser::from_buffer(file)
+        .unwrap()
+        .with_configuration(ParserSettings::new().recover_records(true));
+    let records: Vec<(u64, bool)> = parser
+        .records()
+        .map(|r| r.unwrap())
+        .map(|r| (r.event_record_id, r.recovered))
+        .collect();
+
+    assert_eq!(records, vec![(1, false), (2, true), (3, true)]);
+}
+
+#[test]
+fn test_record_header_fields() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(
+            ParserSettings::new()
+                .num_threads(1)
+                .record_header_fields(true),
+        );
+
+    let record = parser.records_json_value().next().unwrap().unwrap();
+    let keys: Vec<&String> = record.data.as_object().unwrap().keys().collect();
+
+    assert_eq!(keys[..2], ["_record_id", "_timestamp"]);
+    assert_eq!(record.data["_record_id"], record.event_record_id);
+    assert_eq!(
+        record.data["_timestamp"],
+        record
+            .timestamp
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+    );
+    assert!(record.data["Event"].is_object());
+
+    parser = parser.with_configuration(ParserSettings::new().num_threads(1));
+    let record = parser.records_json_value().next().unwrap().unwrap();
+    assert!(record.data.get("_record_id").is_none());
+}
+
+#[test]
+fn test_chunk_templates() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let mut chunk = parser
+        .chunks()
+        .next()
+        .expect("to have chunks")
+        .expect("chunk to be read");
+    let chunk = chunk
+        .parse(Arc::new(ParserSettings::new()))
+        .expect("chunk to parse");
+
+    let templates = chunk.templates().expect("templates to be described");
+    assert_eq!(templates.len(), chunk.template_table.len());
+    assert!(templates.windows(2).all(|w| w[0].offset < w[1].offset));
+
+    assert!(templates
+        .iter()
+        .all(|template| template.elements[0].depth == 0));
+
+    // Some templates only hold the `EventData` of an event, and are nested in other templates.
+    let event = templates
+        .iter()
+        .find(|template| template.elements[0].name.as_str() == "Event")
+        .expect("an event template");
+    let system = event
+        .elements
+        .iter()
+        .find(|element| element.name.as_str() == "System")
+        .expect("a system element");
+    assert_eq!(system.depth, 1);
+}
+
+#[test]
+fn test_get_record_by_id() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let records: Vec<_> = parser.records().map(|record| record.unwrap()).collect();
+    let expected = &records[records.len() / 2];
+
+    let record = parser
+        .get_record(expected.event_record_id)
+        .expect("record to be read")
+        .expect("record to be found");
+    assert_eq!(record.event_record_id, expected.event_record_id);
+    assert_eq!(record.data, expected.data);
+
+    let last_id = records.iter().map(|record| record.event_record_id).max();
+    assert!(parser.get_record(last_id.unwrap() + 1).unwrap().is_none());
+}
+
+#[test]
+fn test_get_record_after_a_corrupt_record() {
+    ensure_env_logger_initialized();
+    let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+    let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+    let records: Vec<_> = parser.records().map(|record| record.unwrap()).collect();
+    let (first, second) = (&records[0], &records[1]);
+
+    // The BinXml of the first record of the first chunk starts with an invalid token.
+    let record_offset = 4096 + 512;
+    evtx_file[record_offset + 24] = 0xff;
+
+    let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+    let record = parser
+        .get_record(second.event_record_id)
+        .expect("record to be read")
+        .expect("record to be found");
+    assert_eq!(record.data, second.data);
+
+    // The corrupt record itself is still an error.
+    assert!(matches!(
+        parser.get_record(first.event_record_id),
+        Err(EvtxError::FailedToParseRecord { record_id, .. }) if record_id == first.event_record_id
+    ));
+}
+
+#[test]
+fn test_records_with_raw() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let records: Vec<_> = parser.records().map(|record| record.unwrap()).collect();
+    let records_with_raw: Vec<_> = parser
+        .records_with_raw()
+        .map(|record| record.unwrap())
+        .collect();
+
+    assert_eq!(records.len(), records_with_raw.len());
+    for (record, (binxml, record_with_raw)) in records.iter().zip(records_with_raw.iter()) {
+        assert_eq!(record.data, record_with_raw.data);
+        // Every record starts with a fragment header, followed by a template instance.
+        assert_eq!(&binxml[..2], &[0x0f, 0x01]);
+    }
+}
+
+#[test]
+fn test_truncated_record_is_an_error() {
+    ensure_env_logger_initialized();
+    let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+    // The first record of the first chunk claims to be larger than the chunk.
+    let record_offset = 4096 + 512;
+    evtx_file[record_offset + 4..record_offset + 8].copy_from_slice(&0x20000_u32.to_le_bytes());
+
+    let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+    let first = parser.records().next().expect("to have records");
+
+    match first {
+        Err(EvtxError::TruncatedRecord {
+            file_offset,
+            data_size,
+            ..
+        }) => {
+            assert_eq!(file_offset, record_offset as u64);
+            assert_eq!(data_size, 0x20000);
+        }
+        other => panic!("Expected a truncated record, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_undersized_record_is_an_error() {
+    ensure_env_logger_initialized();
+    let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+    // The first record of the first chunk claims to be smaller than its own header.
+    let record_offset = 4096 + 512;
+    evtx_file[record_offset + 4..record_offset + 8].copy_from_slice(&10_u32.to_le_bytes());
+
+    let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+    let first = parser.records().next().expect("to have records");
+
+    match first {
+        Err(EvtxError::InvalidRecordSize {
+            file_offset,
+            data_size,
+            ..
+        }) => {
+            assert_eq!(file_offset, record_offset as u64);
+            assert_eq!(data_size, 10);
+        }
+        other => panic!("Expected an invalid record size, got {:?}", other),
+    }
+
+    // The header itself can still be read, without its data size underflowing.
+    let header =
+        EvtxRecordHeader::from_reader(&mut Cursor::new(&evtx_file[record_offset..])).unwrap();
+    assert_eq!(header.record_data_size(), 0);
+}
+
+#[test]
+fn test_zero_size_record_outside_of_time_range_is_an_error() {
+    ensure_env_logger_initialized();
+    let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+    // The first record of the first chunk claims to be empty, skipping it would never advance.
+    let record_offset = 4096 + 512;
+    evtx_file[record_offset + 4..record_offset + 8].copy_from_slice(&0_u32.to_le_bytes());
+
+    let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+    parser.set_time_range(
+        Some(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap()),
+        None,
+    );
+    let mut records = parser.records();
+
+    match records.next().expect("to have records") {
+        Err(EvtxError::InvalidRecordSize {
+            file_offset,
+            data_size,
+            ..
+        }) => {
+            assert_eq!(file_offset, record_offset as u64);
+            assert_eq!(data_size, 0);
+        }
+        other => panic!("Expected an invalid record size, got {:?}", other),
+    }
+}